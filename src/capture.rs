@@ -0,0 +1,388 @@
+use std::{fs, io, path::Path};
+
+use convert_case::Casing;
+use regex::Regex;
+
+use crate::{
+    Config, Error, IfExists, Rule, Value, tera_filters::CASE_FILTERS, walk, write_rule_content,
+};
+
+/// Directories that belong to tooling rather than to the project.
+const SKIPPED_DIRS: [&str; 4] = [".git", ".hg", ".svn", ".genify"];
+
+/// A config reverse-engineered from a project, and the files it leaves out.
+#[derive(Debug, Clone)]
+pub struct CaptureOutput {
+    pub config: Config,
+    /// Files that could not be captured, such as binary files, with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Builds a config that recreates the project at `root`: a `write` rule per
+/// text file, a `chmod` rule per executable and a `mkdir` rule per empty
+/// directory, leaving out what `.gitignore` files, git's excludes and
+/// `.genifyignore` files ignore. Every occurrence of a var's value in paths and contents, and of
+/// its case variants, is replaced by the prop rendered through the matching
+/// case filter, and each var becomes a prop defaulting to its value.
+pub fn capture(root: &Path, vars: &[(String, String)]) -> Result<CaptureOutput, Error> {
+    let templater = Templater::new(vars)?;
+    let mut output = CaptureOutput {
+        config: Config {
+            meta: None,
+            props: vars
+                .iter()
+                .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+                .collect(),
+            rules: Vec::new(),
+            hooks: Vec::new(),
+            formatters: Vec::new(),
+        },
+        skipped: Vec::new(),
+    };
+    let relative = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    for entry in walk::walker(root, &SKIPPED_DIRS).build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => match err.io_error() {
+                Some(source) => {
+                    return Err(Error::IOError(io::Error::new(
+                        source.kind(),
+                        err.to_string(),
+                    )));
+                }
+                // A malformed ignore file only loses its own patterns.
+                None => continue,
+            },
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+        let path = entry.path();
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let empty = fs::read_dir(path).map_err(Error::IOError)?.next().is_none();
+            if empty {
+                output.config.rules.push(Rule::Mkdir {
+                    path: templater.render(&relative(path)),
+                });
+            }
+            continue;
+        }
+        if !file_type.is_file() {
+            output
+                .skipped
+                .push((relative(path), "not a regular file".to_string()));
+            continue;
+        }
+        let Ok(content) = String::from_utf8(fs::read(path).map_err(Error::IOError)?) else {
+            output
+                .skipped
+                .push((relative(path), "not UTF-8 text".to_string()));
+            continue;
+        };
+        let target = templater.render(&relative(path));
+        output.config.rules.push(Rule::Write {
+            path: target.clone(),
+            content: templater.render(&content),
+            if_exists: IfExists::Error,
+            // Most files end in a single newline, which plain writes add.
+            exact: write_rule_content(&content, false) != content,
+        });
+        if let Some(mode) = executable_mode(path)? {
+            output.config.rules.push(Rule::Chmod { path: target, mode });
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(unix)]
+fn executable_mode(path: &Path) -> Result<Option<String>, Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)
+        .map_err(Error::IOError)?
+        .permissions()
+        .mode()
+        & 0o777;
+    Ok((mode & 0o111 != 0).then(|| format!("{mode:o}")))
+}
+
+#[cfg(not(unix))]
+fn executable_mode(_path: &Path) -> Result<Option<String>, Error> {
+    Ok(None)
+}
+
+/// Turns literal text into a template that renders back to it.
+struct Templater {
+    /// Matches any variant, longest first so `AcmeCorp` wins over `Acme`.
+    pattern: Option<Regex>,
+    /// Each variant and the expression that renders it, longest first.
+    expressions: Vec<(String, String)>,
+}
+
+impl Templater {
+    fn new(vars: &[(String, String)]) -> Result<Self, Error> {
+        let identifier = Regex::new("^[A-Za-z_][A-Za-z0-9_]*$").expect("regex is valid");
+        let mut expressions: Vec<(String, String)> = Vec::new();
+        for (name, value) in vars {
+            if !identifier.is_match(name) {
+                return Err(Error::Operation(format!(
+                    "var name `{name}` is not a valid prop name"
+                )));
+            }
+            if value.is_empty() {
+                return Err(Error::Operation(format!("var `{name}` has an empty value")));
+            }
+            let variants = std::iter::once((value.clone(), format!("{{{{ {name} }}}}"))).chain(
+                CASE_FILTERS.iter().map(|(filter, case)| {
+                    (value.to_case(*case), format!("{{{{ {name} | {filter} }}}}"))
+                }),
+            );
+            for (variant, expression) in variants {
+                if !variant.is_empty() && !expressions.iter().any(|(seen, _)| *seen == variant) {
+                    expressions.push((variant, expression));
+                }
+            }
+        }
+
+        expressions.sort_by_key(|(variant, _)| std::cmp::Reverse(variant.len()));
+        let pattern = (!expressions.is_empty()).then(|| {
+            let alternation = expressions
+                .iter()
+                .map(|(variant, _)| regex::escape(variant))
+                .collect::<Vec<_>>()
+                .join("|");
+            Regex::new(&alternation).expect("escaped alternation is valid")
+        });
+        Ok(Self {
+            pattern,
+            expressions,
+        })
+    }
+
+    fn render(&self, text: &str) -> String {
+        let mut template = String::new();
+        let mut literal_start = 0;
+        let mut search_start = 0;
+        while let Some(found) = self
+            .pattern
+            .as_ref()
+            .and_then(|pattern| pattern.find_at(text, search_start))
+        {
+            // The longest variant may run into a word where a shorter one
+            // starting at the same place does not.
+            let start = found.start();
+            let whole_word = self.expressions.iter().find(|(variant, _)| {
+                text[start..].starts_with(variant.as_str())
+                    && on_word_boundaries(text, start, start + variant.len())
+            });
+            let Some((variant, expression)) = whole_word else {
+                search_start = start + text[start..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+            push_literal(&mut template, &text[literal_start..start]);
+            template.push_str(expression);
+            literal_start = start + variant.len();
+            search_start = literal_start;
+        }
+        push_literal(&mut template, &text[literal_start..]);
+        template
+    }
+}
+
+/// Whether `text[start..end]` is a whole word, so `app` is not taken from
+/// `application`. Case changes count as boundaries, as in `AcmeCorpClient`,
+/// and so do the separators of every case variant.
+fn on_word_boundaries(text: &str, start: usize, end: usize) -> bool {
+    let found = &text[start..end];
+    let before = text[..start].chars().next_back();
+    let after = text[end..].chars().next();
+    let (Some(first), Some(last)) = (found.chars().next(), found.chars().next_back()) else {
+        return true;
+    };
+    let starts_word = match before {
+        Some(before) if before.is_alphanumeric() && first.is_alphanumeric() => {
+            first.is_uppercase() && !before.is_uppercase()
+        }
+        _ => true,
+    };
+    let ends_word = match after {
+        Some(after) if after.is_alphanumeric() && last.is_alphanumeric() => {
+            after.is_uppercase() && !last.is_uppercase()
+        }
+        _ => true,
+    };
+    starts_word && ends_word
+}
+
+/// Appends text Tera must not interpret, wrapped in a raw block when it
+/// contains template syntax or could run into the next expression.
+fn push_literal(template: &mut String, literal: &str) {
+    if ["{{", "{%", "{#"].iter().any(|tag| literal.contains(tag)) || literal.ends_with('{') {
+        template.push_str("{% raw %}");
+        template.push_str(literal);
+        template.push_str("{% endraw %}");
+    } else {
+        template.push_str(literal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_root(name: &str) -> std::path::PathBuf {
+        let suffix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after unix epoch")
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("genify-{name}-{suffix}"));
+        fs::create_dir_all(&path).expect("temp root should be created");
+        path
+    }
+
+    #[test]
+    fn replaces_values_and_case_variants() {
+        let templater = Templater::new(&[("name".to_string(), "acme corp".to_string())])
+            .expect("vars should be valid");
+
+        assert_eq!(
+            templater.render("AcmeCorp acme-corp ACME-CORP acme corp acme_corp"),
+            "{{ name | pascal_case }} {{ name | kebab_case }} {{ name | cobol_case }} \
+             {{ name }} {{ name | snake_case }}"
+        );
+        assert_eq!(
+            templater.render("{{ keep }} {acme_corp}"),
+            "{% raw %}{{ keep }} {{% endraw %}{{ name | snake_case }}}"
+        );
+        assert!(Templater::new(&[("bad-name".to_string(), "x".to_string())]).is_err());
+    }
+
+    #[test]
+    fn replaces_whole_words_only() {
+        let templater = Templater::new(&[("app".to_string(), "app".to_string())])
+            .expect("vars should be valid");
+
+        assert_eq!(
+            templater.render("application app_config AppConfig myApp apps APP"),
+            "application {{ app }}_config {{ app | pascal_case }}Config \
+             my{{ app | pascal_case }} apps {{ app | cobol_case }}"
+        );
+    }
+
+    #[test]
+    fn captured_files_keep_their_exact_endings() {
+        let source = temp_root("capture-endings");
+        let files = [
+            ("newline.txt", "one\n"),
+            ("none.txt", "two"),
+            ("blank.txt", "three\n\n"),
+            ("spaces.txt", "four\n  \n"),
+        ];
+        for (name, content) in files {
+            fs::write(source.join(name), content).expect("test file should be written");
+        }
+
+        let output = capture(&source, &[]).expect("project should be captured");
+        let exact = output
+            .config
+            .rules
+            .iter()
+            .filter(|rule| matches!(rule, Rule::Write { exact: true, .. }))
+            .count();
+        assert_eq!(exact, 3);
+
+        let target = temp_root("capture-endings-target");
+        crate::generate(&target, &output.config, None).expect("captured config should generate");
+        for (name, content) in files {
+            assert_eq!(
+                fs::read_to_string(target.join(name)).expect("file should exist"),
+                content,
+                "{name}"
+            );
+        }
+        let _ = fs::remove_dir_all(source);
+        let _ = fs::remove_dir_all(target);
+    }
+
+    #[test]
+    fn captured_config_regenerates_the_project() {
+        let source = temp_root("capture-source");
+        fs::create_dir_all(source.join("acme_corp/empty")).expect("test dir should be created");
+        fs::create_dir_all(source.join(".git")).expect("test dir should be created");
+        fs::write(source.join(".git/HEAD"), "ref").expect("test file should be written");
+        fs::write(
+            source.join("acme_corp/main.py"),
+            "print('AcmeCorp {{ not a template }}')\n",
+        )
+        .expect("test file should be written");
+        fs::write(source.join("logo.bin"), [0xff, 0xfe]).expect("test file should be written");
+
+        let output = capture(&source, &[("name".to_string(), "acme-corp".to_string())])
+            .expect("project should be captured");
+        assert_eq!(
+            output.skipped,
+            vec![("logo.bin".to_string(), "not UTF-8 text".to_string())]
+        );
+        assert_eq!(
+            output
+                .config
+                .rules
+                .iter()
+                .map(Rule::type_name)
+                .collect::<Vec<_>>(),
+            vec!["mkdir", "write"]
+        );
+
+        let target = temp_root("capture-target");
+        let mut config = output.config;
+        config.props = vec![("name".to_string(), Value::String("big-shop".to_string()))];
+        crate::generate(&target, &config, None).expect("captured config should generate");
+        assert_eq!(
+            fs::read_to_string(target.join("big_shop/main.py")).expect("file should exist"),
+            "print('BigShop {{ not a template }}')\n"
+        );
+        assert!(target.join("big_shop/empty").is_dir());
+        assert!(!target.join(".git").exists());
+        let _ = fs::remove_dir_all(source);
+        let _ = fs::remove_dir_all(target);
+    }
+
+    #[test]
+    fn leaves_out_ignored_paths() {
+        let source = temp_root("capture-ignored");
+        fs::create_dir_all(source.join(".git")).expect("test dir should be created");
+        fs::create_dir_all(source.join("target/debug")).expect("test dir should be created");
+        fs::create_dir_all(source.join("vendor")).expect("test dir should be created");
+        fs::write(source.join(".gitignore"), "target/\n*.log\n")
+            .expect("test file should be written");
+        fs::write(source.join(".genifyignore"), "vendor/\n").expect("test file should be written");
+        fs::write(source.join("target/debug/app"), "built").expect("test file should be written");
+        fs::write(source.join("vendor/lib.rs"), "vendored").expect("test file should be written");
+        fs::write(source.join("debug.log"), "log").expect("test file should be written");
+        fs::write(source.join("main.rs"), "fn main() {}\n").expect("test file should be written");
+
+        let output = capture(&source, &[]).expect("project should be captured");
+        let paths = output
+            .config
+            .rules
+            .iter()
+            .map(|rule| match rule {
+                Rule::Write { path, .. } => path.as_str(),
+                _ => panic!("only write rules expected, got {}", rule.type_name()),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![".genifyignore", ".gitignore", "main.rs"]);
+        let _ = fs::remove_dir_all(source);
+    }
+}
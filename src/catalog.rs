@@ -0,0 +1,36 @@
+use serde::Deserialize;
+
+/// Index file that names and describes the configs of a catalog directory.
+pub const CATALOG_FILE: &str = "genify-catalog.toml";
+
+/// A catalog index: configs that can be picked by name instead of by path.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    #[serde(default)]
+    pub templates: Vec<CatalogEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogEntry {
+    pub name: String,
+    /// Config path relative to the catalog directory, or an http(s) or git+ URL.
+    pub path: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl CatalogEntry {
+    pub fn is_remote(&self) -> bool {
+        ["http://", "https://", "git+"]
+            .iter()
+            .any(|scheme| self.path.starts_with(scheme))
+    }
+}
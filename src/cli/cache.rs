@@ -0,0 +1,24 @@
+use std::{env, path::PathBuf};
+
+use genify::manifest::content_hash;
+
+/// Where genify keeps fetched configs: `$GENIFY_CACHE_DIR`, or a `genify`
+/// directory in the user's cache directory.
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("GENIFY_CACHE_DIR") {
+        return dir.into();
+    }
+    let user_cache = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+    match user_cache {
+        Some(dir) => dir.join("genify"),
+        None => env::temp_dir().join("genify-cache"),
+    }
+}
+
+/// A short file name for `key`, such as a URL, that is safe on every platform.
+pub fn key_name(key: &str) -> String {
+    content_hash(key.as_bytes())["sha256:".len()..][..16].to_string()
+}
@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use clap::{ValueEnum, error::ErrorKind};
+use genify::{
+    Config,
+    location::{ConfigLocation, SourceMap},
+};
+
+use crate::cli::report;
+
+/// Serialization formats a config can be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Picks the format from a file extension.
+    pub fn detect(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+        }
+    }
+
+    /// Maps where the rules and props of `raw` are, as far as the format allows.
+    pub fn source_map(self, file: &str, raw: &str) -> SourceMap {
+        match self {
+            Self::Toml => SourceMap::toml(file, raw),
+            Self::Json => SourceMap::json(file, raw),
+            Self::Yaml => SourceMap::unmapped(file, raw),
+        }
+    }
+
+    /// Parses the text of `source`; errors point at the offending line.
+    pub fn parse(self, source: &SourceMap) -> Result<Config, clap::Error> {
+        let at = |line: usize, column: usize| ConfigLocation {
+            file: Some(source.file().to_string()),
+            line: Some(line),
+            column: Some(column),
+            ..ConfigLocation::default()
+        };
+        let raw = source.text();
+        let parsed = match self {
+            Self::Toml => toml::from_str(raw).map_err(|err| {
+                let location = err.span().map(|span| source.locate_span(span));
+                (err.message().to_string(), location)
+            }),
+            Self::Json => serde_json::from_str(raw).map_err(|err| {
+                let location = (err.line() > 0).then(|| at(err.line(), err.column()));
+                (err.to_string(), location)
+            }),
+            Self::Yaml => serde_yaml_ng::from_str(raw).map_err(|err| {
+                let location = err
+                    .location()
+                    .map(|location| at(location.line(), location.column()));
+                (err.to_string(), location)
+            }),
+        };
+        parsed.map_err(|(message, location)| {
+            let pointer = location
+                .map(|location| report::pointer(&location, Some(source)))
+                .unwrap_or_default();
+            clap::Error::raw(
+                ErrorKind::ValueValidation,
+                format!("Failed to parse {}: {message}{pointer}", self.name()),
+            )
+        })
+    }
+
+    pub fn render(self, config: &Config) -> Result<String, clap::Error> {
+        let rendered = match self {
+            Self::Toml => config.to_toml().map_err(|err| err.to_string()),
+            Self::Json => serde_json::to_string_pretty(config)
+                .map(|json| json + "\n")
+                .map_err(|err| err.to_string()),
+            Self::Yaml => serde_yaml_ng::to_string(config).map_err(|err| err.to_string()),
+        };
+        rendered.map_err(|err| {
+            clap::Error::raw(
+                ErrorKind::InvalidValue,
+                format!("Failed to write {}: {err}", self.name()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genify::Value;
+
+    const CONFIG: &str = r##"[props]
+name = "demo"
+released = 2024-05-01T10:00:00Z
+ports = [80, 443]
+
+[props.db]
+host = "localhost"
+
+[[rules]]
+type = "write"
+path = "README.md"
+content = "# {{ name }}"
+if_exists = "skip"
+"##;
+
+    #[test]
+    fn round_trips_configs_between_formats() {
+        let config = ConfigFormat::Toml
+            .parse(&SourceMap::unmapped("config.toml", CONFIG))
+            .expect("TOML should parse");
+
+        for format in [ConfigFormat::Json, ConfigFormat::Yaml, ConfigFormat::Toml] {
+            let rendered = format.render(&config).expect("config should render");
+            let parsed = format
+                .parse(&SourceMap::unmapped("config", rendered.as_str()))
+                .expect("rendered config should parse");
+
+            assert_eq!(parsed.props, config.props, "{rendered}");
+            assert_eq!(
+                serde_json::to_value(&parsed.rules).expect("rules should serialize"),
+                serde_json::to_value(&config.rules).expect("rules should serialize")
+            );
+        }
+        assert_eq!(
+            config
+                .props
+                .iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["name", "released", "ports", "db"]
+        );
+        assert!(matches!(config.props[1].1, Value::Datetime(_)));
+        assert!(
+            ConfigFormat::Toml
+                .render(&config)
+                .expect("config should render")
+                .contains("released = 2024-05-01T10:00:00Z")
+        );
+        let json: serde_json::Value = serde_json::from_str(
+            &ConfigFormat::Json
+                .render(&config)
+                .expect("config should render"),
+        )
+        .expect("rendered JSON should parse");
+        assert_eq!(
+            json["props"]["released"],
+            serde_json::json!({ "$datetime": "2024-05-01T10:00:00Z" })
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_line() {
+        let raw = "[[rules]]\ntype = \"delete\"\npath = 3\n";
+        let err = ConfigFormat::Toml
+            .parse(&ConfigFormat::Toml.source_map("config.toml", raw))
+            .expect_err("path must be a string");
+
+        let message = err.to_string();
+        assert!(
+            message.contains("--> config.toml:1:1 (rules[0], type=delete)"),
+            "{message}"
+        );
+        assert!(message.contains("1 | [[rules]]"), "{message}");
+    }
+}
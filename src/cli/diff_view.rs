@@ -0,0 +1,436 @@
+use std::{
+    env,
+    fmt::Write as _,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use clap::ValueEnum;
+use genify::generation::{DiffHunk, DiffLine, DiffLineKind, DiffOutput, FileDiff, FileDiffStatus};
+use similar::{ChangeTag, TextDiff};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const EMPHASIS_RED: &str = "\x1b[1;41;97m";
+const EMPHASIS_GREEN: &str = "\x1b[1;42;97m";
+
+const DEFAULT_WIDTH: usize = 160;
+const DEFAULT_PAGER: &str = "less -FRX";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DiffViewOptions {
+    pub color: ColorChoice,
+    pub side_by_side: bool,
+    pub pager: bool,
+}
+
+/// Renders a dry-run diff for people: colored, word-highlighted and paged on a
+/// terminal, plain unified text when piped.
+pub fn show(output: &DiffOutput, options: DiffViewOptions) -> io::Result<()> {
+    let is_terminal = io::stdout().is_terminal();
+    let color = match options.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => is_terminal && env::var_os("NO_COLOR").is_none(),
+    };
+
+    let rendered = if !color && !options.side_by_side {
+        output.diff.clone()
+    } else {
+        let palette = Palette::new(color);
+        let mut rendered = String::new();
+        for file in &output.files {
+            if options.side_by_side {
+                render_side_by_side(&mut rendered, file, &palette, terminal_width());
+            } else {
+                render_unified(&mut rendered, file, &palette);
+            }
+        }
+        rendered
+    };
+
+    if options.pager && is_terminal {
+        page(&rendered)
+    } else {
+        io::stdout().write_all(rendered.as_bytes())
+    }
+}
+
+struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    fn paint(&self, out: &mut String, style: &str, text: &str) {
+        if self.enabled && !style.is_empty() && !text.is_empty() {
+            let _ = write!(out, "{style}{text}{RESET}");
+        } else {
+            out.push_str(text);
+        }
+    }
+}
+
+fn render_file_header(out: &mut String, file: &FileDiff, palette: &Palette) {
+    let old_path = file.old_path.as_deref().unwrap_or(&file.path);
+    palette.paint(
+        out,
+        BOLD,
+        &format!("diff --genify a/{old_path} b/{}", file.path),
+    );
+    out.push('\n');
+    let (old, new) = match file.status {
+        FileDiffStatus::Created => ("/dev/null".to_string(), format!("b/{}", file.path)),
+        FileDiffStatus::Deleted => (format!("a/{}", file.path), "/dev/null".to_string()),
+        FileDiffStatus::Modified | FileDiffStatus::Renamed => {
+            (format!("a/{old_path}"), format!("b/{}", file.path))
+        }
+    };
+    if file.status == FileDiffStatus::Renamed {
+        palette.paint(
+            out,
+            BOLD,
+            &format!("rename from {old_path}\nrename to {}", file.path),
+        );
+        out.push('\n');
+    }
+    palette.paint(out, BOLD, &format!("--- {old}\n+++ {new}"));
+    out.push('\n');
+}
+
+fn render_hunk_header(out: &mut String, hunk: &DiffHunk, palette: &Palette) {
+    palette.paint(
+        out,
+        CYAN,
+        &format!(
+            "@@ -{},{} +{},{} @@",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        ),
+    );
+    out.push('\n');
+}
+
+fn render_unified(out: &mut String, file: &FileDiff, palette: &Palette) {
+    render_file_header(out, file, palette);
+    for hunk in &file.hunks {
+        render_hunk_header(out, hunk, palette);
+        for block in blocks(&hunk.lines) {
+            match block {
+                Block::Context(line) => {
+                    out.push(' ');
+                    out.push_str(&line.content);
+                    out.push('\n');
+                }
+                Block::Change { deleted, inserted } => {
+                    let (old, new) = inline_segments(deleted, inserted);
+                    for segments in old {
+                        render_segments(out, '-', RED, EMPHASIS_RED, &segments, palette);
+                    }
+                    for segments in new {
+                        render_segments(out, '+', GREEN, EMPHASIS_GREEN, &segments, palette);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn render_segments(
+    out: &mut String,
+    sign: char,
+    style: &str,
+    emphasis: &str,
+    segments: &[(bool, String)],
+    palette: &Palette,
+) {
+    palette.paint(out, style, &sign.to_string());
+    for (emphasized, text) in segments {
+        palette.paint(out, if *emphasized { emphasis } else { style }, text);
+    }
+    out.push('\n');
+}
+
+fn render_side_by_side(out: &mut String, file: &FileDiff, palette: &Palette, width: usize) {
+    render_file_header(out, file, palette);
+    let column = width.saturating_sub(3) / 2;
+    for hunk in &file.hunks {
+        render_hunk_header(out, hunk, palette);
+        for block in blocks(&hunk.lines) {
+            match block {
+                Block::Context(line) => {
+                    let cell = fit(&[(false, line.content.clone())], column);
+                    push_row(out, &cell, " ", &cell, palette, ("", ""));
+                }
+                Block::Change { deleted, inserted } => {
+                    let (old, new) = inline_segments(deleted, inserted);
+                    for row in 0..old.len().max(new.len()) {
+                        let left = old.get(row).map(|segments| fit(segments, column));
+                        let right = new.get(row).map(|segments| fit(segments, column));
+                        let marker = match (&left, &right) {
+                            (Some(_), Some(_)) => "|",
+                            (Some(_), None) => "<",
+                            _ => ">",
+                        };
+                        let empty = vec![(false, " ".repeat(column))];
+                        push_row(
+                            out,
+                            left.as_ref().unwrap_or(&empty),
+                            marker,
+                            right.as_ref().unwrap_or(&empty),
+                            palette,
+                            (RED, GREEN),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn push_row(
+    out: &mut String,
+    left: &[(bool, String)],
+    marker: &str,
+    right: &[(bool, String)],
+    palette: &Palette,
+    (left_style, right_style): (&str, &str),
+) {
+    for (emphasized, text) in left {
+        let style = if *emphasized {
+            EMPHASIS_RED
+        } else {
+            left_style
+        };
+        palette.paint(out, style, text);
+    }
+    out.push(' ');
+    palette.paint(out, CYAN, marker);
+    out.push(' ');
+    for (emphasized, text) in right {
+        let style = if *emphasized {
+            EMPHASIS_GREEN
+        } else {
+            right_style
+        };
+        palette.paint(out, style, text);
+    }
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    out.push('\n');
+}
+
+/// Truncates or pads segments to exactly `width` characters.
+fn fit(segments: &[(bool, String)], width: usize) -> Vec<(bool, String)> {
+    let mut remaining = width;
+    let mut fitted = Vec::with_capacity(segments.len() + 1);
+    for (emphasized, text) in segments {
+        if remaining == 0 {
+            break;
+        }
+        let text = text.replace('\t', "    ");
+        let taken = text.chars().take(remaining).collect::<String>();
+        remaining -= taken.chars().count();
+        fitted.push((*emphasized, taken));
+    }
+    if remaining > 0 {
+        fitted.push((false, " ".repeat(remaining)));
+    }
+    fitted
+}
+
+enum Block<'a> {
+    Context(&'a DiffLine),
+    Change {
+        deleted: Vec<&'a DiffLine>,
+        inserted: Vec<&'a DiffLine>,
+    },
+}
+
+/// Groups hunk lines into context lines and runs of deletions followed by
+/// insertions, which are the units compared word by word.
+fn blocks(lines: &[DiffLine]) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        if lines[index].kind == DiffLineKind::Context {
+            blocks.push(Block::Context(&lines[index]));
+            index += 1;
+            continue;
+        }
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        while index < lines.len() && lines[index].kind == DiffLineKind::Delete {
+            deleted.push(&lines[index]);
+            index += 1;
+        }
+        while index < lines.len() && lines[index].kind == DiffLineKind::Insert {
+            inserted.push(&lines[index]);
+            index += 1;
+        }
+        blocks.push(Block::Change { deleted, inserted });
+    }
+    blocks
+}
+
+type Segments = Vec<(bool, String)>;
+
+fn inline_segments(
+    deleted: Vec<&DiffLine>,
+    inserted: Vec<&DiffLine>,
+) -> (Vec<Segments>, Vec<Segments>) {
+    let old = join_lines(&deleted);
+    let new = join_lines(&inserted);
+    let diff = TextDiff::from_lines(old.as_str(), new.as_str());
+    let mut old_segments = Vec::with_capacity(deleted.len());
+    let mut new_segments = Vec::with_capacity(inserted.len());
+    for op in diff.ops() {
+        for change in diff.iter_inline_changes(op) {
+            let segments = change
+                .iter_strings_lossy()
+                .map(|(emphasized, text)| {
+                    (emphasized, text.trim_end_matches(['\r', '\n']).to_string())
+                })
+                .collect::<Vec<_>>();
+            match change.tag() {
+                ChangeTag::Delete => old_segments.push(segments),
+                ChangeTag::Insert => new_segments.push(segments),
+                ChangeTag::Equal => {
+                    old_segments.push(segments.clone());
+                    new_segments.push(segments);
+                }
+            }
+        }
+    }
+    (old_segments, new_segments)
+}
+
+fn join_lines(lines: &[&DiffLine]) -> String {
+    lines.iter().fold(String::new(), |mut joined, line| {
+        joined.push_str(&line.content);
+        joined.push('\n');
+        joined
+    })
+}
+
+fn terminal_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|width| *width > 20)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+fn page(rendered: &str) -> io::Result<()> {
+    let pager = env::var("GENIFY_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        return io::stdout().write_all(rendered.as_bytes());
+    };
+
+    let child = Command::new(program)
+        .args(parts)
+        .env(
+            "LESS",
+            env::var("LESS").unwrap_or_else(|_| "FRX".to_string()),
+        )
+        .stdin(Stdio::piped())
+        .spawn();
+    let Ok(mut child) = child else {
+        return io::stdout().write_all(rendered.as_bytes());
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(rendered.as_bytes()) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => {}
+            Err(err) => return Err(err),
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(kind: DiffLineKind, content: &str) -> DiffLine {
+        DiffLine {
+            kind,
+            old_line: None,
+            new_line: None,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn highlights_changed_words() {
+        let file = FileDiff {
+            path: "a.txt".to_string(),
+            old_path: None,
+            status: FileDiffStatus::Modified,
+            additions: 1,
+            deletions: 1,
+            hunks: vec![DiffHunk {
+                old_start: 1,
+                old_lines: 2,
+                new_start: 1,
+                new_lines: 2,
+                lines: vec![
+                    line(DiffLineKind::Delete, "hello world"),
+                    line(DiffLineKind::Insert, "hello there"),
+                    line(DiffLineKind::Context, "end"),
+                ],
+            }],
+        };
+
+        let mut plain = String::new();
+        render_unified(&mut plain, &file, &Palette::new(false));
+        assert!(plain.ends_with("@@ -1,2 +1,2 @@\n-hello world\n+hello there\n end\n"));
+
+        let mut colored = String::new();
+        render_unified(&mut colored, &file, &Palette::new(true));
+        assert!(colored.contains(&format!("{EMPHASIS_RED}world{RESET}")));
+        assert!(colored.contains(&format!("{EMPHASIS_GREEN}there{RESET}")));
+    }
+
+    #[test]
+    fn side_by_side_pairs_changed_lines() {
+        let file = FileDiff {
+            path: "a.txt".to_string(),
+            old_path: None,
+            status: FileDiffStatus::Modified,
+            additions: 2,
+            deletions: 1,
+            hunks: vec![DiffHunk {
+                old_start: 1,
+                old_lines: 1,
+                new_start: 1,
+                new_lines: 2,
+                lines: vec![
+                    line(DiffLineKind::Delete, "old"),
+                    line(DiffLineKind::Insert, "new"),
+                    line(DiffLineKind::Insert, "extra"),
+                ],
+            }],
+        };
+
+        let mut out = String::new();
+        render_side_by_side(&mut out, &file, &Palette::new(false), 23);
+        assert!(out.ends_with("old        | new\n           > extra\n"));
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, builder::FalseyValueParser};
+use genify::manifest::content_hash;
+use reqwest::{StatusCode, blocking::Client, header};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::cli::cache::key_name;
+
+/// How remote configs are fetched and checked.
+#[derive(Args, Debug, Clone, Default)]
+pub struct FetchArgs {
+    /// Read remote configs only from the cache, never from the network.
+    #[arg(long, global = true, env = "GENIFY_OFFLINE", value_parser = FalseyValueParser::new())]
+    pub offline: bool,
+    /// Refuse a config whose SHA-256 is not HASH (hex, `sha256:` prefix optional).
+    #[arg(long, global = true, value_name = "HASH")]
+    pub sha256: Option<String>,
+}
+
+/// Fetches `url` through the cache under `cache`. A cached copy is revalidated
+/// with its ETag and Last-Modified, and used as is when it matches a pinned
+/// hash, when `offline` is set or when the server cannot be reached. The hash
+/// is `expected`, or the URL's `#sha256=<hash>` fragment; content that does
+/// not match it is refused and never cached.
+pub fn fetch_url(
+    url: &Url,
+    cache: &Path,
+    offline: bool,
+    expected: Option<&str>,
+) -> Result<String, String> {
+    let fragment = url
+        .fragment()
+        .and_then(|fragment| fragment.strip_prefix("sha256="));
+    let expected = expected.or(fragment).map(normalize_sha256).transpose()?;
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let cached = CachedUrl {
+        dir: cache.join("http").join(key_name(url.as_str())),
+    };
+    let cached_body = cached.body();
+
+    // Content pinned by hash cannot change, so a matching copy needs no request.
+    if let (Some(body), Some(expected)) = (&cached_body, &expected)
+        && check_sha256(body, expected).is_ok()
+    {
+        return Ok(body.clone());
+    }
+    let body = if offline {
+        cached_body
+            .ok_or_else(|| format!("{url} is not cached; fetch it once without --offline"))?
+    } else {
+        match request(&url, cached_body.as_ref().map(|_| cached.validators())) {
+            Ok(Fetched::NotModified) => cached_body.expect("only cached copies are revalidated"),
+            Ok(Fetched::Body(body, validators)) => {
+                if let Some(expected) = &expected {
+                    check_sha256(&body, expected)?;
+                }
+                if let Err(err) = cached.store(&body, &validators) {
+                    eprintln!("warning: failed to cache {url}: {err}");
+                }
+                return Ok(body);
+            }
+            Err(err) => match cached_body {
+                Some(body) => {
+                    eprintln!("warning: {err}; using the cached copy of {url}");
+                    body
+                }
+                None => return Err(err),
+            },
+        }
+    };
+    if let Some(expected) = &expected {
+        check_sha256(&body, expected)?;
+    }
+    Ok(body)
+}
+
+/// Fails unless `content` hashes to `expected`, given as [`FetchArgs::sha256`].
+pub fn verify_sha256(content: &str, expected: &str) -> Result<(), String> {
+    check_sha256(content, &normalize_sha256(expected)?)
+}
+
+fn check_sha256(content: &str, expected: &str) -> Result<(), String> {
+    let actual = content_hash(content.as_bytes());
+    let actual = &actual["sha256:".len()..];
+    if actual != expected {
+        return Err(format!(
+            "config has SHA-256 {actual}, but {expected} was expected"
+        ));
+    }
+    Ok(())
+}
+
+fn normalize_sha256(hash: &str) -> Result<String, String> {
+    let hex = hash
+        .strip_prefix("sha256:")
+        .unwrap_or(hash)
+        .to_ascii_lowercase();
+    if hex.len() != 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("`{hash}` is not a hex SHA-256 hash"));
+    }
+    Ok(hex)
+}
+
+enum Fetched {
+    NotModified,
+    Body(String, Validators),
+}
+
+fn request(url: &Url, validators: Option<Validators>) -> Result<Fetched, String> {
+    let mut request = Client::new().get(url.clone());
+    if let Some(validators) = validators {
+        if let Some(etag) = validators.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = validators.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("failed to fetch {url}: {err}"))?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value: &header::HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let validators = Validators {
+        etag: header(header::ETAG),
+        last_modified: header(header::LAST_MODIFIED),
+    };
+    let body = response
+        .text()
+        .map_err(|err| format!("failed to fetch {url}: {err}"))?;
+    Ok(Fetched::Body(body, validators))
+}
+
+/// What the server said about the cached copy, sent back to revalidate it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// The last fetched copy of a URL: its `body` and `validators.json`.
+struct CachedUrl {
+    dir: PathBuf,
+}
+
+impl CachedUrl {
+    fn body(&self) -> Option<String> {
+        fs::read_to_string(self.dir.join("body")).ok()
+    }
+
+    fn validators(&self) -> Validators {
+        fs::read_to_string(self.dir.join("validators.json"))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    fn store(&self, body: &str, validators: &Validators) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Written aside and renamed, so readers never see half a body.
+        let staging = self.dir.join(format!("body.{}", std::process::id()));
+        fs::write(&staging, body)?;
+        fs::rename(&staging, self.dir.join("body"))?;
+        fs::write(
+            self.dir.join("validators.json"),
+            serde_json::to_string(validators).map_err(io::Error::other)?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    const CONFIG: &str = "[props]\nname = \"demo\"\n";
+
+    /// Serves `CONFIG` with an ETag for `requests` requests, answering 304
+    /// when a request sends the ETag back. The server thread returns the
+    /// status of every response.
+    fn serve(requests: usize) -> (Url, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("test server should bind");
+        let url = Url::parse(&format!(
+            "http://{}/genify.toml",
+            listener
+                .local_addr()
+                .expect("test server should have an address")
+        ))
+        .expect("test URL should parse");
+        let server = thread::spawn(move || {
+            let mut seen = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.expect("request should connect");
+                let mut revalidated = false;
+                let mut reader = BufReader::new(stream.try_clone().expect("stream should clone"));
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).expect("request should be read");
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    revalidated |= line.eq_ignore_ascii_case("if-none-match: \"v1\"\r\n");
+                }
+                let response = if revalidated {
+                    seen.push("304".to_string());
+                    "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    seen.push("200".to_string());
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{CONFIG}",
+                        CONFIG.len()
+                    )
+                };
+                stream
+                    .write_all(response.as_bytes())
+                    .expect("response should be written");
+            }
+            seen
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn revalidates_cached_copies_and_checks_hashes() {
+        let cache = std::env::temp_dir().join(format!("genify-fetch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache);
+        let hash = &content_hash(CONFIG.as_bytes())["sha256:".len()..];
+        let (url, server) = serve(2);
+
+        assert_eq!(
+            fetch_url(&url, &cache, false, None).expect("config should be fetched"),
+            CONFIG
+        );
+        assert_eq!(
+            fetch_url(&url, &cache, false, None).expect("config should be fetched"),
+            CONFIG
+        );
+        assert_eq!(
+            server.join().expect("test server should finish"),
+            vec!["200", "304"]
+        );
+
+        // The server is gone: pinned and offline reads come from the cache.
+        let mut pinned = url.clone();
+        pinned.set_fragment(Some(&format!("sha256={hash}")));
+        assert_eq!(
+            fetch_url(&pinned, &cache, false, None)
+                .expect("pinned config should come from the cache"),
+            CONFIG
+        );
+        assert_eq!(
+            fetch_url(&url, &cache, true, None).expect("offline config should come from the cache"),
+            CONFIG
+        );
+        let wrong = "0".repeat(64);
+        assert!(
+            fetch_url(&url, &cache, true, Some(&wrong))
+                .unwrap_err()
+                .contains("was expected")
+        );
+        let other = Url::parse("http://127.0.0.1:1/other.toml").expect("test URL should parse");
+        assert!(fetch_url(&other, &cache, true, None).is_err());
+        assert!(verify_sha256(CONFIG, &format!("sha256:{}", hash.to_uppercase())).is_ok());
+        let _ = fs::remove_dir_all(cache);
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use genify::generation::{Diagnostic, GenerationCore, GenerationRequest};
+use serde_json::Value as JsonValue;
+use similar::TextDiff;
+
+/// Files of a fixture tree by path relative to its root, `/`-separated.
+pub type Tree = BTreeMap<String, Vec<u8>>;
+
+/// A fixture directory: the project the config starts from (`input/`), the
+/// props to generate with (`props.json`) and the project it should end up as
+/// (`expected/`). Every part is optional; a missing one is empty.
+#[derive(Debug, Clone)]
+pub struct FixtureCase {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+/// What running a case against the config came to.
+#[derive(Debug)]
+pub enum CaseOutcome {
+    Passed,
+    /// `expected/` was rewritten from the generated files.
+    Blessed,
+    /// Files that differ from `expected/`, as `(path, explanation)`.
+    Mismatched(Vec<(String, String)>),
+    /// Generation reported errors, so there is nothing to compare.
+    Failed(Vec<Diagnostic>),
+}
+
+/// The subdirectories of `dir`, in name order.
+pub fn discover(dir: &Path) -> io::Result<Vec<FixtureCase>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            cases.push(FixtureCase {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                dir: entry.path(),
+            });
+        }
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+impl FixtureCase {
+    /// Generates `config` over a temporary copy of `input/` and compares the
+    /// result with `expected/`, or replaces `expected/` with it when `bless`
+    /// is set. Generation itself runs in memory; only the copy touches disk.
+    pub fn run(&self, config: &JsonValue, bless: bool) -> Result<CaseOutcome, String> {
+        let input = read_tree(&self.dir.join("input"))?;
+        let props = self.props()?;
+
+        let root =
+            std::env::temp_dir().join(format!("genify-test-{}-{}", process::id(), self.name));
+        let _ = fs::remove_dir_all(&root);
+        write_tree(&root, &input)?;
+        let preview = GenerationCore::new(&root)
+            .and_then(|core| {
+                core.preview(GenerationRequest {
+                    config: Some(config.clone()),
+                    props,
+                    ..GenerationRequest::default()
+                })
+            })
+            .map_err(|err| err.to_string());
+        let _ = fs::remove_dir_all(&root);
+        let preview = preview?;
+        if !preview.errors.is_empty() {
+            return Ok(CaseOutcome::Failed(preview.errors));
+        }
+
+        let mut actual = input;
+        for file in preview.files {
+            match file.content {
+                Some(content) => actual.insert(file.path, content.into_bytes()),
+                None => actual.remove(&file.path),
+            };
+        }
+        // Project props and the manifest are inputs, not generated output.
+        actual.retain(|path, _| !path.starts_with(".genify/"));
+
+        let expected_dir = self.dir.join("expected");
+        if bless {
+            if expected_dir.exists() {
+                fs::remove_dir_all(&expected_dir)
+                    .map_err(|err| format!("failed to clear {}: {err}", expected_dir.display()))?;
+            }
+            write_tree(&expected_dir, &actual)?;
+            return Ok(CaseOutcome::Blessed);
+        }
+
+        let mismatches = compare(&read_tree(&expected_dir)?, &actual);
+        Ok(if mismatches.is_empty() {
+            CaseOutcome::Passed
+        } else {
+            CaseOutcome::Mismatched(mismatches)
+        })
+    }
+
+    fn props(&self) -> Result<Option<JsonValue>, String> {
+        let path = self.dir.join("props.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        let raw = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        let props: JsonValue = serde_json::from_str(&raw)
+            .map_err(|err| format!("failed to parse {}: {err}", path.display()))?;
+        if !props.is_object() {
+            return Err(format!("{} must contain a JSON object", path.display()));
+        }
+        Ok(Some(props))
+    }
+}
+
+/// Every file that is missing, unexpected or different in `actual`, with a
+/// unified diff for text files.
+pub fn compare(expected: &Tree, actual: &Tree) -> Vec<(String, String)> {
+    let mut mismatches = Vec::new();
+    for (path, want) in expected {
+        match actual.get(path) {
+            None => mismatches.push((path.clone(), "expected but not generated".to_string())),
+            Some(got) if got != want => {
+                let explanation = match (std::str::from_utf8(want), std::str::from_utf8(got)) {
+                    (Ok(want), Ok(got)) => TextDiff::from_lines(want, got)
+                        .unified_diff()
+                        .header("expected", "actual")
+                        .to_string(),
+                    _ => "binary content differs".to_string(),
+                };
+                mismatches.push((path.clone(), explanation));
+            }
+            Some(_) => {}
+        }
+    }
+    for path in actual.keys() {
+        if !expected.contains_key(path) {
+            mismatches.push((path.clone(), "generated but not expected".to_string()));
+        }
+    }
+    mismatches.sort();
+    mismatches
+}
+
+/// Reads the files under `dir`. A missing directory is an empty tree.
+pub fn read_tree(dir: &Path) -> Result<Tree, String> {
+    fn walk(root: &Path, dir: &Path, tree: &mut Tree) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                walk(root, &path, tree)?;
+                continue;
+            }
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            tree.insert(relative, fs::read(&path)?);
+        }
+        Ok(())
+    }
+
+    let mut tree = Tree::new();
+    if dir.is_dir() {
+        walk(dir, dir, &mut tree)
+            .map_err(|err| format!("failed to read {}: {err}", dir.display()))?;
+    }
+    Ok(tree)
+}
+
+fn write_tree(dir: &Path, tree: &Tree) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+    for (relative, content) in tree {
+        let path = dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+        }
+        fs::write(&path, content)
+            .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixture(name: &str) -> FixtureCase {
+        let dir = std::env::temp_dir().join(format!("genify-fixture-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        write_tree(
+            &dir,
+            &Tree::from([
+                ("input/README.md".to_string(), b"# old\n".to_vec()),
+                (
+                    "input/.genify/props.toml".to_string(),
+                    b"dir = \"src\"\n".to_vec(),
+                ),
+                ("props.json".to_string(), br#"{"name": "demo"}"#.to_vec()),
+            ]),
+        )
+        .expect("fixture should be written");
+        FixtureCase {
+            name: name.to_string(),
+            dir,
+        }
+    }
+
+    #[test]
+    fn blesses_then_passes_and_reports_mismatches() {
+        let case = fixture("bless");
+        let config = json!({
+            "rules": [
+                {"type": "write", "path": "{{ dir }}/{{ name }}.txt", "content": "{{ name }}", "if_exists": "error"},
+                {"type": "replace", "path": "README.md", "replace": "old", "content": "{{ name }}"}
+            ]
+        });
+
+        assert!(matches!(case.run(&config, true), Ok(CaseOutcome::Blessed)));
+        assert_eq!(
+            read_tree(&case.dir.join("expected")).expect("blessed tree should be readable"),
+            Tree::from([
+                ("README.md".to_string(), b"# demo\n".to_vec()),
+                ("src/demo.txt".to_string(), b"demo\n".to_vec()),
+            ])
+        );
+        assert!(matches!(case.run(&config, false), Ok(CaseOutcome::Passed)));
+
+        fs::write(case.dir.join("expected/README.md"), "# other\n")
+            .expect("test file should be written");
+        fs::write(case.dir.join("expected/extra.txt"), "extra\n")
+            .expect("test file should be written");
+        let Ok(CaseOutcome::Mismatched(mismatches)) = case.run(&config, false) else {
+            panic!("changed expectations should not match");
+        };
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[0].0, "README.md");
+        assert!(
+            mismatches[0].1.contains("-# other\n+# demo\n"),
+            "{}",
+            mismatches[0].1
+        );
+        assert_eq!(
+            mismatches[1],
+            (
+                "extra.txt".to_string(),
+                "expected but not generated".to_string()
+            )
+        );
+        let _ = fs::remove_dir_all(case.dir);
+    }
+}
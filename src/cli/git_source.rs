@@ -0,0 +1,248 @@
+use std::{
+    ffi::OsStr,
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+    process::{self, Command},
+};
+
+use url::Url;
+
+use crate::cli::cache::key_name;
+
+/// A config in a git repository, written `git+<url>#<ref>:<path>`: `<url>` is
+/// a `file`, `ssh`, `http` or `https` repository URL, `<ref>` a branch, tag or
+/// commit, and `<path>` the config inside the repository. Without `<ref>:` the
+/// remote's default branch is used.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitSource {
+    /// Repository URL, without the `git+` prefix and the fragment.
+    pub repo: String,
+    pub reference: Option<String>,
+    /// Config path inside the repository, `/`-separated.
+    pub path: String,
+}
+
+impl GitSource {
+    /// The source `value` names, or `None` when it is not a `git+` URL.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut url = Url::parse(value.strip_prefix("git+")?).ok()?;
+        if !matches!(url.scheme(), "file" | "ssh" | "http" | "https") {
+            return None;
+        }
+        let fragment = url.fragment().unwrap_or_default().to_string();
+        url.set_fragment(None);
+        let (reference, path) = match fragment.split_once(':') {
+            Some((reference, path)) => (Some(reference.to_string()), path),
+            None => (None, fragment.as_str()),
+        };
+        Some(Self {
+            repo: url.to_string(),
+            reference: reference.filter(|reference| !reference.is_empty()),
+            path: path.trim_start_matches('/').to_string(),
+        })
+    }
+
+    /// Checks out the commit the source points at under `cache` and returns
+    /// the checkout. Checkouts are kept per commit, so a source pinned to a
+    /// commit that was checked out before is served without fetching; branch
+    /// and tag names are resolved against a freshly fetched mirror, or against
+    /// the mirror as it is when `offline` is set or the remote is unreachable.
+    pub fn checkout(&self, cache: &Path, offline: bool) -> Result<PathBuf, String> {
+        let dir = cache.join("git").join(key_name(&self.repo));
+        if let Some(commit) = self.reference.as_deref().filter(|r| is_commit_id(r))
+            && dir.join(commit).is_dir()
+        {
+            return Ok(dir.join(commit));
+        }
+
+        let mirror = dir.join("mirror.git");
+        if mirror.is_dir() {
+            if !offline && let Err(err) = git(&mirror, ["fetch", "--quiet", "--prune", "origin"]) {
+                eprintln!("warning: {err}; using the cached mirror of {}", self.repo);
+            }
+        } else if offline {
+            return Err(format!(
+                "{} is not cached; fetch it once without --offline",
+                self.repo
+            ));
+        } else {
+            fs::create_dir_all(&dir)
+                .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+            git(
+                &dir,
+                [
+                    OsStr::new("clone"),
+                    OsStr::new("--quiet"),
+                    OsStr::new("--mirror"),
+                    OsStr::new(&self.repo),
+                    mirror.as_os_str(),
+                ],
+            )
+            .inspect_err(|_| {
+                let _ = fs::remove_dir_all(&mirror);
+            })?;
+        }
+        let reference = self.reference.as_deref().unwrap_or("HEAD");
+        let commit = git(
+            &mirror,
+            [
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{reference}^{{commit}}"),
+            ],
+        )
+        .map_err(|_| {
+            format!(
+                "{reference} is not a branch, tag or commit in {}",
+                self.repo
+            )
+        })?;
+
+        let checkout = dir.join(&commit);
+        if !checkout.is_dir() {
+            // Check out next to the final directory and move it in place, so
+            // an interrupted run never leaves a partial checkout behind.
+            let staging = dir.join(format!("{commit}.{}", process::id()));
+            let _ = fs::remove_dir_all(&staging);
+            git(
+                &dir,
+                [
+                    OsStr::new("clone"),
+                    OsStr::new("--quiet"),
+                    OsStr::new("--no-checkout"),
+                    mirror.as_os_str(),
+                    staging.as_os_str(),
+                ],
+            )?;
+            git(&staging, ["checkout", "--quiet", "--detach", &commit])?;
+            if fs::rename(&staging, &checkout).is_err() && !checkout.is_dir() {
+                return Err(format!("failed to create {}", checkout.display()));
+            }
+            let _ = fs::remove_dir_all(&staging);
+        }
+        Ok(checkout)
+    }
+
+    /// The config file inside `checkout`.
+    pub fn file(&self, checkout: &Path) -> Result<PathBuf, String> {
+        let inside = Path::new(&self.path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if self.path.is_empty() || !inside {
+            return Err(format!(
+                "git source needs a config path inside the repository after `#`, got `{}`",
+                self.path
+            ));
+        }
+        Ok(checkout.join(&self.path))
+    }
+}
+
+impl fmt::Display for GitSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "git+{}#", self.repo)?;
+        if let Some(reference) = &self.reference {
+            write!(f, "{reference}:")?;
+        }
+        f.write_str(&self.path)
+    }
+}
+
+/// Runs git in `dir` and returns its trimmed output, or its error message.
+fn git<I, S>(dir: &Path, args: I) -> Result<String, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| format!("failed to run git: {err}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn is_commit_id(reference: &str) -> bool {
+    matches!(reference.len(), 40 | 64) && reference.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(dir: &Path, args: &[&str]) -> String {
+        git(dir, args).unwrap_or_else(|err| panic!("git {args:?} failed: {err}"))
+    }
+
+    #[test]
+    fn parses_reference_and_path() {
+        assert_eq!(
+            GitSource::parse("git+ssh://git@example.com/team/configs.git#v1.2:rust/service.toml"),
+            Some(GitSource {
+                repo: "ssh://git@example.com/team/configs.git".to_string(),
+                reference: Some("v1.2".to_string()),
+                path: "rust/service.toml".to_string(),
+            })
+        );
+        let source = GitSource::parse("git+file:///srv/configs.git#genify.toml")
+            .expect("git URL should parse");
+        assert_eq!(source.reference, None);
+        assert_eq!(source.path, "genify.toml");
+        assert_eq!(GitSource::parse("https://example.com/genify.toml"), None);
+        assert!(
+            GitSource::parse("git+file:///srv/configs.git#main:../x.toml")
+                .expect("git URL should parse")
+                .file(Path::new("/checkout"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn checks_out_tags_once_per_commit() {
+        let root = std::env::temp_dir().join(format!("genify-git-source-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let work = root.join("work");
+        fs::create_dir_all(&work).expect("test dir should be created");
+        run(&work, &["init", "--quiet"]);
+        run(&work, &["config", "user.email", "test@example.com"]);
+        run(&work, &["config", "user.name", "test"]);
+        fs::write(work.join("genify.toml"), "v1").expect("test file should be written");
+        run(&work, &["add", "."]);
+        run(&work, &["commit", "--quiet", "-m", "v1"]);
+        run(&work, &["tag", "v1"]);
+        fs::write(work.join("genify.toml"), "v2").expect("test file should be written");
+        run(&work, &["commit", "--quiet", "-am", "v2"]);
+        run(&root, &["clone", "--quiet", "--bare", "work", "repo.git"]);
+
+        let cache = root.join("cache");
+        let repo =
+            Url::from_directory_path(root.join("repo.git")).expect("repo path should be absolute");
+        let source = |fragment: &str| {
+            GitSource::parse(&format!("git+{repo}#{fragment}")).expect("git URL should parse")
+        };
+        let read = |source: &GitSource| {
+            let checkout = source
+                .checkout(&cache, false)
+                .expect("source should be checked out");
+            fs::read_to_string(
+                source
+                    .file(&checkout)
+                    .expect("path should stay in the checkout"),
+            )
+            .expect("config should be checked out")
+        };
+
+        assert_eq!(read(&source("v1:genify.toml")), "v1");
+        assert_eq!(read(&source("genify.toml")), "v2");
+        let commit = run(&work, &["rev-parse", "HEAD~1"]);
+        fs::remove_dir_all(root.join("repo.git")).expect("test dir should be removed");
+        assert_eq!(read(&source(&format!("{commit}:genify.toml"))), "v1");
+        assert!(source("v1:genify.toml").checkout(&cache, true).is_ok());
+        assert!(source("v9:genify.toml").checkout(&cache, true).is_err());
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,10 @@
+pub mod cache;
+pub mod convert;
+pub mod diff_view;
+pub mod fetch;
+pub mod fixtures;
+pub mod git_source;
+pub mod props;
+pub mod report;
+pub mod trust;
+pub mod watch;
@@ -0,0 +1,400 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, error::ErrorKind};
+use genify::{Map, Value};
+use serde_json::Value as JsonValue;
+
+/// Every prop layer, lowest precedence first. The first three are resolved by
+/// the generation core, the rest come from [`PropsArgs`].
+pub const PRECEDENCE: [&str; 7] = [
+    "config",
+    "manifest",
+    "project",
+    "props-file",
+    "env",
+    "props-json",
+    "set",
+];
+
+pub const DEFAULT_ENV_PREFIX: &str = "GENIFY_PROP_";
+
+#[derive(Args, Default)]
+pub struct PropsArgs {
+    /// Override props using a JSON object (Array/Map supported).
+    #[arg(short = 'p', long = "props-json", value_name = "JSON")]
+    pub props_json: Option<String>,
+    /// Override props from a TOML, JSON or YAML file (repeatable, later files win).
+    #[arg(long = "props-file", value_name = "FILE")]
+    pub props_files: Vec<PathBuf>,
+    /// Set a prop, using dotted keys for nested maps (repeatable), e.g. db.port=5432.
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+    /// Read props from environment variables starting with PREFIX [default: GENIFY_PROP_].
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = DEFAULT_ENV_PREFIX
+    )]
+    pub env_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropLayer {
+    PropsFile,
+    Env,
+    PropsJson,
+    Set,
+}
+
+impl PropLayer {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PropsFile => "props-file",
+            Self::Env => "env",
+            Self::PropsJson => "props-json",
+            Self::Set => "set",
+        }
+    }
+}
+
+/// Props set on the command line, with the layer that set each one last.
+#[derive(Debug, Default)]
+pub struct PropOverrides {
+    pub props: Map,
+    pub sources: BTreeMap<String, PropLayer>,
+}
+
+impl PropOverrides {
+    fn merge(&mut self, props: Map, layer: PropLayer) {
+        for (key, value) in props {
+            self.insert(key, value, layer);
+        }
+    }
+
+    fn insert(&mut self, key: String, value: Value, layer: PropLayer) {
+        self.sources.insert(key.clone(), layer);
+        match self.props.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => self.props.push((key, value)),
+        }
+    }
+
+    /// Sets `path` inside the current value of its top-level prop, taken from
+    /// an earlier layer or from `base`. `raw` is read as the type of the value
+    /// it replaces.
+    fn assign(&mut self, base: &Map, path: &[&str], raw: &str, layer: PropLayer) {
+        let Some((key, rest)) = path.split_first() else {
+            return;
+        };
+        let current = self
+            .props
+            .iter()
+            .chain(base)
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value.clone());
+        let replaced = rest
+            .iter()
+            .try_fold(current.as_ref(), |value, key| match value {
+                Some(Value::Map(map)) => Some(
+                    map.iter()
+                        .find(|(existing, _)| existing == key)
+                        .map(|(_, value)| value),
+                ),
+                _ => None,
+            });
+        let value = parse_scalar(raw, replaced.flatten());
+        self.insert(key.to_string(), set_path(current, rest, value), layer);
+    }
+}
+
+impl PropsArgs {
+    /// Collects the overrides in precedence order. `base` holds the props the
+    /// overrides apply to, so dotted keys keep the rest of a nested map.
+    pub fn overrides(&self, base: &Map) -> Result<PropOverrides, clap::Error> {
+        let mut overrides = PropOverrides::default();
+        for path in &self.props_files {
+            overrides.merge(read_props_file(path)?, PropLayer::PropsFile);
+        }
+        if let Some(prefix) = &self.env_prefix {
+            let mut vars: Vec<_> = env::vars()
+                .filter_map(|(name, value)| {
+                    let key = name.strip_prefix(prefix.as_str())?;
+                    (!key.is_empty()).then(|| (key.to_lowercase().replace("__", "."), value))
+                })
+                .collect();
+            vars.sort();
+            for (key, value) in vars {
+                let path: Vec<_> = key.split('.').collect();
+                overrides.assign(base, &path, &value, PropLayer::Env);
+            }
+        }
+        if let Some(raw) = &self.props_json {
+            overrides.merge(parse_props_json(raw)?, PropLayer::PropsJson);
+        }
+        for assignment in &self.set {
+            let (key, value) = assignment.split_once('=').ok_or_else(|| {
+                clap::Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!("--set expects KEY=VALUE, got \"{assignment}\""),
+                )
+            })?;
+            let path: Vec<_> = key.trim().split('.').collect();
+            if path.iter().any(|segment| segment.is_empty()) {
+                return Err(clap::Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!("--set has an invalid key \"{key}\""),
+                ));
+            }
+            overrides.assign(base, &path, value, PropLayer::Set);
+        }
+        Ok(overrides)
+    }
+}
+
+fn set_path(current: Option<Value>, path: &[&str], value: Value) -> Value {
+    let Some((key, rest)) = path.split_first() else {
+        return value;
+    };
+    let mut map = match current {
+        Some(Value::Map(map)) => map,
+        _ => Map::new(),
+    };
+    match map.iter_mut().find(|(existing, _)| existing == key) {
+        Some((_, existing)) => {
+            let child = std::mem::replace(existing, Value::Boolean(false));
+            *existing = set_path(Some(child), rest, value);
+        }
+        None => map.push((key.to_string(), set_path(None, rest, value))),
+    }
+    Value::Map(map)
+}
+
+/// Reads a `--set` or environment value as the type of the value it
+/// replaces. Without one, or when it does not parse as that type, the value
+/// is read as JSON (numbers, booleans, null, arrays, objects, quoted
+/// strings) when it parses and, for numbers, reads back the same, and as a
+/// plain string otherwise, so `1.10` and `01234` stay as written.
+fn parse_scalar(raw: &str, replaced: Option<&Value>) -> Value {
+    let typed = match replaced {
+        Some(Value::String(_)) => Some(Value::String(raw.to_string())),
+        Some(Value::Integer(_)) => raw.parse().ok().map(Value::Integer),
+        Some(Value::Float(_)) => raw.parse().ok().map(Value::Float),
+        Some(Value::Boolean(_)) => raw.parse().ok().map(Value::Boolean),
+        Some(Value::Datetime(_)) => raw.parse().ok().map(Value::Datetime),
+        Some(Value::Array(_) | Value::Map(_) | Value::Null) | None => None,
+    };
+    typed.unwrap_or_else(|| {
+        serde_json::from_str::<JsonValue>(raw)
+            .ok()
+            .filter(|value| {
+                value
+                    .as_number()
+                    .is_none_or(|number| number.to_string() == raw)
+            })
+            .and_then(|value| Value::try_from(value).ok())
+            .unwrap_or_else(|| Value::String(raw.to_string()))
+    })
+}
+
+fn read_props_file(path: &Path) -> Result<Map, clap::Error> {
+    let raw = fs::read_to_string(path).map_err(|err| {
+        clap::Error::raw(
+            ErrorKind::ValueValidation,
+            format!("Failed to read {}: {err}", path.display()),
+        )
+    })?;
+    let invalid = |err: String| {
+        clap::Error::raw(
+            ErrorKind::InvalidValue,
+            format!("Failed to parse props file {}: {err}", path.display()),
+        )
+    };
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+    let value = match extension {
+        "toml" => {
+            let table: toml::Table =
+                toml::from_str(&raw).map_err(|err| invalid(err.to_string()))?;
+            return Ok(table
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect());
+        }
+        "json" => {
+            serde_json::from_str::<JsonValue>(&raw).map_err(|err| invalid(err.to_string()))?
+        }
+        "yaml" | "yml" => {
+            serde_yaml_ng::from_str::<JsonValue>(&raw).map_err(|err| invalid(err.to_string()))?
+        }
+        _ => {
+            return Err(invalid(
+                "unknown format, expected a .toml, .json, .yaml or .yml file".to_string(),
+            ));
+        }
+    };
+    match Value::try_from(value).map_err(invalid)? {
+        Value::Map(props) => Ok(props),
+        _ => Err(invalid(
+            "props must be a table of key/value pairs".to_string(),
+        )),
+    }
+}
+
+fn parse_props_json(raw: &str) -> Result<Map, clap::Error> {
+    let parsed: JsonValue = serde_json::from_str(raw).map_err(|err| {
+        clap::Error::raw(
+            ErrorKind::InvalidValue,
+            format!("Failed to parse props JSON: {err}"),
+        )
+    })?;
+    let object = parsed.as_object().ok_or_else(|| {
+        clap::Error::raw(ErrorKind::InvalidValue, "Props JSON must be a JSON object")
+    })?;
+
+    let mut props = Vec::with_capacity(object.len());
+    for (key, value) in object {
+        let converted = Value::try_from(value.clone()).map_err(|err| {
+            clap::Error::raw(
+                ErrorKind::InvalidValue,
+                format!("Invalid value for \"{key}\": {err}"),
+            )
+        })?;
+        props.push((key.clone(), converted));
+    }
+
+    Ok(props)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_layers_win_and_dotted_keys_keep_siblings() {
+        let base = vec![(
+            "db".to_string(),
+            Value::Map(vec![
+                ("host".to_string(), Value::String("localhost".to_string())),
+                ("port".to_string(), Value::Integer(5432)),
+            ]),
+        )];
+        let args = PropsArgs {
+            props_json: Some(r#"{"name": "json", "tags": ["a"]}"#.to_string()),
+            set: vec![
+                "name=set".to_string(),
+                "db.port=6543".to_string(),
+                "db.pool.size=4".to_string(),
+            ],
+            ..PropsArgs::default()
+        };
+
+        let overrides = args.overrides(&base).expect("overrides should parse");
+
+        assert_eq!(
+            overrides.props,
+            vec![
+                ("name".to_string(), Value::String("set".to_string())),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![Value::String("a".to_string())])
+                ),
+                (
+                    "db".to_string(),
+                    Value::Map(vec![
+                        ("host".to_string(), Value::String("localhost".to_string())),
+                        ("port".to_string(), Value::Integer(6543)),
+                        (
+                            "pool".to_string(),
+                            Value::Map(vec![("size".to_string(), Value::Integer(4))])
+                        ),
+                    ])
+                ),
+            ]
+        );
+        assert_eq!(overrides.sources["name"], PropLayer::Set);
+        assert_eq!(overrides.sources["tags"], PropLayer::PropsJson);
+    }
+
+    #[test]
+    fn set_values_keep_the_type_of_the_prop_they_replace() {
+        let base = vec![
+            ("version".to_string(), Value::String("1.0".to_string())),
+            ("owner".to_string(), Value::String("team".to_string())),
+            ("zip".to_string(), Value::String("00000".to_string())),
+            ("ratio".to_string(), Value::Float(0.5)),
+            (
+                "db".to_string(),
+                Value::Map(vec![("port".to_string(), Value::Integer(5432))]),
+            ),
+        ];
+        let args = PropsArgs {
+            set: vec![
+                "version=1.10".to_string(),
+                "owner=null".to_string(),
+                "zip=01234".to_string(),
+                "ratio=1.10".to_string(),
+                "db.port=6543".to_string(),
+                "release=1.10".to_string(),
+                "count=3".to_string(),
+                "optional=null".to_string(),
+            ],
+            ..PropsArgs::default()
+        };
+
+        let overrides = args.overrides(&base).expect("overrides should parse");
+
+        let value = |key: &str| {
+            overrides
+                .props
+                .iter()
+                .find(|(existing, _)| existing == key)
+                .map(|(_, value)| value.clone())
+                .expect("prop should be set")
+        };
+        assert_eq!(value("version"), Value::String("1.10".to_string()));
+        assert_eq!(value("owner"), Value::String("null".to_string()));
+        assert_eq!(value("zip"), Value::String("01234".to_string()));
+        assert_eq!(value("ratio"), Value::Float(1.1));
+        assert_eq!(
+            value("db"),
+            Value::Map(vec![("port".to_string(), Value::Integer(6543))])
+        );
+        assert_eq!(value("release"), Value::String("1.10".to_string()));
+        assert_eq!(value("count"), Value::Integer(3));
+        assert_eq!(value("optional"), Value::Null);
+    }
+
+    #[test]
+    fn reads_props_files_by_extension() {
+        let dir = std::env::temp_dir().join(format!("genify-props-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir should be created");
+        let yaml = dir.join("props.yaml");
+        fs::write(&yaml, "name: yaml\nports:\n  - 80\n").expect("test file should be written");
+        let toml = dir.join("props.toml");
+        fs::write(&toml, "name = \"toml\"\n").expect("test file should be written");
+
+        let args = PropsArgs {
+            props_files: vec![yaml, toml],
+            ..PropsArgs::default()
+        };
+        let overrides = args.overrides(&Map::new()).expect("files should parse");
+
+        assert_eq!(
+            overrides.props,
+            vec![
+                ("name".to_string(), Value::String("toml".to_string())),
+                ("ports".to_string(), Value::Array(vec![Value::Integer(80)])),
+            ]
+        );
+        assert_eq!(overrides.sources["ports"], PropLayer::PropsFile);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
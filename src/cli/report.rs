@@ -0,0 +1,83 @@
+use clap::error::ErrorKind;
+use genify::{
+    generation::{CoreError, Diagnostic, DiagnosticSeverity},
+    location::{ConfigLocation, SourceMap},
+};
+
+/// `severity[code]: message (path)`, followed by where the diagnostic comes
+/// from in the config and a snippet of it when `source` maps it.
+pub fn diagnostic(diagnostic: &Diagnostic, source: Option<&SourceMap>) -> String {
+    let severity = match diagnostic.severity {
+        DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Error => "error",
+    };
+    let mut report = match &diagnostic.path {
+        Some(path) => format!(
+            "{severity}[{}]: {} ({path})",
+            diagnostic.code, diagnostic.message
+        ),
+        None => format!("{severity}[{}]: {}", diagnostic.code, diagnostic.message),
+    };
+    if let Some(location) = &diagnostic.location {
+        report.push_str(&pointer(location, source));
+    }
+    report
+}
+
+/// A core error, pointing into the config when it comes from a rule or prop.
+pub fn core_error(err: CoreError, source: Option<&SourceMap>) -> clap::Error {
+    let mut message = format!("Failed to process config: {err}");
+    if let Some(location) = err.location() {
+        message.push_str(&pointer(location, source));
+    }
+    clap::Error::raw(ErrorKind::InvalidValue, message)
+}
+
+/// A rendering error, pointing into the config when it comes from a rule or prop.
+pub fn render_error(err: genify::Error, source: &SourceMap) -> clap::Error {
+    let mut message = format!("Failed to process config: {err}");
+    if let Some(location) = err.location() {
+        message.push_str(&pointer(location, Some(source)));
+    }
+    clap::Error::raw(ErrorKind::InvalidValue, message)
+}
+
+/// Lines to append to a message: ` --> file:line:column (rules[i], type=…)`
+/// and, when the line is known, the source line with a caret under the column.
+pub fn pointer(location: &ConfigLocation, source: Option<&SourceMap>) -> String {
+    let location = match source {
+        Some(source) => source.resolve(location),
+        None => location.clone(),
+    };
+    let line = location
+        .line
+        .and_then(|line| Some((line, source?.line_text(line)?)));
+    let Some((line, text)) = line else {
+        return format!("\n  --> {location}");
+    };
+    let gutter = " ".repeat(line.to_string().len());
+    let caret = " ".repeat(location.column.unwrap_or(1).saturating_sub(1));
+    format!("\n{gutter}--> {location}\n{gutter} |\n{line} | {text}\n{gutter} | {caret}^")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_at_the_rule_with_a_snippet() {
+        let source = SourceMap::toml(
+            "config.toml",
+            "[[rules]]\ntype = \"mkdir\"\npath = \"a\"\n\n  [[rules]]\n  type = \"delete\"\n  path = \"b\"\n",
+        );
+
+        assert_eq!(
+            pointer(&ConfigLocation::rule(1, Some("delete")), Some(&source)),
+            "\n --> config.toml:5:3 (rules[1], type=delete)\n  |\n5 |   [[rules]]\n  |   ^"
+        );
+        assert_eq!(
+            pointer(&ConfigLocation::rule(1, Some("delete")), None),
+            "\n  --> rules[1], type=delete"
+        );
+    }
+}
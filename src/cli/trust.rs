@@ -0,0 +1,236 @@
+use std::{
+    env, fmt, fs,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+};
+
+use minisign_verify::{Error as MinisignError, PublicKey, Signature};
+use serde::Deserialize;
+
+/// Appended to a config's path or URL to find its detached minisign signature.
+pub const SIGNATURE_SUFFIX: &str = ".minisig";
+
+/// Keys whose signatures are trusted, and what to do with remote configs none
+/// of them signed. Read from [`trust_file`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustStore {
+    #[serde(default)]
+    pub policy: TrustPolicy,
+    #[serde(default)]
+    pub keys: Vec<TrustedKey>,
+}
+
+/// What to do with an http(s) or git config that no trusted key signed.
+/// Local files are never refused for being unsigned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrustPolicy {
+    /// Run it after a warning.
+    #[default]
+    Warn,
+    /// Ask first, and refuse when there is no terminal to ask on.
+    Prompt,
+    /// Refuse it.
+    Require,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrustedKey {
+    /// Shown as the signer of the configs the key signed.
+    pub name: String,
+    /// The base64 line of a minisign `.pub` file.
+    pub public_key: String,
+}
+
+/// Who signed a config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signer {
+    /// A key from the trust store, with the signature's trusted comment.
+    Trusted {
+        name: String,
+        comment: String,
+    },
+    /// A key that is not in the trust store.
+    Unknown,
+    Unsigned,
+}
+
+impl fmt::Display for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trusted { name, comment } if comment.is_empty() => f.write_str(name),
+            Self::Trusted { name, comment } => {
+                write!(f, "{name} ({})", comment.replace('\t', " "))
+            }
+            Self::Unknown => f.write_str("a key that is not in the trust store"),
+            Self::Unsigned => f.write_str("nobody, the config is not signed"),
+        }
+    }
+}
+
+/// `$GENIFY_TRUST_FILE`, or `genify/trust.toml` in the user's config directory.
+pub fn trust_file() -> PathBuf {
+    if let Some(file) = env::var_os("GENIFY_TRUST_FILE") {
+        return file.into();
+    }
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    config_dir.join("genify").join("trust.toml")
+}
+
+impl TrustStore {
+    /// Reads [`trust_file`]; a missing file is an empty store.
+    pub fn load() -> Result<Self, String> {
+        let file = trust_file();
+        match fs::read_to_string(&file) {
+            Ok(raw) => toml::from_str(&raw)
+                .map_err(|err| format!("invalid trust store {}: {err}", file.display())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("failed to read {}: {err}", file.display())),
+        }
+    }
+
+    /// Who signed `content`, given its detached minisign `signature`. Fails
+    /// when the signature cannot be read or was made by a trusted key over
+    /// different content, since that means the config was tampered with.
+    pub fn signer(&self, content: &[u8], signature: Option<&str>) -> Result<Signer, String> {
+        let Some(signature) = signature else {
+            return Ok(Signer::Unsigned);
+        };
+        let signature = Signature::decode(signature)
+            .map_err(|err| format!("invalid minisign signature: {err}"))?;
+        for key in &self.keys {
+            let public_key = PublicKey::from_base64(&key.public_key).map_err(|err| {
+                format!(
+                    "invalid public key `{}` in the trust store: {err}",
+                    key.name
+                )
+            })?;
+            match public_key.verify(content, &signature, false) {
+                Ok(()) => {
+                    return Ok(Signer::Trusted {
+                        name: key.name.clone(),
+                        comment: signature.trusted_comment().to_string(),
+                    });
+                }
+                Err(MinisignError::UnexpectedKeyId) => continue,
+                Err(err) => {
+                    return Err(format!(
+                        "signature by {} does not match the config: {err}",
+                        key.name
+                    ));
+                }
+            }
+        }
+        Ok(Signer::Unknown)
+    }
+
+    /// Applies the policy to the remote config `label` signed by `signer`.
+    pub fn admit(&self, label: &str, signer: &Signer) -> Result<(), String> {
+        if matches!(signer, Signer::Trusted { .. }) {
+            return Ok(());
+        }
+        let refusal = |why: &str| {
+            format!(
+                "{label} is not signed by a trusted key and {why}; sign it or change the policy in {}",
+                trust_file().display()
+            )
+        };
+        match self.policy {
+            TrustPolicy::Warn => {
+                eprintln!("warning: {label} is not signed by a trusted key");
+                Ok(())
+            }
+            TrustPolicy::Require => Err(refusal("the trust policy requires signatures")),
+            TrustPolicy::Prompt if !io::stdin().is_terminal() => {
+                Err(refusal("there is no terminal to confirm running it"))
+            }
+            TrustPolicy::Prompt => {
+                eprint!("{label} is not signed by a trusted key. Run it anyway? [y/N] ");
+                let _ = io::stderr().flush();
+                let mut answer = String::new();
+                let _ = io::stdin().read_line(&mut answer);
+                if matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+                    Ok(())
+                } else {
+                    Err(refusal("running it was declined"))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLATFORM_KEY: &str = "RWQHAQIDBAUGB+pKbGPinFIKvvVQexMuxfmVR3auvr57kkIe6mkURtIs";
+    const OTHER_KEY: &str = "RWQJAQIDBAUGB/0XJDhaoMdbZPt4zWAvodmR/ev3axPFjtcC6sg16fYY";
+    const CONFIG: &str = "[[rules]]\ntype = \"mkdir\"\npath = \"signed\"\n";
+    /// Signature of `CONFIG` by `PLATFORM_KEY`.
+    const SIGNATURE: &str = "untrusted comment: signature from genify test key
+RUQHAQIDBAUGB0aXQ8FnTC2X0uxzhi18ikSVGDGMdiGE7eKEE8TqqwtKsMHvHRCrXpDP8GtV6tvZWBRhqZGnnEaTk9Fj/PnIAAs=
+trusted comment: timestamp:1760000000\tfile:genify.toml
+RPhqvhVK5HUEPUcbq3VcpShTBu1/9sWmAqJt8kAnRwEp3uXCMZg0Zg0rxso7+MyGN8ni/N3oQAHXWAu9NQ6JBA==
+";
+
+    fn store(policy: TrustPolicy, keys: &[(&str, &str)]) -> TrustStore {
+        TrustStore {
+            policy,
+            keys: keys
+                .iter()
+                .map(|(name, public_key)| TrustedKey {
+                    name: name.to_string(),
+                    public_key: public_key.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn names_trusted_signers_and_refuses_tampered_configs() {
+        let trusted = store(
+            TrustPolicy::Require,
+            &[("other", OTHER_KEY), ("platform", PLATFORM_KEY)],
+        );
+
+        let signer = trusted
+            .signer(CONFIG.as_bytes(), Some(SIGNATURE))
+            .expect("signature should verify");
+        assert_eq!(
+            signer.to_string(),
+            "platform (timestamp:1760000000 file:genify.toml)"
+        );
+        assert!(trusted.admit("remote.toml", &signer).is_ok());
+        assert!(
+            trusted
+                .signer(
+                    b"[[rules]]\ntype = \"delete\"\npath = \"src\"\n",
+                    Some(SIGNATURE)
+                )
+                .unwrap_err()
+                .contains("signature by platform does not match")
+        );
+
+        let untrusted = store(TrustPolicy::Require, &[("other", OTHER_KEY)]);
+        assert_eq!(
+            untrusted.signer(CONFIG.as_bytes(), Some(SIGNATURE)),
+            Ok(Signer::Unknown)
+        );
+        assert_eq!(
+            untrusted.signer(CONFIG.as_bytes(), None),
+            Ok(Signer::Unsigned)
+        );
+        assert!(untrusted.admit("remote.toml", &Signer::Unknown).is_err());
+        assert!(
+            store(TrustPolicy::Warn, &[])
+                .admit("remote.toml", &Signer::Unsigned)
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use genify::generation::PreviewOutput;
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{self, RecursiveMode},
+};
+use similar::TextDiff;
+
+/// Marks a directory `genify watch --scratch` may empty before every render.
+const SCRATCH_MARKER: &str = ".genify-scratch";
+
+/// Generated content of every file a render changes, `None` for deletions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rendered(BTreeMap<String, Option<String>>);
+
+impl From<&PreviewOutput> for Rendered {
+    fn from(preview: &PreviewOutput) -> Self {
+        Self(
+            preview
+                .files
+                .iter()
+                .map(|file| (file.path.clone(), file.content.clone()))
+                .collect(),
+        )
+    }
+}
+
+impl Rendered {
+    /// What `self` generates differently from the `previous` render: a diff
+    /// of the generated content per file, or a note when a file starts or
+    /// stops being generated or deleted. Empty when nothing changed.
+    pub fn changes_since(&self, previous: &Rendered) -> String {
+        let paths = previous
+            .0
+            .keys()
+            .chain(self.0.keys())
+            .collect::<BTreeSet<_>>();
+        let mut changes = String::new();
+        for path in paths {
+            let before = previous.0.get(path);
+            let after = self.0.get(path);
+            if before == after {
+                continue;
+            }
+            match (before, after) {
+                (Some(Some(before)), Some(Some(after))) => changes.push_str(
+                    &TextDiff::from_lines(before, after)
+                        .unified_diff()
+                        .header(&format!("a/{path}"), &format!("b/{path}"))
+                        .to_string(),
+                ),
+                (_, None) => changes.push_str(&format!("{path}: no longer generated\n")),
+                (_, Some(None)) => changes.push_str(&format!("{path}: now deleted\n")),
+                (_, Some(Some(after))) => changes.push_str(
+                    &TextDiff::from_lines("", after.as_str())
+                        .unified_diff()
+                        .header("/dev/null", &format!("b/{path}"))
+                        .to_string(),
+                ),
+            }
+        }
+        changes
+    }
+}
+
+/// Empties `dir` for a fresh render, creating it when missing. Refuses a
+/// non-empty directory that was not created as a scratch root, so a typo
+/// cannot wipe a real project.
+pub fn reset_scratch(dir: &Path) -> io::Result<()> {
+    if dir.exists() {
+        let is_empty = fs::read_dir(dir)?.next().is_none();
+        if !is_empty && !dir.join(SCRATCH_MARKER).is_file() {
+            return Err(io::Error::other(format!(
+                "{} is not empty and was not created by genify watch",
+                dir.display()
+            )));
+        }
+        fs::remove_dir_all(dir)?;
+    }
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(SCRATCH_MARKER), "")
+}
+
+/// Calls `on_change` with the changed files whenever the content of any of
+/// `files` changes, including when it is created or removed, waiting until
+/// changes have been quiet for `debounce`. The parent directories are watched
+/// rather than the files, so editors that save by replacing the file are
+/// followed and files that do not exist yet are picked up. Events that leave
+/// the content as it was, such as genify reading the file, are ignored. Runs
+/// until the watcher fails.
+pub fn watch(
+    files: &[PathBuf],
+    debounce: Duration,
+    mut on_change: impl FnMut(&[PathBuf]),
+) -> notify::Result<()> {
+    let mut contents = files
+        .iter()
+        .map(|file| {
+            let file = absolute(file);
+            let content = fs::read(&file).ok();
+            (file, content)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(debounce, sender)?;
+    let dirs = contents
+        .keys()
+        .filter_map(|file| file.parent())
+        .collect::<BTreeSet<_>>();
+    for dir in dirs.into_iter().filter(|dir| dir.is_dir()) {
+        debouncer
+            .watcher()
+            .watch(dir, RecursiveMode::NonRecursive)?;
+    }
+
+    for events in receiver {
+        let mut changed = BTreeSet::new();
+        for event in events? {
+            let path = absolute(&event.path);
+            if let Some(content) = contents.get_mut(&path) {
+                let current = fs::read(&path).ok();
+                if *content != current {
+                    *content = current;
+                    changed.insert(path);
+                }
+            }
+        }
+        if !changed.is_empty() {
+            on_change(&changed.into_iter().collect::<Vec<_>>());
+        }
+    }
+    Ok(())
+}
+
+/// `path` made absolute with its directory resolved, so watched paths compare
+/// equal to event paths whatever form either is given in.
+fn absolute(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|parent| parent.join(name))
+            .unwrap_or(path),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendered(files: &[(&str, Option<&str>)]) -> Rendered {
+        Rendered(
+            files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.map(str::to_string)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn reports_only_what_changed_since_the_last_render() {
+        let previous = rendered(&[
+            ("same.txt", Some("same\n")),
+            ("edited.txt", Some("a\nb\n")),
+            ("dropped.txt", Some("x\n")),
+        ]);
+        let current = rendered(&[
+            ("same.txt", Some("same\n")),
+            ("edited.txt", Some("a\nc\n")),
+            ("old.txt", None),
+            ("new.txt", Some("new\n")),
+        ]);
+
+        assert_eq!(
+            current.changes_since(&previous),
+            "dropped.txt: no longer generated\n\
+             --- a/edited.txt\n+++ b/edited.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+c\n\
+             --- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+new\n\
+             old.txt: now deleted\n"
+        );
+        assert_eq!(current.changes_since(&current), "");
+    }
+
+    #[test]
+    fn only_clears_scratch_roots_it_created() {
+        let dir = std::env::temp_dir().join(format!("genify-scratch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        reset_scratch(&dir).expect("missing scratch root should be created");
+        fs::write(dir.join("generated.txt"), "x").expect("test file should be written");
+        reset_scratch(&dir).expect("scratch root should be cleared");
+        assert!(!dir.join("generated.txt").exists());
+
+        fs::remove_file(dir.join(SCRATCH_MARKER)).expect("test file should be removed");
+        fs::write(dir.join("project.txt"), "x").expect("test file should be written");
+        assert!(reset_scratch(&dir).is_err());
+        assert!(dir.join("project.txt").exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -1,9 +1,47 @@
+use crate::location::ConfigLocation;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[error("template rendering failed: {0}")]
+    #[error("template rendering failed: {}", tera_message(.0))]
     Tera(tera::Error),
     #[error("I/O error: {0}")]
     IOError(std::io::Error),
     #[error("{0}")]
     Operation(String),
+    #[error("props form a dependency cycle: {}", .0.join(" -> "))]
+    PropsCycle(Vec<String>),
+    #[error("{location}: {source}")]
+    Located {
+        location: Box<ConfigLocation>,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Attaches the rule or prop the error comes from.
+    pub fn at(self, location: ConfigLocation) -> Self {
+        Self::Located {
+            location: Box::new(location),
+            source: Box::new(self),
+        }
+    }
+
+    pub fn location(&self) -> Option<&ConfigLocation> {
+        match self {
+            Self::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+}
+
+/// Tera wraps the useful part of an error, such as the missing variable, in
+/// its sources; report the whole chain.
+fn tera_message(err: &tera::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(err) = source {
+        message.push_str(&format!(": {err}"));
+        source = err.source();
+    }
+    message
 }
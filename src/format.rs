@@ -0,0 +1,216 @@
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+use crate::FormatWith;
+
+/// Built-in formatters and the extensions `auto` picks them for.
+const BUILTINS: &[(&str, &[&str])] = &[
+    ("rustfmt", &["rs"]),
+    ("gofmt", &["go"]),
+    (
+        "prettier",
+        &[
+            "js", "jsx", "mjs", "ts", "tsx", "json", "css", "scss", "html", "vue", "md", "yaml",
+            "yml",
+        ],
+    ),
+    ("black", &["py", "pyi"]),
+    ("shfmt", &["sh", "bash"]),
+    ("terraform", &["tf", "tfvars"]),
+];
+
+/// Whether `name` is `auto` or a built-in formatter.
+pub(crate) fn is_builtin(name: &str) -> bool {
+    name == "auto" || BUILTINS.iter().any(|(builtin, _)| *builtin == name)
+}
+
+pub(crate) fn builtin_names() -> String {
+    let names: Vec<_> = BUILTINS.iter().map(|(name, _)| *name).collect();
+    names.join(", ")
+}
+
+/// The command `format_with` runs for the file at `path`, relative to
+/// `root`, or `None` when `auto` knows no formatter for its extension.
+pub(crate) fn command(format_with: &FormatWith, path: &str, root: &Path) -> Option<Vec<String>> {
+    let name = match format_with {
+        FormatWith::Command(command) => return Some(command.clone()),
+        FormatWith::Builtin(name) if name == "auto" => {
+            let extension = Path::new(path).extension()?.to_str()?;
+            BUILTINS
+                .iter()
+                .find(|(_, extensions)| extensions.contains(&extension))?
+                .0
+        }
+        FormatWith::Builtin(name) => name.as_str(),
+    };
+    let command: &[&str] = match name {
+        "rustfmt" => return Some(rustfmt(path, root)),
+        "gofmt" => &["gofmt"],
+        "prettier" => &["prettier", "--stdin-filepath", path],
+        "black" => &["black", "--quiet", "-"],
+        "shfmt" => &["shfmt"],
+        "terraform" => &["terraform", "fmt", "-"],
+        _ => return None,
+    };
+    Some(command.iter().map(|arg| arg.to_string()).collect())
+}
+
+/// rustfmt reads stdin with its defaults, so it is pointed at the
+/// `rustfmt.toml` nearest to the file, or else given the edition of the
+/// nearest `Cargo.toml` that names one.
+fn rustfmt(path: &str, root: &Path) -> Vec<String> {
+    let mut command = vec!["rustfmt".to_string()];
+    let file = root.join(path);
+    let dirs = file
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root));
+    for dir in dirs.clone() {
+        if let Some(config) = ["rustfmt.toml", ".rustfmt.toml"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|config| config.is_file())
+        {
+            command.extend(["--config-path".to_string(), config.display().to_string()]);
+            return command;
+        }
+    }
+    let edition = dirs
+        .filter_map(|dir| fs::read_to_string(dir.join("Cargo.toml")).ok())
+        .filter_map(|raw| toml::from_str::<toml::Table>(&raw).ok())
+        .find_map(|manifest| {
+            // A member that inherits its edition leaves it to the workspace.
+            let package = manifest.get("package");
+            let workspace = manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package"));
+            [package, workspace]
+                .into_iter()
+                .flatten()
+                .find_map(|package| package.get("edition")?.as_str().map(str::to_string))
+        });
+    if let Some(edition) = edition {
+        command.extend(["--edition".to_string(), edition]);
+    }
+    command
+}
+
+/// Pipes `content` through `command`, run in `dir`, and returns its output.
+pub(crate) fn run(
+    command: &[String],
+    path: &str,
+    dir: &Path,
+    content: &str,
+) -> Result<String, String> {
+    let program = &command[0];
+    let mut child = Command::new(program)
+        .args(&command[1..])
+        .current_dir(dir)
+        .env("GENIFY_FORMAT_PATH", path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to run {program}: {err}"))?;
+    // Fed from another thread, so a formatter that writes before it has read
+    // everything cannot block on a full pipe.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = content.to_string();
+    let feeder = thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("failed to run {program}: {err}"))?;
+    let _ = feeder.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{program} failed to format the file: {}",
+            stderr.trim()
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{program} did not write UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_builtins_by_extension() {
+        let auto = FormatWith::Builtin("auto".to_string());
+        let root = std::env::temp_dir().join(format!("genify-format-{}", std::process::id()));
+        fs::create_dir_all(&root).expect("test dir should be created");
+        assert_eq!(
+            command(&auto, "src/main.rs", &root),
+            Some(vec!["rustfmt".into()])
+        );
+        assert_eq!(
+            command(&auto, "web/app.tsx", &root),
+            Some(vec![
+                "prettier".into(),
+                "--stdin-filepath".into(),
+                "web/app.tsx".into()
+            ])
+        );
+        assert_eq!(command(&auto, "README", &root), None);
+        assert!(is_builtin("gofmt") && !is_builtin("gofmt2"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn rustfmt_follows_the_project_config() {
+        let root = std::env::temp_dir().join(format!("genify-rustfmt-{}", std::process::id()));
+        fs::create_dir_all(root.join("crates/core/src")).expect("test dir should be created");
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace.package]\nedition = \"2024\"\n",
+        )
+        .expect("test file should be written");
+        fs::write(
+            root.join("crates/core/Cargo.toml"),
+            "[package]\nedition.workspace = true\n",
+        )
+        .expect("test file should be written");
+        assert_eq!(
+            rustfmt("crates/core/src/lib.rs", &root),
+            ["rustfmt", "--edition", "2024"]
+        );
+
+        let config = root.join("crates/rustfmt.toml");
+        fs::write(&config, "edition = \"2018\"\n").expect("test file should be written");
+        assert_eq!(
+            rustfmt("crates/core/src/lib.rs", &root),
+            [
+                "rustfmt".to_string(),
+                "--config-path".to_string(),
+                config.display().to_string()
+            ]
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipes_content_through_the_command() {
+        let upper = ["tr".to_string(), "a-z".to_string(), "A-Z".to_string()];
+        let dir = std::env::temp_dir();
+        assert_eq!(
+            run(&upper, "a.txt", &dir, "hello\n").as_deref(),
+            Ok("HELLO\n")
+        );
+        let failing = [
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo bad >&2; exit 1".to_string(),
+        ];
+        assert_eq!(
+            run(&failing, "a.txt", &dir, ""),
+            Err("sh failed to format the file: bad".to_string())
+        );
+    }
+}
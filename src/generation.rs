@@ -3,18 +3,36 @@ use std::{
     collections::{BTreeMap, BTreeSet},
     fs, io,
     path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
 };
 
+use globset::{GlobBuilder, GlobMatcher, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value as JsonValue, json};
 use similar::{ChangeTag, TextDiff};
 use thiserror::Error;
 
 use crate::{
-    Config, Error as GenifyError, IfExists, Rule, Value, parse_toml, render_config_props,
-    render_config_rules,
+    Config, Error as GenifyError, FormatWith, Formatter, Hook, HookWhen, IfExists, Map, Rule,
+    Value,
+    catalog::{CATALOG_FILE, Catalog},
+    format,
+    git::Worktree,
+    lint,
+    location::ConfigLocation,
+    manifest::{
+        BASELINE_DIR, MANIFEST_PATH, Manifest, ManifestFile, ManifestSource, ORIGINAL_DIR,
+        content_hash,
+    },
+    merge::merge3,
+    parse_toml, render_config_props, render_config_rules,
+    walk::{self, IgnoredPaths},
+    write_rule_content,
 };
 
+/// Per-project prop overrides, relative to the generation root.
+pub const PROJECT_PROPS_PATH: &str = ".genify/props.toml";
+
 #[derive(Debug, Error)]
 pub enum CoreError {
     #[error("root must be an existing directory: {path}")]
@@ -29,8 +47,12 @@ pub enum CoreError {
     InvalidPath { path: String },
     #[error("config is required")]
     MissingConfig,
-    #[error("invalid config in {label}: {message}")]
-    InvalidConfig { label: String, message: String },
+    #[error("invalid config in {label}: {}{message}", located_prefix(.location.as_deref()))]
+    InvalidConfig {
+        label: String,
+        message: String,
+        location: Option<Box<ConfigLocation>>,
+    },
     #[error("path is not a file: {path}")]
     NotAFile { path: String },
     #[error("path is not a directory: {path}")]
@@ -61,16 +83,81 @@ pub enum CoreError {
     },
     #[error("failed to render config: {0}")]
     Render(#[from] GenifyError),
+    #[error("manifest not found at {path}; apply a config before updating")]
+    MissingManifest { path: String },
+    #[error("failed to parse manifest {path}: {source}")]
+    ParseManifest {
+        path: String,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("failed to serialize manifest: {0}")]
+    SerializeManifest(#[from] toml::ser::Error),
     #[error("genify_apply requires explicit approval")]
     ApprovalRequired,
     #[error("server is running in read-only mode")]
     ReadOnly,
+    #[error("root cannot be combined with roots; pass every root in roots")]
+    RootWithRoots,
+    #[error("{message}")]
+    Git { message: String },
+    #[error("invalid protected glob {glob}: {message}")]
+    InvalidGlob { glob: String, message: String },
+    #[error("{location}: {source}")]
+    Located {
+        location: Box<ConfigLocation>,
+        source: Box<CoreError>,
+    },
+}
+
+impl CoreError {
+    /// Attaches the rule or prop the error comes from.
+    fn at(self, location: ConfigLocation) -> Self {
+        match self {
+            Self::Located { .. }
+            | Self::InvalidConfig {
+                location: Some(_), ..
+            } => self,
+            Self::InvalidConfig {
+                label,
+                message,
+                location: None,
+            } => Self::InvalidConfig {
+                label,
+                message,
+                location: Some(Box::new(location)),
+            },
+            _ => Self::Located {
+                location: Box::new(location),
+                source: Box::new(self),
+            },
+        }
+    }
+
+    /// The rule or prop of the config the error comes from, if known.
+    pub fn location(&self) -> Option<&ConfigLocation> {
+        match self {
+            Self::Located { location, .. } => Some(location),
+            Self::InvalidConfig { location, .. } => location.as_deref(),
+            Self::Render(err) => err.location(),
+            _ => None,
+        }
+    }
+}
+
+fn located_prefix(location: Option<&ConfigLocation>) -> String {
+    location
+        .map(|location| format!("{location}: "))
+        .unwrap_or_default()
 }
 
 #[derive(Debug, Clone)]
 pub struct GenerationCore {
     sandbox: PathSandbox,
     read_only: bool,
+    allow_hooks: bool,
+    protect_ignored: bool,
+    protected: GlobSet,
 }
 
 impl GenerationCore {
@@ -82,9 +169,47 @@ impl GenerationCore {
         Ok(Self {
             sandbox: PathSandbox::new(root)?,
             read_only,
+            allow_hooks: false,
+            protect_ignored: false,
+            protected: GlobSet::empty(),
         })
     }
 
+    /// Lets [`GenerationCore::apply`] run the config's hooks, and every
+    /// method that renders files run its formatters, built-ins included. Both
+    /// are off by default, so a config handed to a core cannot run programs
+    /// unless whoever set the core up asked for it; skipped hooks and
+    /// formatters are reported as a warning instead.
+    pub fn allow_hooks(mut self, allow: bool) -> Self {
+        self.allow_hooks = allow;
+        self
+    }
+
+    /// Makes [`GenerationCore::apply`] refuse configs that write to paths
+    /// matching one of the `protected` globs, relative to the root, and, when
+    /// `ignored` is set, to paths that `.gitignore` files, git's excludes or a
+    /// `.genifyignore` ignore. Meant for servers that apply configs on behalf
+    /// of agents.
+    pub fn protect_paths(mut self, ignored: bool, protected: &[String]) -> Result<Self, CoreError> {
+        let mut globs = GlobSetBuilder::new();
+        for glob in protected {
+            let glob = GlobBuilder::new(glob)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| CoreError::InvalidGlob {
+                    glob: glob.clone(),
+                    message: err.kind().to_string(),
+                })?;
+            globs.add(glob);
+        }
+        self.protect_ignored = ignored;
+        self.protected = globs.build().map_err(|err| CoreError::InvalidGlob {
+            glob: err.glob().unwrap_or_default().to_string(),
+            message: err.kind().to_string(),
+        })?;
+        Ok(self)
+    }
+
     pub fn root(&self) -> &Path {
         self.sandbox.root()
     }
@@ -100,6 +225,7 @@ impl GenerationCore {
         if !plan.errors.is_empty() {
             return Ok(DiffOutput {
                 diff: String::new(),
+                files: Vec::new(),
                 summary: ChangeSummary::empty(),
                 warnings: plan.warnings,
                 errors: plan.errors,
@@ -110,31 +236,150 @@ impl GenerationCore {
         Ok(simulation.diff_output(plan.warnings))
     }
 
-    pub fn apply(&self, input: ApplyRequest) -> Result<ApplyOutput, CoreError> {
-        if self.read_only {
-            return Err(CoreError::ReadOnly);
+    /// Runs generation in memory and returns the content each changed file
+    /// would have after applying, without writing anything.
+    pub fn preview(&self, input: GenerationRequest) -> Result<PreviewOutput, CoreError> {
+        let prepared = self.prepare(input)?;
+        let plan = prepared.plan_output();
+        if !plan.errors.is_empty() {
+            return Ok(PreviewOutput {
+                files: Vec::new(),
+                warnings: plan.warnings,
+                errors: plan.errors,
+            });
         }
-        if !input.is_approved() {
-            return Err(CoreError::ApprovalRequired);
+
+        let simulation = self.simulate(&prepared)?;
+        let mut warnings = plan.warnings;
+        warnings.extend(simulation.warnings.clone());
+        if !simulation.errors.is_empty() {
+            return Ok(PreviewOutput {
+                files: Vec::new(),
+                warnings,
+                errors: simulation.errors,
+            });
+        }
+        Ok(PreviewOutput {
+            files: simulation
+                .changed_files()
+                .into_iter()
+                .map(|file| PreviewFile {
+                    path: file.relative_path.clone(),
+                    content: (!file.deleted).then(|| file.current.clone()),
+                })
+                .collect(),
+            warnings,
+            errors: Vec::new(),
+        })
+    }
+
+    pub fn apply(&self, input: ApplyRequest) -> Result<ApplyOutput, CoreError> {
+        self.check_apply(&input)?;
+
+        // The branch is switched first, so the rules see the files on it.
+        let mut git = self.start_git(&input)?;
+        let result = self.apply_on_branch(&input, git.as_mut());
+        let Some(mut git) = git else {
+            return result;
+        };
+        // A run that failed without changing anything leaves the worktree on
+        // the branch it was on, and removes the branch if it created it.
+        let refused = result.as_ref().map_or(true, |output| {
+            output.changed_files.is_empty() && !output.errors.is_empty()
+        });
+        let left = if refused { git.switch_back() } else { Ok(()) };
+        let mut output = result?;
+        if let Err(message) = left {
+            output
+                .errors
+                .push(Diagnostic::error("git_failed", message, None::<String>));
         }
+        output.git = Some(git.output);
+        Ok(output)
+    }
 
+    /// Applies the request on the branch `git` switched to, if any. The
+    /// caller reports what was done in the worktree.
+    fn apply_on_branch(
+        &self,
+        input: &ApplyRequest,
+        git: Option<&mut GitRun>,
+    ) -> Result<ApplyOutput, CoreError> {
         let prepared = self.prepare(input.generation_request())?;
         let plan = prepared.plan_output();
         if !plan.errors.is_empty() {
             return Ok(ApplyOutput {
                 changed_files: Vec::new(),
                 summary: "No files changed because generation has errors.".to_string(),
+                manifest: None,
+                hooks: Vec::new(),
+                git: None,
                 warnings: plan.warnings,
                 errors: plan.errors,
             });
         }
+        let errors = self.protected_writes(&prepared);
+        if !errors.is_empty() {
+            return Ok(ApplyOutput {
+                changed_files: Vec::new(),
+                summary: "No files changed because the config writes to protected paths."
+                    .to_string(),
+                manifest: None,
+                hooks: Vec::new(),
+                git: None,
+                warnings: plan.warnings,
+                errors,
+            });
+        }
+        if let Some(git) = &git {
+            let errors = self.uncommitted_changes(&prepared, &git.worktree)?;
+            if !errors.is_empty() {
+                return Ok(ApplyOutput {
+                    changed_files: Vec::new(),
+                    summary: "No files changed because the worktree has uncommitted changes in files this run would change.".to_string(),
+                    manifest: None,
+                    hooks: Vec::new(),
+                    git: None,
+                    warnings: plan.warnings,
+                    errors,
+                });
+            }
+        }
+
+        let mut warnings = plan.warnings;
+        let mut hooks = Vec::new();
+        if !self.allow_hooks && !prepared.hooks.is_empty() {
+            warnings.push(Diagnostic::warning(
+                "hooks_disabled",
+                format!(
+                    "{} hook(s) skipped because hooks are not allowed",
+                    prepared.hooks.len()
+                ),
+                None::<String>,
+            ));
+        }
+        // Before hooks run first, so the simulation sees whatever they change.
+        if let Some(error) = self.run_hooks(&prepared, HookWhen::Before, &mut hooks) {
+            return Ok(ApplyOutput {
+                changed_files: Vec::new(),
+                summary: "No files changed because a before hook failed.".to_string(),
+                manifest: None,
+                hooks,
+                git: None,
+                warnings,
+                errors: vec![error],
+            });
+        }
 
         let simulation = self.simulate(&prepared)?;
         if !simulation.errors.is_empty() {
             return Ok(ApplyOutput {
                 changed_files: Vec::new(),
                 summary: "No files changed because generation has errors.".to_string(),
-                warnings: plan.warnings,
+                manifest: None,
+                hooks,
+                git: None,
+                warnings,
                 errors: simulation.errors,
             });
         }
@@ -182,15 +427,393 @@ impl GenerationCore {
             }
         }
 
-        let summary = summarize_changed_files(changed_files.len());
+        let manifest = match input.manifest.clone().or_else(|| {
+            prepared
+                .baseline
+                .as_ref()
+                .map(|baseline| baseline.manifest.source.clone())
+        }) {
+            Some(source) => Some(self.write_manifest(&prepared, &simulation, source)?),
+            None => None,
+        };
+
+        let mut summary = summarize_changed_files(changed_files.len());
+        warnings.extend(simulation.warnings.iter().cloned());
+        let mut errors = Vec::new();
+        if let Some(error) = self.run_hooks(&prepared, HookWhen::After, &mut hooks) {
+            summary.push_str(" An after hook failed.");
+            errors.push(error);
+        }
+        // Only a run that went through completely is committed.
+        if let Some(git) = git
+            && errors.is_empty()
+        {
+            // Mode changes leave the content alone but are committed too.
+            let mut paths: Vec<_> = simulation
+                .changed_files()
+                .into_iter()
+                .map(|file| file.path.clone())
+                .chain(
+                    simulation
+                        .metadata_changes
+                        .iter()
+                        .filter(|change| matches!(change.kind, MetadataChangeKind::Chmod))
+                        .map(|change| change.path.clone()),
+                )
+                .collect();
+            paths.sort();
+            paths.dedup();
+            if !paths.is_empty() && manifest.is_some() {
+                paths.push(prepared.effective_root.join(MANIFEST_PATH));
+                for dir in [BASELINE_DIR, ORIGINAL_DIR] {
+                    let dir = prepared.effective_root.join(dir);
+                    if dir.is_dir() {
+                        paths.push(dir);
+                    }
+                }
+            }
+            let committed = match (paths.is_empty(), &input.git_commit) {
+                (true, _) => Ok(None),
+                (false, None) => git.worktree.stage(&paths).map(|()| None),
+                (false, Some(message)) => git
+                    .worktree
+                    .stage(&paths)
+                    .and_then(|()| git.worktree.commit(message, &paths)),
+            };
+            match committed {
+                Ok(commit) => {
+                    git.output.staged = paths
+                        .iter()
+                        .map(|path| self.sandbox.display_path(path))
+                        .collect();
+                    git.output.commit = commit;
+                }
+                Err(message) => {
+                    summary.push_str(" They were not committed.");
+                    errors.push(Diagnostic::error("git_failed", message, None::<String>));
+                }
+            }
+        }
         Ok(ApplyOutput {
             changed_files,
             summary,
-            warnings: plan.warnings,
-            errors: Vec::new(),
+            manifest,
+            hooks,
+            git: None,
+            warnings,
+            errors,
+        })
+    }
+
+    /// Opens the worktree around the request's root when the request asks to
+    /// branch or commit, and switches to the requested branch.
+    fn start_git(&self, input: &ApplyRequest) -> Result<Option<GitRun>, CoreError> {
+        if input.git_branch.is_none() && input.git_commit.is_none() {
+            return Ok(None);
+        }
+        let root = self
+            .sandbox
+            .resolve_existing_dir(input.root.as_deref().unwrap_or("."))?;
+        let git_error = |message| CoreError::Git { message };
+        let worktree = Worktree::open(&root).map_err(git_error)?;
+        let mut output = GitOutput {
+            branch: input.git_branch.clone(),
+            created_branch: false,
+            staged: Vec::new(),
+            commit: None,
+        };
+        let mut previous = None;
+        if let Some(branch) = &input.git_branch {
+            previous = Some(worktree.head().map_err(git_error)?);
+            output.created_branch = worktree.switch(branch).map_err(git_error)?;
+        }
+        Ok(Some(GitRun {
+            worktree,
+            output,
+            previous,
+        }))
+    }
+
+    /// Errors for the paths the run would write, or remove, that the core
+    /// protects.
+    fn protected_writes(&self, prepared: &PreparedGeneration) -> Vec<Diagnostic> {
+        if !self.protect_ignored && self.protected.is_empty() {
+            return Vec::new();
+        }
+        let ignored = self
+            .protect_ignored
+            .then(|| IgnoredPaths::new(self.sandbox.root()));
+        let mut paths = BTreeMap::new();
+        for operation in &prepared.operations {
+            // A copy leaves its source alone.
+            let source = operation
+                .source_path
+                .as_ref()
+                .filter(|_| operation.kind != FileOperationKind::Copy);
+            for path in [Some(&operation.path), source].into_iter().flatten() {
+                paths
+                    .entry(path)
+                    .or_insert_with(|| Some(operation.location.clone()));
+            }
+        }
+        // An update also rewrites the files it regenerates or restores.
+        if let Some(baseline) = &prepared.baseline {
+            for path in baseline.regenerate.iter().chain(baseline.restore.keys()) {
+                paths.entry(path).or_insert(None);
+            }
+        }
+
+        let mut errors = Vec::new();
+        for (path, location) in paths {
+            let relative = path.strip_prefix(self.sandbox.root()).unwrap_or(path);
+            let (code, message) = if relative
+                .ancestors()
+                .any(|path| !path.as_os_str().is_empty() && self.protected.is_match(path))
+            {
+                ("protected_path", "path is protected and cannot be written")
+            } else if ignored
+                .as_ref()
+                .is_some_and(|ignored| ignored.is_ignored(path))
+            {
+                (
+                    "ignored_path",
+                    "path is ignored by .gitignore or .genifyignore and cannot be written",
+                )
+            } else {
+                continue;
+            };
+            errors.push(
+                Diagnostic::error(code, message, Some(self.sandbox.display_path(path)))
+                    .at(location),
+            );
+        }
+        errors
+    }
+
+    /// Errors for the files the run would change that have uncommitted
+    /// changes, which would otherwise end up staged with the generated ones.
+    fn uncommitted_changes(
+        &self,
+        prepared: &PreparedGeneration,
+        worktree: &Worktree,
+    ) -> Result<Vec<Diagnostic>, CoreError> {
+        let mut paths: Vec<_> = prepared
+            .operations
+            .iter()
+            .flat_map(|operation| {
+                // A copy leaves its source alone.
+                let source = operation
+                    .source_path
+                    .as_ref()
+                    .filter(|_| operation.kind != FileOperationKind::Copy);
+                [
+                    Some(&operation.path),
+                    source,
+                    operation.target_path.as_ref(),
+                ]
+            })
+            .flatten()
+            .cloned()
+            .collect();
+        paths.push(prepared.effective_root.join(MANIFEST_PATH));
+        let uncommitted = worktree
+            .uncommitted(&paths)
+            .map_err(|message| CoreError::Git { message })?;
+        Ok(uncommitted
+            .iter()
+            .map(|path| {
+                Diagnostic::error(
+                    "uncommitted_changes",
+                    "file has uncommitted changes; commit or stash them first",
+                    Some(self.sandbox.display_path(path)),
+                )
+            })
+            .collect())
+    }
+
+    /// Runs the prepared hooks for `when` in order, when hooks are allowed,
+    /// and stops at the first one that fails, returning its error.
+    fn run_hooks(
+        &self,
+        prepared: &PreparedGeneration,
+        when: HookWhen,
+        reports: &mut Vec<HookReport>,
+    ) -> Option<Diagnostic> {
+        if !self.allow_hooks {
+            return None;
+        }
+        for hook in prepared.hooks.iter().filter(|hook| hook.when == when) {
+            let report = hook.run();
+            let failed = !report.success;
+            let message = match report.status {
+                Some(code) => format!(
+                    "{} hook `{}` exited with status {code}",
+                    when.name(),
+                    hook.command.join(" ")
+                ),
+                None => format!(
+                    "{} hook `{}` did not finish",
+                    when.name(),
+                    hook.command.join(" ")
+                ),
+            };
+            reports.push(report);
+            if failed {
+                return Some(
+                    Diagnostic::error("hook_failed", message, Some(hook.relative_cwd.clone()))
+                        .at(Some(hook.location.clone())),
+                );
+            }
+        }
+        None
+    }
+
+    /// Applies the same config and props to every root in `input.roots`. Roots
+    /// are resolved inside this core's sandbox and each gets a sandbox of its
+    /// own, so one root's rules cannot reach into another. A failing root does
+    /// not stop the others unless `input.fail_fast` is set.
+    pub fn apply_roots(&self, input: ApplyRequest) -> Result<RootsApplyOutput, CoreError> {
+        self.check_apply(&input)?;
+        apply_each_root(input, |raw| {
+            let root = self.sandbox.resolve_existing_dir(raw)?;
+            let mut core =
+                Self::with_read_only(root, self.read_only)?.allow_hooks(self.allow_hooks);
+            core.protect_ignored = self.protect_ignored;
+            core.protected = self.protected.clone();
+            Ok(core)
         })
     }
 
+    /// Like [`GenerationCore::apply_roots`], but takes the roots as given
+    /// instead of resolving them inside an enclosing sandbox. Meant for the
+    /// CLI, where the roots are whatever directories the user lists.
+    pub fn apply_to_roots(
+        input: ApplyRequest,
+        allow_hooks: bool,
+    ) -> Result<RootsApplyOutput, CoreError> {
+        if !input.is_approved() {
+            return Err(CoreError::ApprovalRequired);
+        }
+        apply_each_root(input, |root| Ok(Self::new(root)?.allow_hooks(allow_hooks)))
+    }
+
+    fn check_apply(&self, input: &ApplyRequest) -> Result<(), CoreError> {
+        if self.read_only {
+            return Err(CoreError::ReadOnly);
+        }
+        if !input.is_approved() {
+            return Err(CoreError::ApprovalRequired);
+        }
+        Ok(())
+    }
+
+    /// Loads the manifest recorded in `root`, relative to the sandbox root.
+    pub fn manifest(&self, root: Option<&str>) -> Result<Manifest, CoreError> {
+        let root = self.sandbox.resolve_existing_dir(root.unwrap_or("."))?;
+        self.read_manifest(&root)?
+            .ok_or_else(|| CoreError::MissingManifest {
+                path: self.sandbox.display_path(&root.join(MANIFEST_PATH)),
+            })
+    }
+
+    /// Loads the project props in `root`, relative to the sandbox root.
+    pub fn project_props(&self, root: Option<&str>) -> Result<Option<Map>, CoreError> {
+        let root = self.sandbox.resolve_existing_dir(root.unwrap_or("."))?;
+        self.read_project_props(&root)
+    }
+
+    /// Reads `.genify/props.toml` from the generation root. Its top-level keys
+    /// are props that override the config and the manifest.
+    fn read_project_props(&self, effective_root: &Path) -> Result<Option<Map>, CoreError> {
+        if !effective_root.join(PROJECT_PROPS_PATH).exists() {
+            return Ok(None);
+        }
+        let raw_path = self
+            .sandbox
+            .display_path(&effective_root.join(PROJECT_PROPS_PATH));
+        let path = self.sandbox.resolve_existing_file(&raw_path)?;
+        let raw = fs::read_to_string(&path).map_err(|source| CoreError::ReadFile {
+            path: raw_path.clone(),
+            source,
+        })?;
+        let table: toml::Table = toml::from_str(&raw).map_err(|source| CoreError::ParseToml {
+            path: raw_path,
+            source,
+        })?;
+        Ok(Some(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        ))
+    }
+
+    fn read_manifest(&self, effective_root: &Path) -> Result<Option<Manifest>, CoreError> {
+        let path = effective_root.join(MANIFEST_PATH);
+        let Some(raw) = read_optional_string(&path)? else {
+            return Ok(None);
+        };
+        Manifest::parse(&raw)
+            .map(Some)
+            .map_err(|source| CoreError::ParseManifest {
+                path: self.sandbox.display_path(&path),
+                source,
+            })
+    }
+
+    fn write_manifest(
+        &self,
+        prepared: &PreparedGeneration,
+        simulation: &Simulation,
+        source: ManifestSource,
+    ) -> Result<String, CoreError> {
+        let root = &prepared.effective_root;
+        let mut manifest = match &prepared.baseline {
+            Some(baseline) => baseline.manifest.clone(),
+            None => self
+                .read_manifest(root)?
+                .unwrap_or_else(|| Manifest::new(source.clone())),
+        };
+        manifest.genify_version = env!("CARGO_PKG_VERSION").to_string();
+        manifest.source = source;
+        manifest.set_props(&prepared.recorded_props);
+
+        for file in simulation.changed_files() {
+            let Ok(relative) = file.path.strip_prefix(root) else {
+                continue;
+            };
+            let baseline = root.join(BASELINE_DIR).join(relative);
+            let original = root.join(ORIGINAL_DIR).join(relative);
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if file.deleted {
+                manifest.files.remove(&relative);
+                remove_optional_file(&baseline)?;
+                remove_optional_file(&original)?;
+                continue;
+            }
+            let tracked = manifest.files.get(&relative);
+            let created = !file.original_existed || tracked.is_some_and(|entry| entry.created);
+            if !created && tracked.is_none() {
+                write_creating_parent(&original, &file.original)?;
+            }
+            // Merged files are tracked by their generated content, so local
+            // edits kept by the merge still count as edits on the next update.
+            let generated = file.generated.as_deref().unwrap_or(&file.current);
+            manifest.files.insert(
+                relative,
+                ManifestFile {
+                    hash: content_hash(generated.as_bytes()),
+                    created,
+                },
+            );
+            write_creating_parent(&baseline, generated)?;
+        }
+
+        let path = root.join(MANIFEST_PATH);
+        write_creating_parent(&path, &manifest.to_toml()?)?;
+        Ok(self.sandbox.display_path(&path))
+    }
+
     pub fn validate_config(
         &self,
         input: ValidateConfigRequest,
@@ -208,12 +831,12 @@ impl GenerationCore {
 
         let source = match self.load_config(reference) {
             Ok(source) => source,
-            Err(CoreError::InvalidConfig { label, message }) => {
-                return Ok(invalid_config_output(
-                    "invalid_config",
-                    format!("invalid config in {label}: {message}"),
-                    None::<String>,
-                ));
+            Err(err @ CoreError::InvalidConfig { .. }) => {
+                let location = err.location().cloned();
+                let mut output =
+                    invalid_config_output("invalid_config", err.to_string(), None::<String>);
+                output.diagnostics[0].location = location;
+                return Ok(output);
             }
             Err(CoreError::ParseToml { path, source }) => {
                 return Ok(invalid_config_output(
@@ -225,10 +848,19 @@ impl GenerationCore {
             Err(err) => return Err(err),
         };
         let config = source.config;
-        let rendered = render_config_props(config).and_then(render_config_rules)?;
-
         let mut diagnostics = Vec::new();
-        if rendered.rules.is_empty() {
+        let rendered = match render_config_props(config.clone()).and_then(render_config_rules) {
+            Ok(rendered) => Some(rendered),
+            Err(err) => {
+                diagnostics.push(
+                    Diagnostic::error("render_failed", err.to_string(), None::<String>)
+                        .at(err.location().cloned()),
+                );
+                None
+            }
+        };
+
+        if config.rules.is_empty() {
             diagnostics.push(Diagnostic::error(
                 "missing_rules",
                 "config must contain at least one rule in the rules array",
@@ -236,19 +868,20 @@ impl GenerationCore {
             ));
         }
         let effective_root = self.sandbox.root().to_path_buf();
-        for rule in &rendered.rules {
+        let rendered_rules = rendered.as_ref().map_or(&[][..], |config| &config.rules);
+        for (index, rule) in rendered_rules.iter().enumerate() {
             let raw_path = rule_path(rule);
             if let Err(err) = self
                 .sandbox
                 .resolve_generated_path(&effective_root, raw_path)
             {
-                diagnostics.push(Diagnostic::error(
-                    "invalid_path",
-                    err.to_string(),
-                    Some(raw_path),
-                ));
+                diagnostics.push(
+                    Diagnostic::error("invalid_path", err.to_string(), Some(raw_path))
+                        .at(Some(ConfigLocation::rule(index, Some(rule.type_name())))),
+                );
             }
         }
+        diagnostics.extend(lint::lint(&config, rendered.as_ref()));
 
         let valid = diagnostics
             .iter()
@@ -262,80 +895,161 @@ impl GenerationCore {
 
     pub fn list_templates(&self) -> Result<ListTemplatesOutput, CoreError> {
         let mut items = Vec::new();
-        self.collect_templates(self.sandbox.root(), &mut items)?;
+        let mut warnings = Vec::new();
+        self.collect_templates(&mut items)?;
+        self.apply_catalog(&mut items, &mut warnings)?;
         items.sort_by(|left, right| left.path.cmp(&right.path));
-        Ok(ListTemplatesOutput { items })
+        Ok(ListTemplatesOutput { items, warnings })
     }
 
-    fn collect_templates(
-        &self,
-        dir: &Path,
-        items: &mut Vec<TemplateInfo>,
-    ) -> Result<(), CoreError> {
-        let entries = fs::read_dir(dir).map_err(|source| CoreError::ReadFile {
-            path: path_to_string(dir),
-            source,
-        })?;
-        for entry in entries {
-            let entry = entry.map_err(|source| CoreError::ReadFile {
-                path: path_to_string(dir),
-                source,
-            })?;
-            let path = entry.path();
-            let name = entry.file_name().to_string_lossy().to_string();
-            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                if matches!(name.as_str(), ".git" | "target" | "tmp") {
-                    continue;
-                }
-                self.collect_templates(&path, items)?;
+    /// Finds configs and templates under the root, leaving out build and
+    /// scratch directories and what `.gitignore` files, git's excludes and
+    /// `.genifyignore` files ignore.
+    fn collect_templates(&self, items: &mut Vec<TemplateInfo>) -> Result<(), CoreError> {
+        let root = self.sandbox.root();
+        for entry in walk::walker(root, &[".git", ".genify", "target", "tmp"]).build() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => match err.io_error() {
+                    Some(source) => {
+                        return Err(CoreError::ReadFile {
+                            path: path_to_string(root),
+                            source: io::Error::new(source.kind(), err.to_string()),
+                        });
+                    }
+                    // A malformed ignore file only loses its own patterns.
+                    None => continue,
+                },
+            };
+            if entry.file_type().is_none_or(|kind| kind.is_dir()) {
                 continue;
             }
-
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
             let Some(extension) = path.extension().and_then(|value| value.to_str()) else {
                 continue;
             };
             let Ok(canonical) = path.canonicalize() else {
                 continue;
             };
-            if !canonical.starts_with(self.sandbox.root()) {
+            if !canonical.starts_with(root) {
                 continue;
             }
             match extension {
-                "toml" => {
-                    let metadata = self.config_metadata(&path);
-                    items.push(TemplateInfo {
-                        name,
-                        path: self.sandbox.display_path(&path),
-                        kind: TemplateKind::Config,
-                        metadata,
-                    });
-                }
-                "hbs" | "tera" | "tpl" => items.push(TemplateInfo {
+                "toml" if name != CATALOG_FILE => items.push(self.config_template(name, path)),
+                "hbs" | "tera" | "tpl" => items.push(TemplateInfo::new(
                     name,
-                    path: self.sandbox.display_path(&path),
-                    kind: TemplateKind::Template,
-                    metadata: JsonValue::Null,
-                }),
+                    self.sandbox.display_path(path),
+                    TemplateKind::Template,
+                    JsonValue::Null,
+                )),
                 _ => {}
             }
         }
         Ok(())
     }
 
-    fn config_metadata(&self, path: &Path) -> JsonValue {
+    /// Names and describes the configs listed in the root's catalog index,
+    /// adding the ones the scan does not find, such as remote configs. A
+    /// local entry whose config is missing is reported and left out.
+    fn apply_catalog(
+        &self,
+        items: &mut Vec<TemplateInfo>,
+        warnings: &mut Vec<Diagnostic>,
+    ) -> Result<(), CoreError> {
+        let path = self.sandbox.root().join(CATALOG_FILE);
+        let Some(raw) = read_optional_string(&path)? else {
+            return Ok(());
+        };
+        let catalog: Catalog = toml::from_str(&raw).map_err(|source| CoreError::ParseToml {
+            path: CATALOG_FILE.to_string(),
+            source,
+        })?;
+
+        for entry in catalog.templates {
+            let index = if entry.is_remote() {
+                items.push(TemplateInfo::new(
+                    entry.name.clone(),
+                    entry.path.clone(),
+                    TemplateKind::Config,
+                    JsonValue::Null,
+                ));
+                items.len() - 1
+            } else {
+                let config = match self.sandbox.resolve_existing_file(&entry.path) {
+                    Ok(config) => config,
+                    Err(err) => {
+                        warnings.push(Diagnostic::warning(
+                            "catalog_entry_missing",
+                            format!("catalog entry {} is skipped: {err}", entry.name),
+                            Some(entry.path.clone()),
+                        ));
+                        continue;
+                    }
+                };
+                let display = self.sandbox.display_path(&config);
+                match items.iter().position(|item| item.path == display) {
+                    Some(index) => index,
+                    None => {
+                        items.push(self.config_template(entry.name.clone(), &config));
+                        items.len() - 1
+                    }
+                }
+            };
+            let item = &mut items[index];
+            item.name = entry.name;
+            item.title = entry.title.or(item.title.take());
+            item.description = entry.description.or(item.description.take());
+            item.version = entry.version.or(item.version.take());
+            if !entry.tags.is_empty() {
+                item.tags = entry.tags;
+            }
+        }
+        Ok(())
+    }
+
+    fn config_template(&self, name: String, path: &Path) -> TemplateInfo {
+        let display = self.sandbox.display_path(path);
         let Ok(raw) = fs::read_to_string(path) else {
-            return json!({ "readable": false });
+            return TemplateInfo::new(
+                name,
+                display,
+                TemplateKind::Config,
+                json!({ "readable": false }),
+            );
         };
         match parse_toml(&raw) {
-            Ok(config) => json!({
-                "readable": true,
-                "props_count": config.props.len(),
-                "rules_count": config.rules.len()
-            }),
-            Err(err) => json!({
-                "readable": true,
-                "parse_error": err.to_string()
-            }),
+            Ok(config) => {
+                let mut info = TemplateInfo::new(
+                    name,
+                    display,
+                    TemplateKind::Config,
+                    json!({
+                        "readable": true,
+                        "props_count": config.props.len(),
+                        "rules_count": config.rules.len()
+                    }),
+                );
+                info.required_props = lint::required_props(&config);
+                if let Some(meta) = config.meta {
+                    info.title = meta.name;
+                    info.description = meta.description;
+                    info.version = meta.version;
+                    info.authors = meta.authors;
+                    info.min_genify_version = meta.min_genify_version;
+                    info.tags = meta.tags;
+                }
+                info
+            }
+            Err(err) => TemplateInfo::new(
+                name,
+                display,
+                TemplateKind::Config,
+                json!({
+                    "readable": true,
+                    "parse_error": err.to_string()
+                }),
+            ),
         }
     }
 
@@ -344,19 +1058,156 @@ impl GenerationCore {
         let effective_root = self
             .sandbox
             .resolve_existing_dir(input.root.as_deref().unwrap_or("."))?;
-        let config = source.config;
+        let mut config = source.config;
+        let manifest = if input.update {
+            Some(self.read_manifest(&effective_root)?.ok_or_else(|| {
+                CoreError::MissingManifest {
+                    path: self
+                        .sandbox
+                        .display_path(&effective_root.join(MANIFEST_PATH)),
+                }
+            })?)
+        } else {
+            None
+        };
+        let mut prop_sources = config
+            .props
+            .iter()
+            .map(|(key, _)| (key.clone(), PropSource::Config))
+            .collect();
+        if let Some(manifest) = &manifest {
+            merge_props(
+                &mut config.props,
+                &mut prop_sources,
+                manifest.props(),
+                PropSource::Manifest,
+            );
+        }
+        if let Some(project) = self.read_project_props(&effective_root)? {
+            merge_props(
+                &mut config.props,
+                &mut prop_sources,
+                project,
+                PropSource::Project,
+            );
+        }
+        if let Some(props) = &input.props {
+            let overrides = parse_json_props(props, "props")?;
+            merge_props(
+                &mut config.props,
+                &mut prop_sources,
+                overrides,
+                PropSource::Request,
+            );
+        }
+        // Only the props someone supplied are recorded, unrendered, so a
+        // template's new defaults still reach the project on update.
+        let recorded_props = config
+            .props
+            .iter()
+            .filter(|(key, _)| prop_sources.get(key) != Some(&PropSource::Config))
+            .cloned()
+            .collect();
         let rendered = render_config_props(config).and_then(render_config_rules)?;
-        let operations = self.operations_from_config(&effective_root, &rendered)?;
-
-        Ok(PreparedGeneration { operations })
-    }
-
-    fn load_config(&self, reference: ConfigReference<'_>) -> Result<ConfigSource, CoreError> {
-        let config =
-            parse_json_config(reference.value).map_err(|message| CoreError::InvalidConfig {
-                label: "inline config".to_string(),
-                message,
-            })?;
+        let mut operations = self.operations_from_config(&effective_root, &rendered)?;
+        let hooks = self.hooks_from_config(&effective_root, &rendered)?;
+        let mut formatters = formatters_from_config(&rendered)?;
+        let mut warnings = Vec::new();
+        // Built-ins run external programs too, and pick up their config from
+        // the project, so they are skipped along with commands.
+        if !self.allow_hooks && !formatters.is_empty() {
+            for formatter in &mut formatters {
+                formatter.disabled = true;
+            }
+            warnings.push(Diagnostic::warning(
+                "formatters_disabled",
+                format!(
+                    "{} formatter(s) skipped because hooks are not allowed",
+                    formatters.len()
+                ),
+                None::<String>,
+            ));
+        }
+        let baseline = match manifest {
+            Some(manifest) => {
+                let baseline = self.update_baseline(&effective_root, manifest)?;
+                operations.retain(|operation| {
+                    if !baseline.preserve.contains(&operation.path)
+                        || operation.if_exists == Some(IfExists::Merge)
+                    {
+                        return true;
+                    }
+                    warnings.push(
+                        Diagnostic::warning(
+                            "locally_modified",
+                            "file was edited since it was generated and is kept as is",
+                            Some(operation.relative_path.clone()),
+                        )
+                        .at(Some(operation.location.clone())),
+                    );
+                    false
+                });
+                Some(baseline)
+            }
+            None => None,
+        };
+
+        Ok(PreparedGeneration {
+            effective_root,
+            props: rendered.props,
+            prop_sources,
+            recorded_props,
+            operations,
+            hooks,
+            formatters,
+            baseline,
+            warnings,
+        })
+    }
+
+    /// Splits the files genify changed last time into ones the user never
+    /// touched, which are regenerated from scratch or from their original
+    /// content, and ones they edited or removed, which are left alone.
+    fn update_baseline(
+        &self,
+        effective_root: &Path,
+        manifest: Manifest,
+    ) -> Result<UpdateBaseline, CoreError> {
+        let mut regenerate = BTreeSet::new();
+        let mut restore = BTreeMap::new();
+        let mut preserve = BTreeSet::new();
+        for (relative, file) in &manifest.files {
+            let path = self.resolve_rule_path(effective_root, relative)?;
+            let untouched = read_optional_string(&path)?
+                .is_some_and(|content| content_hash(content.as_bytes()) == file.hash);
+            if !untouched {
+                preserve.insert(path);
+            } else if file.created {
+                regenerate.insert(path);
+            } else {
+                // Without its original content the rules could only be
+                // applied on top of their own output.
+                let original = effective_root.join(ORIGINAL_DIR).join(relative);
+                match read_optional_string(&original)? {
+                    Some(original) => {
+                        restore.insert(path, original);
+                    }
+                    None => {
+                        preserve.insert(path);
+                    }
+                }
+            }
+        }
+        Ok(UpdateBaseline {
+            manifest,
+            regenerate,
+            restore,
+            preserve,
+        })
+    }
+
+    fn load_config(&self, reference: ConfigReference<'_>) -> Result<ConfigSource, CoreError> {
+        let config = parse_json_config(reference.value)?;
         Ok(ConfigSource { config })
     }
 
@@ -366,99 +1217,131 @@ impl GenerationCore {
         config: &Config,
     ) -> Result<Vec<PreparedOperation>, CoreError> {
         let mut operations = Vec::with_capacity(config.rules.len());
-        for rule in &config.rules {
-            let operation = match rule {
-                Rule::Write {
-                    content, if_exists, ..
-                } => PreparedOperation {
-                    kind: FileOperationKind::Write,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: Some(*if_exists),
-                },
-                Rule::Delete { .. } => PreparedOperation {
-                    kind: FileOperationKind::Delete,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: None,
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::Rename { from, to } | Rule::Move { from, to } | Rule::Copy { from, to } => {
-                    let from_path = self.resolve_rule_path(effective_root, from)?;
-                    let to_path = self.resolve_rule_path(effective_root, to)?;
-                    PreparedOperation {
-                        kind: match rule {
-                            Rule::Rename { .. } => FileOperationKind::Rename,
-                            Rule::Move { .. } => FileOperationKind::Move,
-                            Rule::Copy { .. } => FileOperationKind::Copy,
-                            _ => unreachable!(),
-                        },
-                        path: to_path.clone(),
-                        relative_path: self.sandbox.display_path(&to_path),
-                        source_path: Some(from_path.clone()),
-                        source_relative_path: Some(self.sandbox.display_path(&from_path)),
-                        target_path: Some(to_path.clone()),
-                        target_relative_path: Some(self.sandbox.display_path(&to_path)),
-                        content: None,
-                        replace: None,
-                        replace_all: false,
-                        expected_matches: None,
-                        marker: None,
-                        start_marker: None,
-                        end_marker: None,
-                        mode: None,
-                        if_exists: None,
-                    }
+        for (index, rule) in config.rules.iter().enumerate() {
+            let location = ConfigLocation::rule(index, Some(rule.type_name()));
+            let mut operation = self
+                .prepare_operation(effective_root, rule, &location)
+                .map_err(|err| err.at(location))?;
+            if operation.if_exists == Some(IfExists::Merge) {
+                operation.merge_base = self.read_merge_base(effective_root, &operation.path)?;
+            }
+            operations.push(operation);
+        }
+        Ok(operations)
+    }
+
+    fn hooks_from_config(
+        &self,
+        effective_root: &Path,
+        config: &Config,
+    ) -> Result<Vec<PreparedHook>, CoreError> {
+        config
+            .hooks
+            .iter()
+            .enumerate()
+            .map(|(index, hook)| {
+                let location = ConfigLocation::hook(index);
+                if hook.command.first().is_none_or(String::is_empty) {
+                    return Err(CoreError::InvalidConfig {
+                        label: "inline config".to_string(),
+                        message: "hook command must name a program".to_string(),
+                        location: Some(Box::new(location)),
+                    });
                 }
-                Rule::Mkdir { .. } => PreparedOperation {
-                    kind: FileOperationKind::Mkdir,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: None,
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::Chmod { mode, .. } => PreparedOperation {
-                    kind: FileOperationKind::Chmod,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
+                let cwd = self
+                    .resolve_rule_path(effective_root, hook.cwd.as_deref().unwrap_or("."))
+                    .map_err(|err| err.at(location.clone()))?;
+                // After hooks may run in a directory the rules create.
+                if hook.when == HookWhen::Before && !cwd.is_dir() {
+                    return Err(CoreError::InvalidConfig {
+                        label: "inline config".to_string(),
+                        message: format!(
+                            "before hook cwd `{}` is not a directory; before hooks run before the rules create anything",
+                            self.sandbox.display_path(&cwd)
+                        ),
+                        location: Some(Box::new(location)),
+                    });
+                }
+                Ok(PreparedHook {
+                    when: hook.when,
+                    command: hook.command.clone(),
+                    relative_cwd: self.sandbox.display_path(&cwd),
+                    cwd,
+                    env: hook.env.clone(),
+                    location,
+                })
+            })
+            .collect()
+    }
+
+    fn prepare_operation(
+        &self,
+        effective_root: &Path,
+        rule: &Rule,
+        location: &ConfigLocation,
+    ) -> Result<PreparedOperation, CoreError> {
+        Ok(match rule {
+            Rule::Write {
+                content,
+                if_exists,
+                exact,
+                ..
+            } => PreparedOperation {
+                kind: FileOperationKind::Write,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(write_rule_content(content, *exact)),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: Some(*if_exists),
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::Delete { .. } => PreparedOperation {
+                kind: FileOperationKind::Delete,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: None,
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::Rename { from, to } | Rule::Move { from, to } | Rule::Copy { from, to } => {
+                let from_path = self.resolve_rule_path(effective_root, from)?;
+                let to_path = self.resolve_rule_path(effective_root, to)?;
+                PreparedOperation {
+                    kind: match rule {
+                        Rule::Rename { .. } => FileOperationKind::Rename,
+                        Rule::Move { .. } => FileOperationKind::Move,
+                        Rule::Copy { .. } => FileOperationKind::Copy,
+                        _ => unreachable!(),
+                    },
+                    path: to_path.clone(),
+                    relative_path: self.sandbox.display_path(&to_path),
+                    source_path: Some(from_path.clone()),
+                    source_relative_path: Some(self.sandbox.display_path(&from_path)),
+                    target_path: Some(to_path.clone()),
+                    target_relative_path: Some(self.sandbox.display_path(&to_path)),
                     content: None,
                     replace: None,
                     replace_all: false,
@@ -466,183 +1349,252 @@ impl GenerationCore {
                     marker: None,
                     start_marker: None,
                     end_marker: None,
-                    mode: Some(
-                        parse_mode(mode).map_err(|message| CoreError::InvalidConfig {
-                            label: "inline config".to_string(),
-                            message,
-                        })?,
-                    ),
-                    if_exists: None,
-                },
-                Rule::Append { content, .. } => PreparedOperation {
-                    kind: FileOperationKind::Append,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::AppendOnce { content, .. } => PreparedOperation {
-                    kind: FileOperationKind::AppendOnce,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::Prepend { content, .. } => PreparedOperation {
-                    kind: FileOperationKind::Prepend,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::InsertBefore {
-                    marker, content, ..
-                } => PreparedOperation {
-                    kind: FileOperationKind::InsertBefore,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: Some(marker.clone()),
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::InsertAfter {
-                    marker, content, ..
-                } => PreparedOperation {
-                    kind: FileOperationKind::InsertAfter,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: Some(marker.clone()),
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::Replace {
-                    replace,
-                    content,
-                    replace_all,
-                    expected_matches,
-                    ..
-                } => PreparedOperation {
-                    kind: FileOperationKind::Replace,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: Some(replace.clone()),
-                    replace_all: *replace_all,
-                    expected_matches: *expected_matches,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::ReplaceOrAppend {
-                    replace,
-                    content,
-                    replace_all,
-                    expected_matches,
-                    ..
-                } => PreparedOperation {
-                    kind: FileOperationKind::ReplaceOrAppend,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: Some(replace.clone()),
-                    replace_all: *replace_all,
-                    expected_matches: *expected_matches,
-                    marker: None,
-                    start_marker: None,
-                    end_marker: None,
-                    mode: None,
-                    if_exists: None,
-                },
-                Rule::ManagedBlock {
-                    start_marker,
-                    end_marker,
-                    content,
-                    ..
-                } => PreparedOperation {
-                    kind: FileOperationKind::ManagedBlock,
-                    path: self.resolve_rule_path(effective_root, rule_path(rule))?,
-                    relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
-                    source_path: None,
-                    source_relative_path: None,
-                    target_path: None,
-                    target_relative_path: None,
-                    content: Some(content.clone()),
-                    replace: None,
-                    replace_all: false,
-                    expected_matches: None,
-                    marker: None,
-                    start_marker: Some(start_marker.clone()),
-                    end_marker: Some(end_marker.clone()),
                     mode: None,
                     if_exists: None,
-                },
-            };
-            operations.push(operation);
-        }
-        Ok(operations)
+                    merge_base: None,
+                    location: location.clone(),
+                }
+            }
+            Rule::Mkdir { .. } => PreparedOperation {
+                kind: FileOperationKind::Mkdir,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: None,
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::Chmod { mode, .. } => PreparedOperation {
+                kind: FileOperationKind::Chmod,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: None,
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: Some(
+                    parse_mode(mode).map_err(|message| CoreError::InvalidConfig {
+                        label: "inline config".to_string(),
+                        message,
+                        location: None,
+                    })?,
+                ),
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::Append { content, .. } => PreparedOperation {
+                kind: FileOperationKind::Append,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::AppendOnce { content, .. } => PreparedOperation {
+                kind: FileOperationKind::AppendOnce,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::Prepend { content, .. } => PreparedOperation {
+                kind: FileOperationKind::Prepend,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::InsertBefore {
+                marker, content, ..
+            } => PreparedOperation {
+                kind: FileOperationKind::InsertBefore,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: Some(marker.clone()),
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::InsertAfter {
+                marker, content, ..
+            } => PreparedOperation {
+                kind: FileOperationKind::InsertAfter,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: Some(marker.clone()),
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::Replace {
+                replace,
+                content,
+                replace_all,
+                expected_matches,
+                ..
+            } => PreparedOperation {
+                kind: FileOperationKind::Replace,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: Some(replace.clone()),
+                replace_all: *replace_all,
+                expected_matches: *expected_matches,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::ReplaceOrAppend {
+                replace,
+                content,
+                replace_all,
+                expected_matches,
+                ..
+            } => PreparedOperation {
+                kind: FileOperationKind::ReplaceOrAppend,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: Some(replace.clone()),
+                replace_all: *replace_all,
+                expected_matches: *expected_matches,
+                marker: None,
+                start_marker: None,
+                end_marker: None,
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+            Rule::ManagedBlock {
+                start_marker,
+                end_marker,
+                content,
+                ..
+            } => PreparedOperation {
+                kind: FileOperationKind::ManagedBlock,
+                path: self.resolve_rule_path(effective_root, rule_path(rule))?,
+                relative_path: self.relative_rule_path(effective_root, rule_path(rule))?,
+                source_path: None,
+                source_relative_path: None,
+                target_path: None,
+                target_relative_path: None,
+                content: Some(content.clone()),
+                replace: None,
+                replace_all: false,
+                expected_matches: None,
+                marker: None,
+                start_marker: Some(start_marker.clone()),
+                end_marker: Some(end_marker.clone()),
+                mode: None,
+                if_exists: None,
+                merge_base: None,
+                location: location.clone(),
+            },
+        })
+    }
+
+    /// Reads the content genify generated for `path` last time, if it was recorded.
+    fn read_merge_base(
+        &self,
+        effective_root: &Path,
+        path: &Path,
+    ) -> Result<Option<String>, CoreError> {
+        let Ok(relative) = path.strip_prefix(effective_root) else {
+            return Ok(None);
+        };
+        read_optional_string(&effective_root.join(BASELINE_DIR).join(relative))
     }
 
     fn resolve_rule_path(&self, effective_root: &Path, raw: &str) -> Result<PathBuf, CoreError> {
@@ -660,13 +1612,58 @@ impl GenerationCore {
         let mut errors = Vec::new();
         let mut metadata_changes = Vec::new();
 
+        if let Some(baseline) = &prepared.baseline {
+            for path in &baseline.regenerate {
+                let original = read_optional_string(path)?.unwrap_or_default();
+                files.insert(
+                    path.clone(),
+                    SimulatedFile {
+                        path: path.clone(),
+                        relative_path: self.sandbox.display_path(path),
+                        original,
+                        current: String::new(),
+                        original_existed: true,
+                        existed: true,
+                        deleted: true,
+                        renamed_from: None,
+                        generated: None,
+                    },
+                );
+            }
+            for (path, content) in &baseline.restore {
+                let original = read_optional_string(path)?.unwrap_or_default();
+                files.insert(
+                    path.clone(),
+                    SimulatedFile {
+                        path: path.clone(),
+                        relative_path: self.sandbox.display_path(path),
+                        original,
+                        current: content.clone(),
+                        original_existed: true,
+                        existed: true,
+                        deleted: false,
+                        renamed_from: None,
+                        generated: None,
+                    },
+                );
+            }
+        }
+
         for operation in &prepared.operations {
-            operation.simulate(
-                &mut files,
-                &mut metadata_changes,
-                &mut warnings,
-                &mut errors,
-            )?;
+            let (first_warning, first_error) = (warnings.len(), errors.len());
+            operation
+                .simulate(
+                    &mut files,
+                    &mut metadata_changes,
+                    &mut warnings,
+                    &mut errors,
+                )
+                .map_err(|err| err.at(operation.location.clone()))?;
+            locate_from(&mut warnings, first_warning, &operation.location);
+            locate_from(&mut errors, first_error, &operation.location);
+        }
+        for file in files.values_mut() {
+            format_file(prepared, file, &mut warnings, &mut errors);
         }
 
         Ok(Simulation {
@@ -837,6 +1834,13 @@ pub struct GenerationRequest {
     pub config: Option<JsonValue>,
     #[serde(default)]
     pub root: Option<String>,
+    /// Prop overrides applied over the config props.
+    #[serde(default)]
+    pub props: Option<JsonValue>,
+    /// Regenerate against the manifest recorded in the root, keeping files
+    /// that were edited since the last generation.
+    #[serde(default)]
+    pub update: bool,
 }
 
 impl GenerationRequest {
@@ -855,9 +1859,31 @@ pub struct ApplyRequest {
     #[serde(default)]
     pub root: Option<String>,
     #[serde(default)]
+    pub props: Option<JsonValue>,
+    #[serde(default)]
+    pub update: bool,
+    /// Record the run in the root's manifest under this source.
+    #[serde(default)]
+    pub manifest: Option<ManifestSource>,
+    #[serde(default)]
     pub confirm_token: Option<String>,
     #[serde(default)]
     pub explicit_approval: bool,
+    /// Apply to each of these roots instead of a single one; see
+    /// [`GenerationCore::apply_roots`].
+    #[serde(default)]
+    pub roots: Vec<String>,
+    /// Stop at the first root that fails and report the rest as skipped.
+    #[serde(default)]
+    pub fail_fast: bool,
+    /// Switch the root's git worktree to this branch before applying,
+    /// creating it when it does not exist, and stage the changed files.
+    #[serde(default)]
+    pub git_branch: Option<String>,
+    /// Stage the changed files and commit them with this message. Other
+    /// changes in the worktree are left out of the commit.
+    #[serde(default)]
+    pub git_commit: Option<String>,
 }
 
 impl ApplyRequest {
@@ -869,6 +1895,8 @@ impl ApplyRequest {
         GenerationRequest {
             config: self.config.clone(),
             root: self.root.clone(),
+            props: self.props.clone(),
+            update: self.update,
         }
     }
 }
@@ -892,79 +1920,318 @@ impl ValidateConfigRequest {
 pub struct PlanOutput {
     pub operations: Vec<PlannedFileOperation>,
     pub affected_paths: Vec<String>,
+    /// Commands the config runs around generation, when hooks are allowed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<PlannedHook>,
+    /// Resolved props in declaration order, with the layer that set each one.
+    pub props: Vec<ResolvedProp>,
     pub warnings: Vec<Diagnostic>,
     pub errors: Vec<Diagnostic>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedHook {
+    pub when: HookWhen,
+    pub command: Vec<String>,
+    /// Working directory relative to the root.
+    pub cwd: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedProp {
+    pub name: String,
+    pub value: Value,
+    pub source: PropSource,
+}
+
+/// Where a prop value came from. Later layers override earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PropSource {
+    /// The config's `[props]`.
+    Config,
+    /// Props recorded in `.genify/manifest.toml`, used by updates.
+    Manifest,
+    /// The root's `.genify/props.toml`.
+    Project,
+    /// Overrides passed with the request, such as `--props-json`.
+    Request,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct DiffOutput {
     pub diff: String,
+    pub files: Vec<FileDiff>,
     pub summary: ChangeSummary,
     pub warnings: Vec<Diagnostic>,
     pub errors: Vec<Diagnostic>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct ApplyOutput {
-    pub changed_files: Vec<String>,
-    pub summary: String,
-    pub warnings: Vec<Diagnostic>,
-    pub errors: Vec<Diagnostic>,
+pub struct FileDiff {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub status: FileDiffStatus,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ValidateConfigOutput {
-    pub valid: bool,
-    pub diagnostics: Vec<Diagnostic>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub hint: Option<ConfigHint>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileDiffStatus {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct ConfigHint {
-    pub summary: String,
-    pub minimal_config: JsonValue,
-    pub examples: Vec<ConfigExample>,
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct ConfigExample {
-    pub operation: FileOperationKind,
-    pub config: JsonValue,
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_line: Option<usize>,
+    pub content: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ListTemplatesOutput {
-    pub items: Vec<TemplateInfo>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    Context,
+    Insert,
+    Delete,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct TemplateInfo {
-    pub name: String,
-    pub path: String,
-    pub kind: TemplateKind,
-    pub metadata: JsonValue,
+pub struct PreviewOutput {
+    pub files: Vec<PreviewFile>,
+    pub warnings: Vec<Diagnostic>,
+    pub errors: Vec<Diagnostic>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
-#[serde(rename_all = "snake_case")]
-pub enum TemplateKind {
-    Config,
-    Template,
+/// A file changed by generation and its new content, `None` when deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PreviewFile {
+    pub path: String,
+    pub content: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct PlannedFileOperation {
-    pub operation: FileOperationKind,
-    pub path: String,
+pub struct ApplyOutput {
+    pub changed_files: Vec<String>,
+    pub summary: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub source_path: Option<String>,
+    pub manifest: Option<String>,
+    /// Hooks that ran, in order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookReport>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub target_path: Option<String>,
-    pub exists: bool,
-    pub will_create: bool,
-    pub will_modify: bool,
-    pub will_delete: bool,
+    pub git: Option<GitOutput>,
+    pub warnings: Vec<Diagnostic>,
+    pub errors: Vec<Diagnostic>,
+}
+
+/// The worktree an apply runs in, and the branch or commit it switched away
+/// from.
+#[derive(Debug)]
+struct GitRun {
+    worktree: Worktree,
+    output: GitOutput,
+    previous: Option<String>,
+}
+
+impl GitRun {
+    /// Switches back to where the run started, deleting the branch if the
+    /// run created it.
+    fn switch_back(&mut self) -> Result<(), String> {
+        let (Some(previous), Some(branch)) = (&self.previous, &self.output.branch) else {
+            return Ok(());
+        };
+        if previous == branch {
+            return Ok(());
+        }
+        self.worktree
+            .switch_back(previous, branch, self.output.created_branch)?;
+        self.output.branch = None;
+        self.output.created_branch = false;
+        Ok(())
+    }
+}
+
+/// What was done in the root's git worktree.
+#[derive(Debug, Clone, Serialize)]
+pub struct GitOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Whether the branch was created by this run.
+    pub created_branch: bool,
+    /// Files staged, the manifest included.
+    pub staged: Vec<String>,
+    /// Id of the commit, when one was asked for and there was something to
+    /// commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HookReport {
+    pub when: HookWhen,
+    pub command: Vec<String>,
+    pub cwd: String,
+    /// Exit code, or `None` when the command could not be started or was
+    /// killed by a signal.
+    pub status: Option<i32>,
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RootsApplyOutput {
+    pub roots: Vec<RootApplyReport>,
+    pub summary: String,
+}
+
+impl RootsApplyOutput {
+    pub fn has_failures(&self) -> bool {
+        self.roots
+            .iter()
+            .any(|report| report.status == RootApplyStatus::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RootApplyReport {
+    /// The root as it was requested.
+    pub root: String,
+    pub status: RootApplyStatus,
+    pub changed_files: Vec<String>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitOutput>,
+    pub warnings: Vec<Diagnostic>,
+    pub errors: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RootApplyStatus {
+    Changed,
+    Unchanged,
+    Failed,
+    /// Not attempted because an earlier root failed with `fail_fast` set.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidateConfigOutput {
+    pub valid: bool,
+    pub diagnostics: Vec<Diagnostic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<ConfigHint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigHint {
+    pub summary: String,
+    pub minimal_config: JsonValue,
+    pub examples: Vec<ConfigExample>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigExample {
+    pub operation: FileOperationKind,
+    pub config: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListTemplatesOutput {
+    pub items: Vec<TemplateInfo>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// A config or template file found under the root. Configs describe
+/// themselves with their `[meta]`; the root's catalog index can rename them
+/// and overrides what it sets.
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub path: String,
+    pub kind: TemplateKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_genify_version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Props the config declares without a value or uses without declaring,
+    /// which have to be given when generating.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub required_props: Vec<String>,
+    pub metadata: JsonValue,
+}
+
+impl TemplateInfo {
+    fn new(name: String, path: String, kind: TemplateKind, metadata: JsonValue) -> Self {
+        Self {
+            name,
+            path,
+            kind,
+            title: None,
+            description: None,
+            version: None,
+            authors: Vec::new(),
+            min_genify_version: None,
+            tags: Vec::new(),
+            required_props: Vec::new(),
+            metadata,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    Config,
+    Template,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFileOperation {
+    pub operation: FileOperationKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_path: Option<String>,
+    pub exists: bool,
+    pub will_create: bool,
+    pub will_modify: bool,
+    pub will_delete: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
@@ -994,10 +2261,13 @@ pub struct Diagnostic {
     pub message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
+    /// The config rule or prop the diagnostic comes from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<ConfigLocation>,
 }
 
 impl Diagnostic {
-    fn warning(
+    pub(crate) fn warning(
         code: impl Into<String>,
         message: impl Into<String>,
         path: Option<impl Into<String>>,
@@ -1007,10 +2277,11 @@ impl Diagnostic {
             code: code.into(),
             message: message.into(),
             path: path.map(Into::into),
+            location: None,
         }
     }
 
-    fn error(
+    pub(crate) fn error(
         code: impl Into<String>,
         message: impl Into<String>,
         path: Option<impl Into<String>>,
@@ -1020,8 +2291,21 @@ impl Diagnostic {
             code: code.into(),
             message: message.into(),
             path: path.map(Into::into),
+            location: None,
         }
     }
+
+    pub(crate) fn at(mut self, location: Option<ConfigLocation>) -> Self {
+        self.location = location;
+        self
+    }
+}
+
+/// Attaches `location` to the diagnostics an operation added from `start` on.
+fn locate_from(diagnostics: &mut [Diagnostic], start: usize, location: &ConfigLocation) {
+    for diagnostic in &mut diagnostics[start..] {
+        diagnostic.location.get_or_insert_with(|| location.clone());
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
@@ -1052,18 +2336,101 @@ impl ChangeSummary {
 
 #[derive(Debug, Clone)]
 struct PreparedGeneration {
+    effective_root: PathBuf,
+    props: Map,
+    prop_sources: BTreeMap<String, PropSource>,
+    /// The manifest, project and request props, as given.
+    recorded_props: Map,
     operations: Vec<PreparedOperation>,
+    hooks: Vec<PreparedHook>,
+    formatters: Vec<PreparedFormatter>,
+    baseline: Option<UpdateBaseline>,
+    warnings: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone)]
+struct PreparedFormatter {
+    matcher: GlobMatcher,
+    format_with: FormatWith,
+    location: ConfigLocation,
+    /// A command formatter the core may not run; the files it matches are
+    /// left unformatted rather than passed on to a later formatter.
+    disabled: bool,
+}
+
+#[derive(Debug, Clone)]
+struct PreparedHook {
+    when: HookWhen,
+    command: Vec<String>,
+    cwd: PathBuf,
+    relative_cwd: String,
+    env: BTreeMap<String, String>,
+    location: ConfigLocation,
+}
+
+impl PreparedHook {
+    fn planned(&self) -> PlannedHook {
+        PlannedHook {
+            when: self.when,
+            command: self.command.clone(),
+            cwd: self.relative_cwd.clone(),
+            env: self.env.clone(),
+        }
+    }
+
+    /// Runs the command without a shell and with no input, capturing its
+    /// output.
+    fn run(&self) -> HookReport {
+        let output = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .current_dir(&self.cwd)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .output();
+        let (status, stdout, stderr) = match output {
+            Ok(output) => (
+                output.status.code(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            ),
+            Err(err) => (
+                None,
+                String::new(),
+                format!("failed to run {}: {err}", self.command[0]),
+            ),
+        };
+        HookReport {
+            when: self.when,
+            command: self.command.clone(),
+            cwd: self.relative_cwd.clone(),
+            status,
+            success: status == Some(0),
+            stdout,
+            stderr,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct UpdateBaseline {
+    manifest: Manifest,
+    regenerate: BTreeSet<PathBuf>,
+    /// Files genify patched but did not create, with the content they had
+    /// before genify first touched them.
+    restore: BTreeMap<PathBuf, String>,
+    preserve: BTreeSet<PathBuf>,
 }
 
 impl PreparedGeneration {
     fn plan_output(&self) -> PlanOutput {
         let mut affected_paths = BTreeSet::new();
         let mut operations = Vec::with_capacity(self.operations.len());
-        let mut warnings = Vec::new();
+        let mut warnings = self.warnings.clone();
         let mut errors = Vec::new();
 
         for operation in &self.operations {
-            let exists = operation.path.exists();
+            let (first_warning, first_error) = (warnings.len(), errors.len());
+            let exists = operation.path.exists() && !self.regenerates(&operation.path);
             if operation.kind == FileOperationKind::Write
                 && operation.if_exists == Some(IfExists::Error)
                 && exists
@@ -1088,6 +2455,8 @@ impl PreparedGeneration {
                     Some(operation.relative_path.clone()),
                 ));
             }
+            locate_from(&mut warnings, first_warning, &operation.location);
+            locate_from(&mut errors, first_error, &operation.location);
 
             affected_paths.insert(operation.relative_path.clone());
             if let Some(source) = &operation.source_relative_path {
@@ -1111,13 +2480,35 @@ impl PreparedGeneration {
             });
         }
 
+        let props = self
+            .props
+            .iter()
+            .map(|(name, value)| ResolvedProp {
+                name: name.clone(),
+                value: value.clone(),
+                source: self
+                    .prop_sources
+                    .get(name)
+                    .copied()
+                    .unwrap_or(PropSource::Config),
+            })
+            .collect();
+
         PlanOutput {
             operations,
             affected_paths: affected_paths.into_iter().collect(),
+            hooks: self.hooks.iter().map(PreparedHook::planned).collect(),
+            props,
             warnings,
             errors,
         }
     }
+
+    fn regenerates(&self, path: &Path) -> bool {
+        self.baseline
+            .as_ref()
+            .is_some_and(|baseline| baseline.regenerate.contains(path))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1138,6 +2529,9 @@ struct PreparedOperation {
     end_marker: Option<String>,
     mode: Option<u32>,
     if_exists: Option<IfExists>,
+    merge_base: Option<String>,
+    /// The rule the operation was built from.
+    location: ConfigLocation,
 }
 
 impl PreparedOperation {
@@ -1156,7 +2550,7 @@ impl PreparedOperation {
 
     fn will_modify(&self, exists: bool) -> bool {
         if self.kind == FileOperationKind::Write {
-            return exists && self.if_exists == Some(IfExists::Overwrite);
+            return exists && matches!(self.if_exists, Some(IfExists::Overwrite | IfExists::Merge));
         }
         matches!(
             self.kind,
@@ -1195,9 +2589,33 @@ impl PreparedOperation {
                         }
                         IfExists::Skip => return Ok(()),
                         IfExists::Overwrite => {}
+                        IfExists::Merge => {
+                            // Write contents already end the way the rule asks.
+                            let generated = self.content()?.to_string();
+                            let merged = merge3(
+                                self.merge_base.as_deref().unwrap_or_default(),
+                                &file.current,
+                                &generated,
+                            );
+                            for conflict in &merged.conflicts {
+                                warnings.push(Diagnostic::warning(
+                                    "merge_conflict",
+                                    format!(
+                                        "local edits conflict with the generated content at lines {}-{}",
+                                        conflict.start,
+                                        conflict.end - 1
+                                    ),
+                                    Some(file.relative_path.clone()),
+                                ));
+                            }
+                            file.current = merged.content;
+                            file.generated = Some(generated);
+                            return Ok(());
+                        }
                     }
                 }
-                file.current = format!("{}\n", self.content()?.trim_end());
+                file.current = self.content()?.to_string();
+                file.generated = None;
                 file.deleted = false;
                 file.existed = true;
             }
@@ -1212,6 +2630,8 @@ impl PreparedOperation {
                 }
                 file.current.clear();
                 file.deleted = true;
+                // A deleted rename target no longer stands for its source.
+                file.renamed_from = None;
             }
             FileOperationKind::Rename | FileOperationKind::Move => {
                 let source_path = self.source_path()?;
@@ -1243,6 +2663,7 @@ impl PreparedOperation {
                     target.current = source_content;
                     target.deleted = false;
                     target.existed = true;
+                    target.renamed_from = Some(source_path.to_path_buf());
                 }
                 let source = simulated_file(files, source_path, source_relative_path)?;
                 source.current.clear();
@@ -1452,6 +2873,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing content".to_string(),
+                location: None,
             })
     }
 
@@ -1459,6 +2881,7 @@ impl PreparedOperation {
         self.if_exists.ok_or_else(|| CoreError::InvalidConfig {
             label: "inline config".to_string(),
             message: "write operation is missing if_exists".to_string(),
+            location: None,
         })
     }
 
@@ -1468,6 +2891,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing marker".to_string(),
+                location: None,
             })
     }
 
@@ -1477,6 +2901,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing start_marker".to_string(),
+                location: None,
             })
     }
 
@@ -1486,6 +2911,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing end_marker".to_string(),
+                location: None,
             })
     }
 
@@ -1495,6 +2921,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing source path".to_string(),
+                location: None,
             })
     }
 
@@ -1504,6 +2931,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing source path".to_string(),
+                location: None,
             })
     }
 
@@ -1513,6 +2941,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing target path".to_string(),
+                location: None,
             })
     }
 
@@ -1522,6 +2951,7 @@ impl PreparedOperation {
             .ok_or_else(|| CoreError::InvalidConfig {
                 label: "inline config".to_string(),
                 message: "operation is missing target path".to_string(),
+                location: None,
             })
     }
 }
@@ -1532,8 +2962,12 @@ struct SimulatedFile {
     relative_path: String,
     original: String,
     current: String,
+    original_existed: bool,
     existed: bool,
     deleted: bool,
+    renamed_from: Option<PathBuf>,
+    /// Pure generated content when `current` is a merge with local edits.
+    generated: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -1562,7 +2996,7 @@ impl Simulation {
     fn changed_files(&self) -> Vec<&SimulatedFile> {
         self.files
             .values()
-            .filter(|file| file.original != file.current || (file.existed && file.deleted))
+            .filter(|file| file.original != file.current || (file.original_existed && file.deleted))
             .collect()
     }
 
@@ -1587,6 +3021,7 @@ impl Simulation {
         if !self.errors.is_empty() {
             return DiffOutput {
                 diff: String::new(),
+                files: Vec::new(),
                 summary: ChangeSummary::empty(),
                 warnings: plan_warnings,
                 errors: self.errors.clone(),
@@ -1602,7 +3037,13 @@ impl Simulation {
             .map(|file| file.relative_path.clone())
             .collect::<Vec<_>>();
 
-        for file in changed_files {
+        let renamed_sources = changed_files
+            .iter()
+            .filter_map(|file| file.renamed_from.as_deref())
+            .collect::<BTreeSet<_>>();
+        let mut files = Vec::new();
+
+        for file in &changed_files {
             let text_diff = TextDiff::from_lines(&file.original, &file.current);
             additions += text_diff
                 .iter_all_changes()
@@ -1621,10 +3062,16 @@ impl Simulation {
                     )
                     .to_string(),
             );
+
+            if file.deleted && renamed_sources.contains(file.path.as_path()) {
+                continue;
+            }
+            files.push(self.file_diff(file));
         }
 
         DiffOutput {
             diff,
+            files,
             summary: ChangeSummary {
                 files_changed: changed_paths.len(),
                 changed_files: changed_paths,
@@ -1635,6 +3082,80 @@ impl Simulation {
             errors: Vec::new(),
         }
     }
+
+    fn file_diff(&self, file: &SimulatedFile) -> FileDiff {
+        let source = file
+            .renamed_from
+            .as_ref()
+            .filter(|_| !file.deleted)
+            .and_then(|path| self.files.get(path));
+        let (status, original) = match source {
+            Some(source) => (FileDiffStatus::Renamed, source.original.as_str()),
+            None if file.deleted => (FileDiffStatus::Deleted, file.original.as_str()),
+            None if !file.original_existed => (FileDiffStatus::Created, file.original.as_str()),
+            None => (FileDiffStatus::Modified, file.original.as_str()),
+        };
+
+        let text_diff = TextDiff::from_lines(original, &file.current);
+        let mut additions = 0;
+        let mut deletions = 0;
+        let mut hunks = Vec::new();
+        for group in text_diff.grouped_ops(3) {
+            let (Some(first), Some(last)) = (group.first(), group.last()) else {
+                continue;
+            };
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+            let mut lines = Vec::new();
+            for op in &group {
+                for change in text_diff.iter_changes(op) {
+                    let kind = match change.tag() {
+                        ChangeTag::Equal => DiffLineKind::Context,
+                        ChangeTag::Insert => {
+                            additions += 1;
+                            DiffLineKind::Insert
+                        }
+                        ChangeTag::Delete => {
+                            deletions += 1;
+                            DiffLineKind::Delete
+                        }
+                    };
+                    lines.push(DiffLine {
+                        kind,
+                        old_line: change.old_index().map(|index| index + 1),
+                        new_line: change.new_index().map(|index| index + 1),
+                        content: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                    });
+                }
+            }
+            hunks.push(DiffHunk {
+                old_start: hunk_start(&old_range),
+                old_lines: old_range.len(),
+                new_start: hunk_start(&new_range),
+                new_lines: new_range.len(),
+                lines,
+            });
+        }
+
+        FileDiff {
+            path: file.relative_path.clone(),
+            old_path: source.map(|source| source.relative_path.clone()),
+            status,
+            additions,
+            deletions,
+            hunks,
+        }
+    }
+}
+
+/// Uses the unified diff convention: 1-based, and an empty range starts at the
+/// line just before it.
+fn hunk_start(range: &std::ops::Range<usize>) -> usize {
+    if range.is_empty() {
+        range.start
+    } else {
+        range.start + 1
+    }
 }
 
 fn rule_path(rule: &Rule) -> &str {
@@ -1670,14 +3191,18 @@ fn simulated_file<'a>(
                 relative_path: relative_path.to_string(),
                 original: current.clone(),
                 current,
+                original_existed: original.is_some(),
                 existed: original.is_some(),
                 deleted: false,
+                renamed_from: None,
+                generated: None,
             },
         );
     }
     files.get_mut(path).ok_or_else(|| CoreError::InvalidConfig {
         label: "simulation".to_string(),
         message: "failed to load simulated file".to_string(),
+        location: None,
     })
 }
 
@@ -1773,47 +3298,267 @@ fn set_mode(path: &Path, _mode: u32) -> Result<(), CoreError> {
     Err(CoreError::InvalidConfig {
         label: path_to_string(path),
         message: "chmod is only supported on Unix platforms".to_string(),
+        location: None,
     })
 }
 
-fn parse_json_config(value: &JsonValue) -> Result<Config, String> {
+fn parse_json_config(value: &JsonValue) -> Result<Config, CoreError> {
+    let invalid = |message: String, location: Option<ConfigLocation>| CoreError::InvalidConfig {
+        label: "inline config".to_string(),
+        message,
+        location: location.map(Box::new),
+    };
     let object = value
         .as_object()
-        .ok_or_else(|| "config must be a JSON object".to_string())?;
+        .ok_or_else(|| invalid("config must be a JSON object".to_string(), None))?;
 
     for key in object.keys() {
-        if key != "props" && key != "rules" {
-            return Err(format!("unsupported config field `{key}`"));
+        if !matches!(
+            key.as_str(),
+            "meta" | "props" | "rules" | "hooks" | "formatters"
+        ) {
+            return Err(invalid(format!("unsupported config field `{key}`"), None));
         }
     }
 
+    let meta = match object.get("meta") {
+        None | Some(JsonValue::Null) => None,
+        Some(meta) => Some(
+            serde_json::from_value(meta.clone())
+                .map_err(|err| invalid(format!("invalid meta: {err}"), None))?,
+        ),
+    };
+
     let props = match object.get("props") {
         None | Some(JsonValue::Null) => Vec::new(),
-        Some(JsonValue::Object(props)) => {
-            let mut converted = Vec::with_capacity(props.len());
-            for (key, value) in props {
-                converted.push((
-                    key.clone(),
-                    Value::try_from(value.clone())
-                        .map_err(|err| format!("invalid prop `{key}`: {err}"))?,
-                ));
-            }
-            converted
+        Some(props @ JsonValue::Object(_)) => parse_json_props(props, "inline config")?,
+        Some(_) => {
+            return Err(invalid(
+                "config.props must be a JSON object when provided".to_string(),
+                None,
+            ));
+        }
+    };
+
+    let rules = match object.get("rules") {
+        None => Vec::new(),
+        Some(JsonValue::Array(rules)) => rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                serde_json::from_value::<Rule>(rule.clone()).map_err(|err| {
+                    let rule_type = rule.get("type").and_then(JsonValue::as_str);
+                    invalid(
+                        format!("invalid rule: {err}"),
+                        Some(ConfigLocation::rule(index, rule_type)),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => {
+            return Err(invalid(
+                "config.rules must be a JSON array".to_string(),
+                None,
+            ));
+        }
+    };
+
+    let hooks = match object.get("hooks") {
+        None => Vec::new(),
+        Some(JsonValue::Array(hooks)) => hooks
+            .iter()
+            .enumerate()
+            .map(|(index, hook)| {
+                serde_json::from_value::<Hook>(hook.clone()).map_err(|err| {
+                    invalid(
+                        format!("invalid hook: {err}"),
+                        Some(ConfigLocation::hook(index)),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => {
+            return Err(invalid(
+                "config.hooks must be a JSON array".to_string(),
+                None,
+            ));
+        }
+    };
+
+    let formatters = match object.get("formatters") {
+        None => Vec::new(),
+        Some(JsonValue::Array(formatters)) => formatters
+            .iter()
+            .enumerate()
+            .map(|(index, formatter)| {
+                serde_json::from_value::<Formatter>(formatter.clone()).map_err(|err| {
+                    invalid(
+                        format!("invalid formatter: {err}"),
+                        Some(ConfigLocation::formatter(index)),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => {
+            return Err(invalid(
+                "config.formatters must be a JSON array".to_string(),
+                None,
+            ));
         }
-        Some(_) => return Err("config.props must be a JSON object when provided".to_string()),
     };
 
-    let rules_value = object
-        .get("rules")
-        .cloned()
-        .unwrap_or_else(|| JsonValue::Array(Vec::new()));
-    if !rules_value.is_array() {
-        return Err("config.rules must be a JSON array".to_string());
+    Ok(Config {
+        meta,
+        props,
+        rules,
+        hooks,
+        formatters,
+    })
+}
+
+fn formatters_from_config(config: &Config) -> Result<Vec<PreparedFormatter>, CoreError> {
+    config
+        .formatters
+        .iter()
+        .enumerate()
+        .map(|(index, formatter)| {
+            let location = ConfigLocation::formatter(index);
+            let invalid = |message: String| CoreError::InvalidConfig {
+                label: "inline config".to_string(),
+                message,
+                location: Some(Box::new(location.clone())),
+            };
+            match &formatter.format_with {
+                FormatWith::Builtin(name) if !format::is_builtin(name) => {
+                    return Err(invalid(format!(
+                        "unknown formatter `{name}`; use auto, {} or a command array",
+                        format::builtin_names()
+                    )));
+                }
+                FormatWith::Command(command) if command.first().is_none_or(String::is_empty) => {
+                    return Err(invalid("formatter command must name a program".to_string()));
+                }
+                _ => {}
+            }
+            // `*` stays within a directory, so `*.rs` only matches the root.
+            let matcher = GlobBuilder::new(&formatter.glob)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| invalid(format!("invalid glob: {err}")))?
+                .compile_matcher();
+            Ok(PreparedFormatter {
+                matcher,
+                format_with: formatter.format_with.clone(),
+                location,
+                disabled: false,
+            })
+        })
+        .collect()
+}
+
+/// Pipes a file the rules changed through the first formatter whose glob
+/// matches it. A file merged with local edits may not even parse, so failing
+/// to format it is only a warning; what genify generated for it, the next
+/// merge base, is formatted either way.
+fn format_file(
+    prepared: &PreparedGeneration,
+    file: &mut SimulatedFile,
+    warnings: &mut Vec<Diagnostic>,
+    errors: &mut Vec<Diagnostic>,
+) {
+    if file.deleted || file.current == file.original {
+        return;
+    }
+    let Ok(relative) = file.path.strip_prefix(&prepared.effective_root) else {
+        return;
+    };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    let Some(formatter) = prepared
+        .formatters
+        .iter()
+        .find(|formatter| formatter.matcher.is_match(&relative))
+    else {
+        return;
+    };
+    if formatter.disabled {
+        return;
+    }
+    let Some(command) =
+        format::command(&formatter.format_with, &relative, &prepared.effective_root)
+    else {
+        return;
+    };
+    let location = Some(formatter.location.clone());
+    let format =
+        |content: &str| format::run(&command, &relative, &prepared.effective_root, content);
+
+    if let Some(generated) = &mut file.generated {
+        match format(generated) {
+            Ok(formatted) => *generated = formatted,
+            Err(message) => {
+                errors.push(
+                    Diagnostic::error("format_failed", message, Some(file.relative_path.clone()))
+                        .at(location),
+                );
+                return;
+            }
+        }
+    }
+    match format(&file.current) {
+        Ok(formatted) => file.current = formatted,
+        Err(message) if file.generated.is_some() => warnings.push(
+            Diagnostic::warning(
+                "format_skipped",
+                format!("{message}; the merged file is written unformatted"),
+                Some(file.relative_path.clone()),
+            )
+            .at(location),
+        ),
+        Err(message) => errors.push(
+            Diagnostic::error("format_failed", message, Some(file.relative_path.clone()))
+                .at(location),
+        ),
+    }
+}
+
+fn parse_json_props(value: &JsonValue, label: &str) -> Result<Map, CoreError> {
+    let invalid = |message: String, location: Option<ConfigLocation>| CoreError::InvalidConfig {
+        label: label.to_string(),
+        message,
+        location: location.map(Box::new),
+    };
+    let props = value
+        .as_object()
+        .ok_or_else(|| invalid("props must be a JSON object".to_string(), None))?;
+    let mut converted = Vec::with_capacity(props.len());
+    for (key, value) in props {
+        converted.push((
+            key.clone(),
+            Value::try_from(value.clone()).map_err(|err| {
+                invalid(
+                    format!("invalid prop: {err}"),
+                    Some(ConfigLocation::prop(key)),
+                )
+            })?,
+        ));
     }
-    let rules = serde_json::from_value::<Vec<Rule>>(rules_value)
-        .map_err(|err| format!("invalid config.rules: {err}"))?;
+    Ok(converted)
+}
 
-    Ok(Config { props, rules })
+/// Overrides props in place so they keep their declaration order.
+fn merge_props(
+    props: &mut Map,
+    sources: &mut BTreeMap<String, PropSource>,
+    overrides: Map,
+    source: PropSource,
+) {
+    for (key, value) in overrides {
+        sources.insert(key.clone(), source);
+        match props.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => props.push((key, value)),
+        }
+    }
 }
 
 fn normalize_path(path: &Path) -> Result<PathBuf, CoreError> {
@@ -1847,6 +3592,114 @@ fn read_optional_string(path: &Path) -> Result<Option<String>, CoreError> {
     }
 }
 
+fn apply_each_root(
+    mut input: ApplyRequest,
+    open: impl Fn(&str) -> Result<GenerationCore, CoreError>,
+) -> Result<RootsApplyOutput, CoreError> {
+    // Every root would otherwise resolve `root` inside itself.
+    if input.root.is_some() {
+        return Err(CoreError::RootWithRoots);
+    }
+    let roots = std::mem::take(&mut input.roots);
+    let mut reports = Vec::with_capacity(roots.len());
+    let mut failed = false;
+    for root in roots {
+        if failed && input.fail_fast {
+            reports.push(RootApplyReport {
+                root,
+                status: RootApplyStatus::Skipped,
+                changed_files: Vec::new(),
+                summary: "Skipped because an earlier root failed.".to_string(),
+                manifest: None,
+                hooks: Vec::new(),
+                git: None,
+                warnings: Vec::new(),
+                errors: Vec::new(),
+            });
+            continue;
+        }
+        let report = match open(&root).and_then(|core| core.apply(input.clone())) {
+            Ok(output) => RootApplyReport {
+                root,
+                status: if !output.errors.is_empty() {
+                    RootApplyStatus::Failed
+                } else if output.changed_files.is_empty() {
+                    RootApplyStatus::Unchanged
+                } else {
+                    RootApplyStatus::Changed
+                },
+                changed_files: output.changed_files,
+                summary: output.summary,
+                manifest: output.manifest,
+                hooks: output.hooks,
+                git: output.git,
+                warnings: output.warnings,
+                errors: output.errors,
+            },
+            Err(err) => RootApplyReport {
+                root,
+                status: RootApplyStatus::Failed,
+                changed_files: Vec::new(),
+                summary: "No files changed because the root failed.".to_string(),
+                manifest: None,
+                hooks: Vec::new(),
+                git: None,
+                warnings: Vec::new(),
+                errors: vec![Diagnostic::error(
+                    "root_failed",
+                    err.to_string(),
+                    None::<String>,
+                )],
+            },
+        };
+        failed |= report.status == RootApplyStatus::Failed;
+        reports.push(report);
+    }
+
+    let count = |status| {
+        reports
+            .iter()
+            .filter(|report| report.status == status)
+            .count()
+    };
+    let summary = format!(
+        "{} root(s): {} changed, {} unchanged, {} failed, {} skipped.",
+        reports.len(),
+        count(RootApplyStatus::Changed),
+        count(RootApplyStatus::Unchanged),
+        count(RootApplyStatus::Failed),
+        count(RootApplyStatus::Skipped),
+    );
+    Ok(RootsApplyOutput {
+        roots: reports,
+        summary,
+    })
+}
+
+fn write_creating_parent(path: &Path, content: &str) -> Result<(), CoreError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|source| CoreError::CreateDirectory {
+            path: path_to_string(parent),
+            source,
+        })?;
+    }
+    fs::write(path, content).map_err(|source| CoreError::WriteFile {
+        path: path_to_string(path),
+        source,
+    })
+}
+
+fn remove_optional_file(path: &Path) -> Result<(), CoreError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(source) if source.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(source) => Err(CoreError::WriteFile {
+            path: path_to_string(path),
+            source,
+        }),
+    }
+}
+
 fn path_to_string(path: &Path) -> String {
     path.to_string_lossy().to_string()
 }
@@ -2016,7 +3869,11 @@ mod tests {
             })
             .expect_err("path traversal should be rejected");
 
-        assert!(matches!(err, CoreError::PathOutsideRoot { .. }));
+        let CoreError::Located { location, source } = err else {
+            panic!("error should point at the rule: {err}");
+        };
+        assert!(matches!(*source, CoreError::PathOutsideRoot { .. }));
+        assert_eq!(location.to_string(), "rules[0], type=write");
         let _ = fs::remove_dir_all(root);
     }
 
@@ -2050,6 +3907,866 @@ mod tests {
         let _ = fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn preview_returns_final_contents_without_writing() {
+        let root = temp_root("preview");
+        fs::write(root.join("old.rs"), "moved\n").expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let output = core
+            .preview(GenerationRequest {
+                config: Some(json!({
+                    "rules": [
+                        {"type": "move", "from": "old.rs", "to": "new.rs"},
+                        {"type": "append", "path": "new.rs", "content": "{{ name }}"}
+                    ]
+                })),
+                props: Some(json!({"name": "tail"})),
+                ..GenerationRequest::default()
+            })
+            .expect("preview should be generated");
+
+        assert_eq!(
+            output.files,
+            vec![
+                PreviewFile {
+                    path: "new.rs".to_string(),
+                    content: Some("moved\ntail\n".to_string()),
+                },
+                PreviewFile {
+                    path: "old.rs".to_string(),
+                    content: None,
+                },
+            ]
+        );
+        assert!(root.join("old.rs").exists());
+        assert!(!root.join("new.rs").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn list_templates_names_configs_from_the_catalog() {
+        let root = temp_root("catalog");
+        fs::create_dir_all(root.join("services")).expect("test dir should be created");
+        fs::write(
+            root.join("services/rust.toml"),
+            "[meta]\nname = \"Rust service\"\nversion = \"1.2.0\"\nauthors = [\"Platform team\"]\ntags = [\"http\"]\n\n[props]\nname = \"demo\"\n\n[[rules]]\ntype = \"write\"\npath = \"{{ name }}/{{ team }}\"\ncontent = \"\"\nif_exists = \"skip\"\n",
+        )
+        .expect("test file should be written");
+        fs::write(root.join("plain.toml"), "").expect("test file should be written");
+        fs::write(
+            root.join(CATALOG_FILE),
+            r#"
+[[templates]]
+name = "rust-service"
+path = "services/rust.toml"
+description = "HTTP service in Rust"
+tags = ["rust"]
+
+[[templates]]
+name = "remote"
+path = "https://example.com/genify.toml"
+"#,
+        )
+        .expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let items = core
+            .list_templates()
+            .expect("templates should be listed")
+            .items;
+
+        let summary = items
+            .iter()
+            .map(|item| (item.name.as_str(), item.path.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("remote", "https://example.com/genify.toml"),
+                ("plain.toml", "plain.toml"),
+                ("rust-service", "services/rust.toml"),
+            ]
+        );
+        assert_eq!(
+            items[2].description.as_deref(),
+            Some("HTTP service in Rust")
+        );
+        assert_eq!(items[2].title.as_deref(), Some("Rust service"));
+        assert_eq!(items[2].version.as_deref(), Some("1.2.0"));
+        assert_eq!(items[2].authors, vec!["Platform team"]);
+        assert_eq!(items[2].tags, vec!["rust"]);
+        assert_eq!(items[2].required_props, vec!["team"]);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn list_templates_warns_about_missing_catalog_entries() {
+        let root = temp_root("catalog-missing");
+        fs::write(root.join("present.toml"), "").expect("test file should be written");
+        fs::write(
+            root.join(CATALOG_FILE),
+            r#"
+[[templates]]
+name = "gone"
+path = "gone.toml"
+
+[[templates]]
+name = "present"
+path = "present.toml"
+"#,
+        )
+        .expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let output = core.list_templates().expect("templates should be listed");
+
+        let names = output
+            .items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["present"]);
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].code, "catalog_entry_missing");
+        assert_eq!(output.warnings[0].path.as_deref(), Some("gone.toml"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn diff_returns_structured_file_hunks() {
+        let root = temp_root("structured-diff");
+        fs::write(root.join("app.rs"), "a\nold\nc\n").expect("test file should be written");
+        fs::write(root.join("old.rs"), "moved\n").expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let output = core
+            .diff(GenerationRequest {
+                config: Some(json!({
+                    "rules": [
+                        {
+                            "type": "replace",
+                            "path": "app.rs",
+                            "replace": "old",
+                            "content": "new"
+                        },
+                        {
+                            "type": "move",
+                            "from": "old.rs",
+                            "to": "new.rs"
+                        },
+                        {
+                            "type": "write",
+                            "path": "created.txt",
+                            "content": "hello",
+                            "if_exists": "error"
+                        }
+                    ]
+                })),
+                ..GenerationRequest::default()
+            })
+            .expect("diff should be generated");
+
+        let statuses = output
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                ("app.rs", FileDiffStatus::Modified),
+                ("created.txt", FileDiffStatus::Created),
+                ("new.rs", FileDiffStatus::Renamed),
+            ]
+        );
+
+        let modified = &output.files[0];
+        assert_eq!(modified.hunks.len(), 1);
+        let hunk = &modified.hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines), (1, 3));
+        assert_eq!((hunk.new_start, hunk.new_lines), (1, 3));
+        let deleted = &hunk.lines[1];
+        assert_eq!(deleted.kind, DiffLineKind::Delete);
+        assert_eq!(deleted.old_line, Some(2));
+        assert_eq!(deleted.content, "old");
+        let inserted = &hunk.lines[2];
+        assert_eq!(inserted.kind, DiffLineKind::Insert);
+        assert_eq!(inserted.new_line, Some(2));
+        assert_eq!(inserted.content, "new");
+
+        let renamed = &output.files[2];
+        assert_eq!(renamed.old_path.as_deref(), Some("old.rs"));
+        assert!(renamed.hunks.is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn diff_reports_a_moved_then_deleted_file_as_deleted() {
+        let root = temp_root("move-delete-diff");
+        fs::write(root.join("a.txt"), "gone\n").expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let output = core
+            .diff(GenerationRequest {
+                config: Some(json!({
+                    "rules": [
+                        { "type": "move", "from": "a.txt", "to": "b.txt" },
+                        { "type": "delete", "path": "b.txt" }
+                    ]
+                })),
+                ..GenerationRequest::default()
+            })
+            .expect("diff should be generated");
+
+        assert_eq!(output.summary.changed_files, vec!["a.txt"]);
+        assert_eq!(output.files.len(), 1);
+        let deleted = &output.files[0];
+        assert_eq!(deleted.path, "a.txt");
+        assert_eq!(deleted.status, FileDiffStatus::Deleted);
+        assert_eq!(deleted.deletions, 1);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn update_regenerates_untouched_files_and_keeps_edited_ones() {
+        let root = temp_root("update-manifest");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let source = ManifestSource {
+            location: "service.toml".to_string(),
+            hash: None,
+        };
+
+        let first = core
+            .apply(ApplyRequest {
+                config: Some(json!({
+                    "props": { "name": "demo" },
+                    "rules": [
+                        { "type": "write", "path": "a.txt", "content": "a v1 {{ name }}", "if_exists": "error" },
+                        { "type": "write", "path": "b.txt", "content": "b v1", "if_exists": "error" },
+                        { "type": "write", "path": "c.txt", "content": "c v1", "if_exists": "error" }
+                    ]
+                })),
+                props: Some(json!({ "name": "custom" })),
+                manifest: Some(source.clone()),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("first apply should succeed");
+        assert_eq!(first.manifest.as_deref(), Some(MANIFEST_PATH));
+        let manifest = core.manifest(None).expect("manifest should be written");
+        assert_eq!(manifest.source, source);
+        assert!(manifest.files["a.txt"].created);
+        assert_eq!(manifest.props()[0].1, Value::String("custom".to_string()));
+
+        fs::write(root.join("b.txt"), "b edited\n").expect("test file should be written");
+
+        let output = core
+            .apply(ApplyRequest {
+                config: Some(json!({
+                    "props": { "name": "demo" },
+                    "rules": [
+                        { "type": "write", "path": "a.txt", "content": "a v2 {{ name }}", "if_exists": "error" },
+                        { "type": "write", "path": "b.txt", "content": "b v2", "if_exists": "error" }
+                    ]
+                })),
+                update: true,
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("update should succeed");
+
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        assert_eq!(output.changed_files, vec!["a.txt", "c.txt"]);
+        assert_eq!(output.warnings[0].code, "locally_modified");
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).expect("file should exist"),
+            "a v2 custom\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("b.txt")).expect("file should exist"),
+            "b edited\n"
+        );
+        assert!(!root.join("c.txt").exists());
+
+        let manifest = core.manifest(None).expect("manifest should be updated");
+        assert!(!manifest.files.contains_key("c.txt"));
+        assert_eq!(
+            manifest.files["a.txt"].hash,
+            content_hash("a v2 custom\n".as_bytes())
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn update_reapplies_patches_to_the_original_content_with_new_defaults() {
+        let root = temp_root("update-patch");
+        fs::write(root.join("notes.txt"), "mine\n").expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let config = |version: i64| {
+            json!({
+                "props": { "name": "svc", "version": version, "image": "{{ name }}:{{ version }}" },
+                "rules": [
+                    { "type": "append", "path": "notes.txt", "content": "appended {{ image }}" }
+                ]
+            })
+        };
+        let source = ManifestSource {
+            location: "service.toml".to_string(),
+            hash: None,
+        };
+
+        core.apply(ApplyRequest {
+            config: Some(config(1)),
+            props: Some(json!({ "name": "api" })),
+            manifest: Some(source.clone()),
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        })
+        .expect("first apply should succeed");
+        let manifest = core.manifest(None).expect("manifest should be written");
+        assert_eq!(
+            manifest.props(),
+            vec![("name".to_string(), Value::String("api".to_string()))]
+        );
+
+        let output = core
+            .apply(ApplyRequest {
+                config: Some(config(2)),
+                update: true,
+                manifest: Some(source),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("update should succeed");
+
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        assert_eq!(
+            fs::read_to_string(root.join("notes.txt")).expect("file should exist"),
+            "mine\nappended api:2\n"
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn update_merges_local_edits_with_regenerated_content() {
+        let root = temp_root("update-merge");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let rules = |content: &str| {
+            json!({
+                "rules": [
+                    { "type": "write", "path": "notes.txt", "content": content, "if_exists": "merge" }
+                ]
+            })
+        };
+
+        core.apply(ApplyRequest {
+            config: Some(rules("one\ntwo\nthree")),
+            manifest: Some(ManifestSource {
+                location: "notes.toml".to_string(),
+                hash: None,
+            }),
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        })
+        .expect("first apply should succeed");
+        assert_eq!(
+            fs::read_to_string(root.join(".genify/baseline/notes.txt"))
+                .expect("baseline should exist"),
+            "one\ntwo\nthree\n"
+        );
+
+        fs::write(root.join("notes.txt"), "ONE\ntwo\nmine\n").expect("test file should be written");
+
+        let output = core
+            .apply(ApplyRequest {
+                config: Some(rules("one\ntwo\nTHREE\nfour")),
+                update: true,
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("update should succeed");
+
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].code, "merge_conflict");
+        assert!(output.warnings[0].message.contains("lines 3-8"));
+        assert_eq!(
+            fs::read_to_string(root.join("notes.txt")).expect("file should exist"),
+            "ONE\ntwo\n<<<<<<< current\nmine\n=======\nTHREE\nfour\n>>>>>>> generated\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join(".genify/baseline/notes.txt"))
+                .expect("baseline should exist"),
+            "one\ntwo\nTHREE\nfour\n"
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn apply_roots_reports_each_root_and_continues_after_failures() {
+        let root = temp_root("apply-roots");
+        for repo in ["one", "two", "three"] {
+            fs::create_dir_all(root.join(repo)).expect("test root should be created");
+        }
+        fs::write(root.join("two/ci.yml"), "custom\n").expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let request = |fail_fast| ApplyRequest {
+            config: Some(json!({
+                "rules": [
+                    { "type": "write", "path": "ci.yml", "content": "shared", "if_exists": "error" }
+                ]
+            })),
+            roots: vec![
+                "two".to_string(),
+                "missing".to_string(),
+                "one".to_string(),
+                "../outside".to_string(),
+            ],
+            fail_fast,
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        };
+
+        let output = core.apply_roots(request(true)).expect("apply should run");
+        let statuses: Vec<_> = output.roots.iter().map(|report| report.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                RootApplyStatus::Failed,
+                RootApplyStatus::Skipped,
+                RootApplyStatus::Skipped,
+                RootApplyStatus::Skipped
+            ]
+        );
+        assert!(!root.join("one/ci.yml").exists());
+
+        let output = core.apply_roots(request(false)).expect("apply should run");
+        let statuses: Vec<_> = output.roots.iter().map(|report| report.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                RootApplyStatus::Failed,
+                RootApplyStatus::Failed,
+                RootApplyStatus::Changed,
+                RootApplyStatus::Failed
+            ]
+        );
+        assert!(output.has_failures());
+        assert_eq!(output.roots[2].changed_files, vec!["ci.yml"]);
+        assert_eq!(output.roots[3].errors[0].code, "root_failed");
+        assert_eq!(
+            output.summary,
+            "4 root(s): 1 changed, 0 unchanged, 3 failed, 0 skipped."
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("one/ci.yml")).expect("file should exist"),
+            "shared\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("two/ci.yml")).expect("file should exist"),
+            "custom\n"
+        );
+        assert!(matches!(
+            core.apply_roots(ApplyRequest {
+                root: Some("one".to_string()),
+                ..request(false)
+            }),
+            Err(CoreError::RootWithRoots)
+        ));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_runs_hooks_only_when_allowed() {
+        let root = temp_root("hooks");
+        let config = |before: &str| {
+            json!({
+                "props": { "name": "demo" },
+                "rules": [
+                    { "type": "write", "path": "out.txt", "content": "generated", "if_exists": "overwrite" }
+                ],
+                "hooks": [
+                    { "when": "before", "command": ["sh", "-c", before] },
+                    {
+                        "when": "after",
+                        "command": ["sh", "-c", "cat out.txt; echo $GREETING >&2"],
+                        "env": { "GREETING": "hi {{ name }}" }
+                    }
+                ]
+            })
+        };
+        let request = |before: &str| ApplyRequest {
+            config: Some(config(before)),
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        };
+        let before = "test ! -e out.txt && touch seen.txt";
+
+        let plan = GenerationCore::new(&root)
+            .expect("root should be valid")
+            .plan(GenerationRequest {
+                config: Some(config(before)),
+                ..GenerationRequest::default()
+            })
+            .expect("plan should succeed");
+        assert_eq!(plan.hooks.len(), 2);
+        assert_eq!(plan.hooks[1].env["GREETING"], "hi demo");
+        assert_eq!(plan.hooks[1].cwd, ".");
+
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let output = core.apply(request(before)).expect("apply should run");
+        assert_eq!(output.changed_files, vec!["out.txt"]);
+        assert!(output.hooks.is_empty());
+        assert_eq!(output.warnings[0].code, "hooks_disabled");
+        assert!(!root.join("seen.txt").exists());
+
+        fs::remove_file(root.join("out.txt")).expect("test file should be removed");
+        let core = core.allow_hooks(true);
+        let output = core.apply(request(before)).expect("apply should run");
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        assert!(root.join("seen.txt").exists());
+        let after = &output.hooks[1];
+        assert_eq!((after.when, after.status), (HookWhen::After, Some(0)));
+        assert_eq!(after.stdout, "generated\n");
+        assert_eq!(after.stderr, "hi demo\n");
+
+        fs::remove_file(root.join("out.txt")).expect("test file should be removed");
+        let output = core.apply(request("exit 3")).expect("apply should run");
+        assert!(output.changed_files.is_empty());
+        assert_eq!(output.hooks.len(), 1);
+        assert_eq!(output.hooks[0].status, Some(3));
+        assert_eq!(output.errors[0].code, "hook_failed");
+        assert!(!root.join("out.txt").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn before_hooks_need_an_existing_cwd() {
+        let root = temp_root("hook-cwd");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let plan = |when: &str| {
+            core.plan(GenerationRequest {
+                config: Some(json!({
+                    "rules": [
+                        { "type": "write", "path": "app/main.rs", "content": "fn main() {}", "if_exists": "error" }
+                    ],
+                    "hooks": [{ "when": when, "command": ["cargo", "fmt"], "cwd": "app" }]
+                })),
+                ..GenerationRequest::default()
+            })
+        };
+
+        let err = plan("before").expect_err("a missing before hook cwd should be rejected");
+        assert!(
+            err.to_string()
+                .contains("before hook cwd `app` is not a directory"),
+            "{err}"
+        );
+        let plan = plan("after").expect("after hooks may run in created directories");
+        assert_eq!(plan.hooks[0].cwd, "app");
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn formatters_apply_to_matching_files_before_diffing() {
+        let root = temp_root("formatters");
+        let core = GenerationCore::new(&root)
+            .expect("root should be valid")
+            .allow_hooks(true);
+        let config = |command: JsonValue| {
+            json!({
+                "rules": [
+                    { "type": "write", "path": "top.txt", "content": "top", "if_exists": "overwrite" },
+                    { "type": "write", "path": "nested/deep.txt", "content": "deep", "if_exists": "overwrite" }
+                ],
+                "formatters": [{ "glob": "*.txt", "format_with": command }]
+            })
+        };
+
+        let diff = core
+            .diff(GenerationRequest {
+                config: Some(config(json!(["tr", "a-z", "A-Z"]))),
+                ..GenerationRequest::default()
+            })
+            .expect("diff should succeed");
+        assert!(diff.diff.contains("+TOP"), "{}", diff.diff);
+        assert!(diff.diff.contains("+deep"), "{}", diff.diff);
+
+        let output = core
+            .apply(ApplyRequest {
+                config: Some(config(json!(["sh", "-c", "exit 1"]))),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("apply should run");
+        assert!(output.changed_files.is_empty());
+        assert_eq!(output.errors[0].code, "format_failed");
+        assert_eq!(output.errors[0].path.as_deref(), Some("top.txt"));
+
+        let err = core
+            .plan(GenerationRequest {
+                config: Some(config(json!("clang-tidy"))),
+                ..GenerationRequest::default()
+            })
+            .expect_err("unknown formatters should be rejected");
+        assert!(err.to_string().contains("unknown formatter `clang-tidy`"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn formatters_only_run_when_hooks_are_allowed() {
+        let root = temp_root("formatters-disabled");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let config = json!({
+            "rules": [{ "type": "write", "path": "top.txt", "content": "top", "if_exists": "overwrite" }],
+            "formatters": [
+                { "glob": "*.txt", "format_with": ["sh", "-c", "touch ran; tr a-z A-Z"] },
+                { "glob": "*.rs", "format_with": "rustfmt" }
+            ]
+        });
+
+        let diff = core
+            .diff(GenerationRequest {
+                config: Some(config.clone()),
+                ..GenerationRequest::default()
+            })
+            .expect("diff should succeed");
+        assert!(diff.diff.contains("+top"), "{}", diff.diff);
+        assert_eq!(diff.warnings[0].code, "formatters_disabled");
+        assert!(diff.warnings[0].message.starts_with("2 formatter(s)"));
+        let preview = core
+            .preview(GenerationRequest {
+                config: Some(config.clone()),
+                ..GenerationRequest::default()
+            })
+            .expect("preview should succeed");
+        assert_eq!(preview.files[0].content.as_deref(), Some("top\n"));
+        assert!(!root.join("ran").exists());
+
+        let output = core
+            .allow_hooks(true)
+            .diff(GenerationRequest {
+                config: Some(config),
+                ..GenerationRequest::default()
+            })
+            .expect("diff should succeed");
+        assert!(output.diff.contains("+TOP"), "{}", output.diff);
+        assert!(output.warnings.is_empty());
+        assert!(root.join("ran").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn apply_commits_only_the_changed_files_on_a_branch() {
+        let root = temp_root("git-commit");
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&root)
+                .output()
+                .expect("git should run");
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "test"]);
+        fs::write(root.join("notes.txt"), "v1\n").expect("test file should be written");
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        fs::write(root.join("notes.txt"), "v2\n").expect("test file should be written");
+        git(&["add", "notes.txt"]);
+
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let request = |content: &str| ApplyRequest {
+            config: Some(json!({
+                "rules": [
+                    { "type": "write", "path": "ci.yml", "content": content, "if_exists": "overwrite" }
+                ]
+            })),
+            git_branch: Some("genify/ci".to_string()),
+            git_commit: Some("Add CI".to_string()),
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        };
+
+        let output = core.apply(request("v1")).expect("apply should run");
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        let report = output.git.expect("git should be reported");
+        assert!(report.created_branch);
+        assert_eq!(report.staged, vec!["ci.yml"]);
+        assert_eq!(report.commit, Some(git(&["rev-parse", "HEAD"])));
+        assert_eq!(git(&["branch", "--show-current"]), "genify/ci");
+        assert_eq!(git(&["show", "--name-only", "--format=", "HEAD"]), "ci.yml");
+        assert_eq!(git(&["diff", "--cached", "--name-only"]), "notes.txt");
+
+        fs::write(root.join("ci.yml"), "local\n").expect("test file should be written");
+        let output = core.apply(request("v2")).expect("apply should run");
+        assert!(!output.git.expect("git should be reported").created_branch);
+        assert_eq!(output.errors[0].code, "uncommitted_changes");
+        assert_eq!(output.errors[0].path.as_deref(), Some("ci.yml"));
+        assert_eq!(
+            fs::read_to_string(root.join("ci.yml")).expect("file should exist"),
+            "local\n"
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_commits_mode_changes_and_copies_from_dirty_files() {
+        let root = temp_root("git-chmod");
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&root)
+                .output()
+                .expect("git should run");
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        git(&["init", "--quiet"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "test"]);
+        fs::write(root.join("run.sh"), "echo hi\n").expect("test file should be written");
+        fs::write(root.join("template.txt"), "v1\n").expect("test file should be written");
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        fs::write(root.join("template.txt"), "local\n").expect("test file should be written");
+
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let output = core
+            .apply(ApplyRequest {
+                config: Some(json!({
+                    "rules": [
+                        { "type": "chmod", "path": "run.sh", "mode": "755" },
+                        { "type": "copy", "from": "template.txt", "to": "copy.txt" }
+                    ]
+                })),
+                git_commit: Some("Make run.sh executable".to_string()),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("apply should run");
+
+        assert!(output.errors.is_empty(), "{:?}", output.errors);
+        let report = output.git.expect("git should be reported");
+        assert_eq!(report.staged, vec!["copy.txt", "run.sh"]);
+        assert_eq!(
+            git(&["show", "--name-only", "--format=", "HEAD"]),
+            "copy.txt\nrun.sh"
+        );
+        assert!(git(&["ls-files", "--stage", "run.sh"]).starts_with("100755"));
+        assert_eq!(git(&["status", "--porcelain"]), "M template.txt");
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn refused_apply_leaves_the_branch_it_started_on() {
+        let root = temp_root("git-refused");
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&root)
+                .output()
+                .expect("git should run");
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        };
+        git(&["init", "--quiet", "--initial-branch", "main"]);
+        git(&["config", "user.email", "test@example.com"]);
+        git(&["config", "user.name", "test"]);
+        fs::write(root.join("ci.yml"), "v1\n").expect("test file should be written");
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        fs::write(root.join("ci.yml"), "local\n").expect("test file should be written");
+
+        let core = GenerationCore::new(&root).expect("root should be valid");
+        let output = core
+            .apply(ApplyRequest {
+                config: Some(json!({
+                    "rules": [
+                        { "type": "write", "path": "ci.yml", "content": "v2", "if_exists": "overwrite" }
+                    ]
+                })),
+                git_branch: Some("genify/ci".to_string()),
+                git_commit: Some("Update CI".to_string()),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("apply should run");
+
+        assert_eq!(output.errors[0].code, "uncommitted_changes");
+        let report = output.git.expect("git should be reported");
+        assert_eq!(report.branch, None);
+        assert!(!report.created_branch);
+        assert_eq!(git(&["branch", "--show-current"]), "main");
+        assert_eq!(git(&["branch", "--list", "genify/ci"]), "");
+        assert_eq!(
+            fs::read_to_string(root.join("ci.yml")).expect("file should exist"),
+            "local\n"
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn plan_merges_project_props_between_config_and_request() {
+        let root = temp_root("project-props");
+        fs::create_dir_all(root.join(".genify")).expect("test dir should be created");
+        fs::write(
+            root.join(PROJECT_PROPS_PATH),
+            "service = \"billing\"\nport = 8080\nowner = \"team-a\"\n",
+        )
+        .expect("test file should be written");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let plan = core
+            .plan(GenerationRequest {
+                config: Some(json!({
+                    "props": { "service": "demo", "port": 80, "tag": "{{ service }}:{{ port }}" },
+                    "rules": [
+                        { "type": "write", "path": "{{ service }}.txt", "content": "{{ tag }} {{ owner }}", "if_exists": "error" }
+                    ]
+                })),
+                props: Some(json!({ "owner": "team-b" })),
+                ..GenerationRequest::default()
+            })
+            .expect("plan should succeed");
+
+        assert_eq!(plan.affected_paths, vec!["billing.txt"]);
+        let props: Vec<_> = plan
+            .props
+            .iter()
+            .map(|prop| (prop.name.as_str(), &prop.value, prop.source))
+            .collect();
+        assert_eq!(
+            props,
+            vec![
+                ("port", &Value::Integer(8080), PropSource::Project),
+                (
+                    "service",
+                    &Value::String("billing".to_string()),
+                    PropSource::Project
+                ),
+                (
+                    "tag",
+                    &Value::String("billing:8080".to_string()),
+                    PropSource::Config
+                ),
+                (
+                    "owner",
+                    &Value::String("team-b".to_string()),
+                    PropSource::Request
+                ),
+            ]
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
     #[test]
     fn apply_requires_explicit_approval() {
         let root = temp_root("apply-approval");
@@ -2076,6 +4793,124 @@ mod tests {
         let _ = fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn list_templates_skips_ignored_paths() {
+        let root = temp_root("list-ignored");
+        for dir in [
+            ".git",
+            "node_modules/pkg",
+            "vendor",
+            "configs",
+            "target",
+            "tmp",
+        ] {
+            fs::create_dir_all(root.join(dir)).expect("test dir should be created");
+        }
+        fs::write(root.join(".gitignore"), "node_modules/\n").expect("test file should be written");
+        fs::write(root.join(".genifyignore"), "vendor/\n").expect("test file should be written");
+        for path in [
+            "node_modules/pkg/a.toml",
+            "vendor/b.toml",
+            "configs/c.toml",
+            "target/d.toml",
+            "tmp/e.toml",
+        ] {
+            fs::write(root.join(path), "").expect("test file should be written");
+        }
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let items = core
+            .list_templates()
+            .expect("templates should be listed")
+            .items;
+
+        let paths: Vec<_> = items.iter().map(|item| item.path.as_str()).collect();
+        assert_eq!(paths, vec!["configs/c.toml"]);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn apply_refuses_writes_to_ignored_and_protected_paths() {
+        let root = temp_root("apply-protected");
+        fs::create_dir_all(root.join(".git")).expect("test dir should be created");
+        fs::write(root.join(".gitignore"), "*.log\n").expect("test file should be written");
+        let core = GenerationCore::new(&root)
+            .and_then(|core| core.protect_paths(true, &["secrets/**".to_string()]))
+            .expect("protection should be valid");
+        let write = |path: &str| {
+            core.apply(ApplyRequest {
+                config: Some(json!({
+                    "rules": [
+                        { "type": "write", "path": "out.txt", "content": "ok", "if_exists": "error" },
+                        { "type": "write", "path": path, "content": "no", "if_exists": "error" }
+                    ]
+                })),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("apply should report errors")
+        };
+
+        let ignored = write("build/debug.log");
+        assert_eq!(ignored.errors[0].code, "ignored_path");
+        assert_eq!(ignored.errors[0].path.as_deref(), Some("build/debug.log"));
+        let protected = write("secrets/key.pem");
+        assert_eq!(protected.errors[0].code, "protected_path");
+        assert!(protected.changed_files.is_empty());
+        assert!(!root.join("out.txt").exists());
+
+        let allowed = write("notes.txt");
+        assert!(allowed.errors.is_empty());
+        assert!(root.join("notes.txt").exists());
+        assert!(matches!(
+            GenerationCore::new(&root)
+                .and_then(|core| core.protect_paths(false, &["[".to_string()])),
+            Err(CoreError::InvalidGlob { .. })
+        ));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn update_refuses_to_regenerate_protected_paths() {
+        let root = temp_root("update-protected");
+        let rules = |paths: &[&str]| {
+            let rules: Vec<_> = paths
+                .iter()
+                .map(|path| json!({ "type": "write", "path": path, "content": "v1", "if_exists": "error" }))
+                .collect();
+            Some(json!({ "rules": rules }))
+        };
+        GenerationCore::new(&root)
+            .expect("root should be valid")
+            .apply(ApplyRequest {
+                config: rules(&["out.txt", "secrets/token.txt"]),
+                manifest: Some(ManifestSource {
+                    location: "service.toml".to_string(),
+                    hash: None,
+                }),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("first apply should succeed");
+
+        let output = GenerationCore::new(&root)
+            .and_then(|core| core.protect_paths(false, &["secrets/**".to_string()]))
+            .expect("protection should be valid")
+            .apply(ApplyRequest {
+                config: rules(&["out.txt"]),
+                update: true,
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .expect("update should report errors");
+
+        assert_eq!(output.errors.len(), 1, "{:?}", output.errors);
+        assert_eq!(output.errors[0].code, "protected_path");
+        assert_eq!(output.errors[0].path.as_deref(), Some("secrets/token.txt"));
+        assert!(root.join("secrets/token.txt").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
     #[test]
     fn write_if_exists_overwrite_replaces_existing_file() {
         let root = temp_root("write-overwrite");
@@ -2188,6 +5023,51 @@ mod tests {
         let _ = fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn diagnostics_point_at_the_rule_they_come_from() {
+        let root = temp_root("diagnostic-location");
+        let core = GenerationCore::new(&root).expect("root should be valid");
+
+        let plan = core
+            .plan(GenerationRequest {
+                config: Some(json!({
+                    "rules": [
+                        {"type": "mkdir", "path": "src"},
+                        {"type": "replace", "path": "missing.txt", "replace": "a", "content": "b"}
+                    ]
+                })),
+                ..GenerationRequest::default()
+            })
+            .expect("plan should succeed");
+        let location = plan.warnings[0]
+            .location
+            .as_ref()
+            .expect("warning should point at its rule");
+        assert_eq!(location.rule, Some(1));
+        assert_eq!(location.rule_type.as_deref(), Some("replace"));
+
+        let output = core
+            .validate_config(ValidateConfigRequest {
+                config: Some(json!({
+                    "rules": [
+                        {"type": "mkdir", "path": "src"},
+                        {"type": "write", "path": "a.txt", "content": "a"}
+                    ]
+                })),
+            })
+            .expect("validation should return structured output");
+        assert!(!output.valid);
+        assert_eq!(
+            output.diagnostics[0]
+                .location
+                .as_ref()
+                .map(ToString::to_string)
+                .as_deref(),
+            Some("rules[1], type=write")
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
     #[test]
     fn append_once_is_idempotent() {
         let root = temp_root("append-once");
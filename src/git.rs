@@ -0,0 +1,276 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The git worktree a generation root is in.
+#[derive(Debug)]
+pub(crate) struct Worktree {
+    top_level: PathBuf,
+}
+
+impl Worktree {
+    /// The worktree containing `dir`.
+    pub(crate) fn open(dir: &Path) -> Result<Self, String> {
+        let top_level = git(dir, ["rev-parse", "--show-toplevel"])
+            .map_err(|_| format!("{} is not inside a git worktree", dir.display()))?;
+        let top_level = fs::canonicalize(&top_level).unwrap_or_else(|_| top_level.into());
+        Ok(Self { top_level })
+    }
+
+    /// Switches to `branch`, creating it at the current commit when it does
+    /// not exist yet. Returns whether it was created.
+    pub(crate) fn switch(&self, branch: &str) -> Result<bool, String> {
+        if self.run(["check-ref-format", "--branch", branch]).is_err() {
+            return Err(format!("`{branch}` is not a valid branch name"));
+        }
+        let exists = self
+            .run([
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("refs/heads/{branch}"),
+            ])
+            .is_ok();
+        let switch = if exists {
+            self.run(["switch", "--quiet", branch])
+        } else {
+            self.run(["switch", "--quiet", "--create", branch])
+        };
+        switch.map_err(|err| format!("failed to switch to branch {branch}: {err}"))?;
+        Ok(!exists)
+    }
+
+    /// The branch checked out, or the commit id when none is.
+    pub(crate) fn head(&self) -> Result<String, String> {
+        self.run(["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .or_else(|_| self.run(["rev-parse", "HEAD"]))
+            .map_err(|err| format!("failed to read the current branch: {err}"))
+    }
+
+    /// Switches from `branch` back to `previous`, a branch or commit id, and
+    /// deletes `branch` when `delete` is set.
+    pub(crate) fn switch_back(
+        &self,
+        previous: &str,
+        branch: &str,
+        delete: bool,
+    ) -> Result<(), String> {
+        self.run(["checkout", "--quiet", previous, "--"])
+            .map_err(|err| format!("failed to switch back to {previous}: {err}"))?;
+        if delete {
+            self.run(["branch", "--quiet", "--delete", "--force", branch])
+                .map_err(|err| format!("failed to delete branch {branch}: {err}"))?;
+        }
+        Ok(())
+    }
+
+    /// Those of `paths` with uncommitted changes, staged or not, untracked
+    /// files included.
+    pub(crate) fn uncommitted(&self, paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Not trimmed: the first entry's status may start with a space.
+        let status = output(
+            &self.top_level,
+            ["status", "--porcelain", "-z", "--untracked-files=all", "--"]
+                .iter()
+                .map(OsStr::new)
+                .chain(paths.iter().map(|path| path.as_os_str())),
+        )?;
+        let mut entries = status.split('\0').filter(|entry| !entry.is_empty());
+        let mut uncommitted = Vec::new();
+        while let Some(entry) = entries.next() {
+            let (code, path) = entry.split_at(entry.len().min(3));
+            uncommitted.push(self.top_level.join(path));
+            // Renames and copies are followed by the path they came from,
+            // which a rename changes too and a copy leaves alone.
+            if code.contains(['R', 'C'])
+                && let Some(source) = entries.next()
+                && code.contains('R')
+            {
+                uncommitted.push(self.top_level.join(source));
+            }
+        }
+        Ok(uncommitted)
+    }
+
+    /// Stages `paths`, deletions included.
+    pub(crate) fn stage(&self, paths: &[PathBuf]) -> Result<(), String> {
+        self.run(
+            ["add", "--all", "--"]
+                .iter()
+                .map(OsStr::new)
+                .chain(paths.iter().map(|path| path.as_os_str())),
+        )
+        .map(drop)
+        .map_err(|err| format!("failed to stage the changed files: {err}"))
+    }
+
+    /// Commits the staged changes to `paths` and nothing else the index
+    /// holds. Returns the commit id, or `None` when `paths` have no changes.
+    pub(crate) fn commit(
+        &self,
+        message: &str,
+        paths: &[PathBuf],
+    ) -> Result<Option<String>, String> {
+        let path_args = || paths.iter().map(|path| path.as_os_str());
+        let unchanged = self
+            .run(
+                ["diff", "--cached", "--quiet", "--"]
+                    .iter()
+                    .map(OsStr::new)
+                    .chain(path_args()),
+            )
+            .is_ok();
+        if paths.is_empty() || unchanged {
+            return Ok(None);
+        }
+        self.run(
+            ["commit", "--quiet", "--only", "--message", message, "--"]
+                .iter()
+                .map(OsStr::new)
+                .chain(path_args()),
+        )
+        .map_err(|err| format!("failed to commit the changed files: {err}"))?;
+        self.run(["rev-parse", "HEAD"]).map(Some)
+    }
+
+    fn run<I, S>(&self, args: I) -> Result<String, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        git(&self.top_level, args)
+    }
+}
+
+/// Runs git in `dir` and returns its trimmed output, or its error message.
+fn git<I, S>(dir: &Path, args: I) -> Result<String, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    output(dir, args).map(|output| output.trim().to_string())
+}
+
+fn output<I, S>(dir: &Path, args: I) -> Result<String, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|err| format!("failed to run git: {err}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// A fresh repository on `main` with one commit of `a.txt`.
+    fn temp_repo(name: &str) -> (PathBuf, Worktree) {
+        let suffix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system clock should be after unix epoch")
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("genify-git-{name}-{suffix}"));
+        fs::create_dir_all(&root).expect("temp repo should be created");
+        for args in [
+            &["init", "--quiet", "--initial-branch", "main"][..],
+            &["config", "user.email", "test@example.com"],
+            &["config", "user.name", "test"],
+        ] {
+            git(&root, args).expect("git should set up the repo");
+        }
+        fs::write(root.join("a.txt"), "a\n").expect("test file should be written");
+        git(&root, ["add", "."]).expect("git add should succeed");
+        git(&root, ["commit", "--quiet", "-m", "init"]).expect("git commit should succeed");
+        let worktree = Worktree::open(&root).expect("worktree should open");
+        (root, worktree)
+    }
+
+    #[test]
+    fn lists_renames_with_their_source_and_untracked_files() {
+        let (root, worktree) = temp_repo("uncommitted");
+        let top = worktree.top_level.clone();
+        fs::write(root.join("a b.txt"), "new\n").expect("test file should be written");
+        worktree
+            .run(["mv", "a.txt", "renamed.txt"])
+            .expect("git mv should succeed");
+
+        let uncommitted = worktree
+            .uncommitted(&[
+                top.join("a.txt"),
+                top.join("renamed.txt"),
+                top.join("a b.txt"),
+                top.join("clean.txt"),
+            ])
+            .expect("status should be read");
+
+        assert_eq!(
+            uncommitted,
+            vec![
+                top.join("renamed.txt"),
+                top.join("a.txt"),
+                top.join("a b.txt")
+            ]
+        );
+        assert!(
+            worktree
+                .uncommitted(&[])
+                .expect("status should be read")
+                .is_empty()
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn switches_back_and_deletes_a_created_branch() {
+        let (root, worktree) = temp_repo("switch-back");
+
+        assert_eq!(worktree.head().expect("head should be read"), "main");
+        assert!(
+            worktree
+                .switch("genify/new")
+                .expect("switch should succeed")
+        );
+        assert_eq!(worktree.head().expect("head should be read"), "genify/new");
+        worktree
+            .switch_back("main", "genify/new", true)
+            .expect("switching back should succeed");
+
+        assert_eq!(worktree.head().expect("head should be read"), "main");
+        assert_eq!(
+            worktree
+                .run(["branch", "--list", "genify/new"])
+                .expect("branches should be listed"),
+            ""
+        );
+
+        let commit = worktree
+            .run(["rev-parse", "HEAD"])
+            .expect("commit should be read");
+        worktree
+            .run(["checkout", "--quiet", "--detach"])
+            .expect("detaching should succeed");
+        assert_eq!(worktree.head().expect("head should be read"), commit);
+        assert!(!worktree.switch("main").expect("switch should succeed"));
+        worktree
+            .switch_back(&commit, "main", false)
+            .expect("switching back should succeed");
+        assert_eq!(worktree.head().expect("head should be read"), commit);
+        assert!(worktree.switch("bad..name").is_err());
+        let _ = fs::remove_dir_all(root);
+    }
+}
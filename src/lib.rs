@@ -1,10 +1,20 @@
+pub mod capture;
+pub mod catalog;
 mod error;
+mod format;
 pub mod generation;
+mod git;
+mod lint;
+pub mod location;
+pub mod manifest;
 #[cfg(feature = "mcp")]
 pub mod mcp;
+mod merge;
+mod props;
 mod schema;
 mod tera_filters;
 mod toml;
+mod walk;
 
 use regex::Regex;
 use std::{
@@ -15,6 +25,8 @@ use std::{
 };
 use tera::{Context, Tera};
 
+use crate::location::ConfigLocation;
+
 pub use crate::error::*;
 pub use crate::schema::*;
 pub use crate::toml::parse_toml;
@@ -49,17 +61,46 @@ pub fn render_config_props_with_func(
 
     let mut context = Context::new();
 
-    for (key, val) in config.props.iter_mut() {
-        if let Value::String(s) = val {
-            *s = tera.render_str(s, &context).map_err(Error::Tera)?;
-        }
+    for position in props::render_order(&config.props)? {
+        let (key, val) = &mut config.props[position];
+        render_value(&mut tera, &context, val)
+            .map_err(|err| err.at(ConfigLocation::prop(&*key)))?;
         func(key, val);
-        context.insert(key.as_str(), val);
+        insert_prop(&mut context, key, val);
     }
 
     Ok(config)
 }
 
+fn render_value(tera: &mut Tera, context: &Context, value: &mut Value) -> Result<(), Error> {
+    match value {
+        Value::String(s) => *s = tera.render_str(s, context).map_err(Error::Tera)?,
+        Value::Array(values) => {
+            for value in values {
+                render_value(tera, context, value)?;
+            }
+        }
+        Value::Map(map) => {
+            for (_, value) in map {
+                render_value(tera, context, value)?;
+            }
+        }
+        Value::Null
+        | Value::Integer(_)
+        | Value::Float(_)
+        | Value::Boolean(_)
+        | Value::Datetime(_) => {}
+    }
+    Ok(())
+}
+
+/// Null props stay out of the context so templates treat them as undefined.
+fn insert_prop(context: &mut Context, key: &str, value: &Value) {
+    if *value != Value::Null {
+        context.insert(key, value);
+    }
+}
+
 pub fn render_config_rules(mut config: Config) -> Result<Config, Error> {
     let mut tera = Tera::default();
     tera_filters::register_all(&mut tera);
@@ -67,80 +108,125 @@ pub fn render_config_rules(mut config: Config) -> Result<Config, Error> {
     let mut context = Context::new();
 
     for (key, val) in config.props.iter_mut() {
-        context.insert(key.as_str(), val);
+        insert_prop(&mut context, key, val);
     }
 
-    for rule in config.rules.iter_mut() {
-        match rule {
-            Rule::Write {
-                path,
-                content,
-                if_exists: _,
-            } => {
-                render_string(&mut tera, &context, path)?;
-                render_string(&mut tera, &context, content)?;
-            }
-            Rule::Delete { path } | Rule::Mkdir { path } | Rule::Chmod { path, mode: _ } => {
-                render_string(&mut tera, &context, path)?;
-            }
-            Rule::Rename { from, to } | Rule::Move { from, to } | Rule::Copy { from, to } => {
-                render_string(&mut tera, &context, from)?;
-                render_string(&mut tera, &context, to)?;
-            }
-            Rule::Append { path, content }
-            | Rule::AppendOnce { path, content }
-            | Rule::Prepend { path, content } => {
-                render_string(&mut tera, &context, path)?;
-                render_string(&mut tera, &context, content)?;
-            }
-            Rule::InsertBefore {
-                path,
-                marker,
-                content,
-            }
-            | Rule::InsertAfter {
-                path,
-                marker,
-                content,
-            } => {
-                render_string(&mut tera, &context, path)?;
-                render_string(&mut tera, &context, marker)?;
-                render_string(&mut tera, &context, content)?;
-            }
-            Rule::Replace {
-                path,
-                replace: _,
-                content,
-                replace_all: _,
-                expected_matches: _,
-            }
-            | Rule::ReplaceOrAppend {
-                path,
-                replace: _,
-                content,
-                replace_all: _,
-                expected_matches: _,
-            } => {
-                render_string(&mut tera, &context, path)?;
-                render_string(&mut tera, &context, content)?;
-            }
-            Rule::ManagedBlock {
-                path,
-                start_marker,
-                end_marker,
-                content,
-            } => {
-                render_string(&mut tera, &context, path)?;
-                render_string(&mut tera, &context, start_marker)?;
-                render_string(&mut tera, &context, end_marker)?;
-                render_string(&mut tera, &context, content)?;
-            }
-        }
+    for (index, rule) in config.rules.iter_mut().enumerate() {
+        let location = ConfigLocation::rule(index, Some(rule.type_name()));
+        render_rule(&mut tera, &context, rule).map_err(|err| err.at(location))?;
+    }
+
+    for (index, hook) in config.hooks.iter_mut().enumerate() {
+        render_hook(&mut tera, &context, hook)
+            .map_err(|err| err.at(ConfigLocation::hook(index)))?;
+    }
+
+    for (index, formatter) in config.formatters.iter_mut().enumerate() {
+        render_formatter(&mut tera, &context, formatter)
+            .map_err(|err| err.at(ConfigLocation::formatter(index)))?;
     }
 
     Ok(config)
 }
 
+fn render_formatter(
+    tera: &mut Tera,
+    context: &Context,
+    formatter: &mut Formatter,
+) -> Result<(), Error> {
+    render_string(tera, context, &mut formatter.glob)?;
+    if let FormatWith::Command(command) = &mut formatter.format_with {
+        for arg in command {
+            render_string(tera, context, arg)?;
+        }
+    }
+    Ok(())
+}
+
+fn render_hook(tera: &mut Tera, context: &Context, hook: &mut Hook) -> Result<(), Error> {
+    for arg in &mut hook.command {
+        render_string(tera, context, arg)?;
+    }
+    if let Some(cwd) = &mut hook.cwd {
+        render_string(tera, context, cwd)?;
+    }
+    for value in hook.env.values_mut() {
+        render_string(tera, context, value)?;
+    }
+    Ok(())
+}
+
+fn render_rule(tera: &mut Tera, context: &Context, rule: &mut Rule) -> Result<(), Error> {
+    match rule {
+        Rule::Write {
+            path,
+            content,
+            if_exists: _,
+            exact: _,
+        } => {
+            render_string(tera, context, path)?;
+            render_string(tera, context, content)?;
+        }
+        Rule::Delete { path } | Rule::Mkdir { path } | Rule::Chmod { path, mode: _ } => {
+            render_string(tera, context, path)?;
+        }
+        Rule::Rename { from, to } | Rule::Move { from, to } | Rule::Copy { from, to } => {
+            render_string(tera, context, from)?;
+            render_string(tera, context, to)?;
+        }
+        Rule::Append { path, content }
+        | Rule::AppendOnce { path, content }
+        | Rule::Prepend { path, content } => {
+            render_string(tera, context, path)?;
+            render_string(tera, context, content)?;
+        }
+        Rule::InsertBefore {
+            path,
+            marker,
+            content,
+        }
+        | Rule::InsertAfter {
+            path,
+            marker,
+            content,
+        } => {
+            render_string(tera, context, path)?;
+            render_string(tera, context, marker)?;
+            render_string(tera, context, content)?;
+        }
+        Rule::Replace {
+            path,
+            replace: _,
+            content,
+            replace_all: _,
+            expected_matches: _,
+        }
+        | Rule::ReplaceOrAppend {
+            path,
+            replace: _,
+            content,
+            replace_all: _,
+            expected_matches: _,
+        } => {
+            render_string(tera, context, path)?;
+            render_string(tera, context, content)?;
+        }
+        Rule::ManagedBlock {
+            path,
+            start_marker,
+            end_marker,
+            content,
+        } => {
+            render_string(tera, context, path)?;
+            render_string(tera, context, start_marker)?;
+            render_string(tera, context, end_marker)?;
+            render_string(tera, context, content)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn render_string(tera: &mut Tera, context: &Context, value: &mut String) -> Result<(), Error> {
     *value = tera.render_str(value, context).map_err(Error::Tera)?;
     Ok(())
@@ -153,6 +239,7 @@ pub fn extend_paths(mut config: Config, root: &Path) -> Result<Config, Error> {
                 path,
                 content: _,
                 if_exists: _,
+                exact: _,
             }
             | Rule::Delete { path }
             | Rule::Mkdir { path }
@@ -211,9 +298,11 @@ pub fn generate_files(config: Config) -> Result<(), Error> {
                 path,
                 content,
                 if_exists,
+                exact,
             } => {
                 let path = Path::new(path);
                 create_dir_all(path)?;
+                let generated = write_rule_content(content, *exact);
                 match if_exists {
                     IfExists::Error => {
                         let mut file = fs::File::options()
@@ -221,11 +310,11 @@ pub fn generate_files(config: Config) -> Result<(), Error> {
                             .write(true)
                             .open(path)
                             .map_err(Error::IOError)?;
-                        writeln!(&mut file, "{}", content.trim_end()).map_err(Error::IOError)?;
+                        file.write_all(generated.as_bytes())
+                            .map_err(Error::IOError)?;
                     }
                     IfExists::Overwrite => {
-                        fs::write(path, format!("{}\n", content.trim_end()))
-                            .map_err(Error::IOError)?;
+                        fs::write(path, generated).map_err(Error::IOError)?;
                     }
                     IfExists::Skip => {
                         if !path.exists() {
@@ -234,10 +323,23 @@ pub fn generate_files(config: Config) -> Result<(), Error> {
                                 .write(true)
                                 .open(path)
                                 .map_err(Error::IOError)?;
-                            writeln!(&mut file, "{}", content.trim_end())
+                            file.write_all(generated.as_bytes())
                                 .map_err(Error::IOError)?;
                         }
                     }
+                    IfExists::Merge => {
+                        // Without a recorded baseline there is nothing to tell
+                        // local edits from generated lines, so only a missing or
+                        // identical file is safe.
+                        if !path.exists() {
+                            fs::write(path, generated).map_err(Error::IOError)?;
+                        } else if read_file_or_empty(path)? != generated {
+                            return Err(Error::Operation(format!(
+                                "cannot merge into existing file {} without a recorded baseline",
+                                path.display()
+                            )));
+                        }
+                    }
                 }
             }
             Rule::Delete { path } => {
@@ -396,6 +498,16 @@ fn create_dir_all(path: &Path) -> Result<(), Error> {
     fs::create_dir_all(parent).map_err(Error::IOError)
 }
 
+/// The content a write rule gives its file: the rule's content ending in a
+/// single newline, or exactly as given for `exact` rules.
+pub(crate) fn write_rule_content(content: &str, exact: bool) -> String {
+    if exact {
+        content.to_string()
+    } else {
+        format!("{}\n", content.trim_end())
+    }
+}
+
 fn read_file_or_empty(path: &Path) -> Result<String, Error> {
     match fs::read_to_string(path) {
         Ok(content) => Ok(content),
@@ -520,6 +632,114 @@ mod tests {
         assert!(config.is_ok());
     }
 
+    #[test]
+    fn test_render_props_in_dependency_order() {
+        let config = parse_toml(
+            r#"
+                [props]
+                image = "{{ registry.host }}/{{ name }}"
+                tags = ["{{ name }}:latest", "{{ version }}"]
+                name = "demo"
+                version = "1.0"
+
+                [props.registry]
+                host = "{{ name }}.io"
+            "#,
+        )
+        .expect("Config should be parsed");
+
+        let config = render_config_props(config).expect("Props should be rendered");
+
+        assert_eq!(
+            config.props,
+            vec![
+                (
+                    "image".to_string(),
+                    Value::String("demo.io/demo".to_string())
+                ),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![
+                        Value::String("demo:latest".to_string()),
+                        Value::String("1.0".to_string()),
+                    ])
+                ),
+                ("name".to_string(), Value::String("demo".to_string())),
+                ("version".to_string(), Value::String("1.0".to_string())),
+                (
+                    "registry".to_string(),
+                    Value::Map(vec![(
+                        "host".to_string(),
+                        Value::String("demo.io".to_string())
+                    )])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_null_and_datetime_props() {
+        let mut config = parse_toml(
+            r#"
+                [props]
+                generated_on = 2024-05-01T10:30:00Z
+
+                [[rules]]
+                type = "write"
+                path = "out.txt"
+                content = "{{ generated_on }} {% if owner is defined %}owned{% else %}unowned{% endif %} {{ owner | default(value='none') }}"
+                if_exists = "error"
+            "#,
+        )
+        .expect("Config should be parsed");
+        config.props.push(("owner".to_string(), Value::Null));
+
+        let generated_on = config.props[0].1.clone();
+        assert!(matches!(generated_on, Value::Datetime(_)));
+        assert_eq!(
+            Value::from(crate::toml::to_toml_value(&generated_on).expect("datetime is TOML")),
+            generated_on
+        );
+        assert_eq!(crate::toml::to_toml_value(&Value::Null), None);
+
+        let config = render_config_props(config)
+            .and_then(render_config_rules)
+            .expect("Config should be rendered");
+        let Rule::Write { content, .. } = &config.rules[0] else {
+            panic!("expected a write rule");
+        };
+        assert_eq!(content, "2024-05-01T10:30:00Z unowned none");
+    }
+
+    #[test]
+    fn test_merge_without_baseline_refuses_changed_files() {
+        let dir = std::env::temp_dir().join(format!("genify-merge-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("test dir should be created");
+        let config: Config = parse_toml(
+            r#"
+                [[rules]]
+                type = "write"
+                path = "a.txt"
+                content = "generated"
+                if_exists = "merge"
+            "#,
+        )
+        .expect("Config should be parsed");
+
+        generate(&dir, &config, None).expect("missing file should be written");
+        generate(&dir, &config, None).expect("identical file should be kept");
+        fs::write(dir.join("a.txt"), "edited\n").expect("test file should be written");
+        let err = generate(&dir, &config, None).expect_err("edited file should be refused");
+
+        assert!(err.to_string().contains("without a recorded baseline"));
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).expect("File should be read"),
+            "edited\n"
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
     #[test]
     fn test_generate() {
         if let Err(err) = fs::remove_dir_all("tmp") {
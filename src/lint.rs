@@ -0,0 +1,417 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path},
+};
+
+use crate::{
+    Config, FormatWith, Formatter, Hook, Rule, Value,
+    generation::Diagnostic,
+    location::ConfigLocation,
+    props::{collect_references, references},
+};
+
+/// Statically checks a config for rules that conflict with each other or can
+/// never do what they say, and for props that are unused or undefined. Rule
+/// paths are compared as rendered when `rendered` is given.
+pub(crate) fn lint(config: &Config, rendered: Option<&Config>) -> Vec<Diagnostic> {
+    let mut diagnostics = lint_props(config);
+    diagnostics.extend(lint_rules(rendered.unwrap_or(config)));
+    diagnostics
+}
+
+/// Props a config has to be given: the ones it declares without a value,
+/// then the ones its props, rules, hooks or formatters use without declaring
+/// them.
+pub(crate) fn required_props(config: &Config) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    for (_, value) in &config.props {
+        collect_references(value, &mut used);
+    }
+    for (_, templates) in templated(config) {
+        for template in templates {
+            references(template, &mut used);
+        }
+    }
+    let mut required: Vec<String> = config
+        .props
+        .iter()
+        .filter(|(_, value)| *value == Value::Null)
+        .map(|(key, _)| key.clone())
+        .collect();
+    required.extend(
+        used.into_iter()
+            .filter(|name| !config.props.iter().any(|(key, _)| key == name)),
+    );
+    required
+}
+
+fn lint_props(config: &Config) -> Vec<Diagnostic> {
+    let defined: BTreeSet<&str> = config.props.iter().map(|(key, _)| key.as_str()).collect();
+    let mut used = BTreeSet::new();
+    let mut diagnostics = Vec::new();
+    let mut undefined = |names: BTreeSet<String>, location: ConfigLocation| {
+        for name in names {
+            if !defined.contains(name.as_str()) {
+                diagnostics.push(
+                    Diagnostic::warning(
+                        "undefined_prop",
+                        format!(
+                            "`{name}` is not defined in props and must be passed when generating"
+                        ),
+                        None::<String>,
+                    )
+                    .at(Some(location.clone())),
+                );
+            }
+        }
+    };
+
+    for (key, value) in &config.props {
+        let mut names = BTreeSet::new();
+        collect_references(value, &mut names);
+        names.remove(key);
+        used.extend(names.iter().cloned());
+        undefined(names, ConfigLocation::prop(key));
+    }
+    for (location, templates) in templated(config) {
+        let mut names = BTreeSet::new();
+        for template in templates {
+            references(template, &mut names);
+        }
+        used.extend(names.iter().cloned());
+        undefined(names, location);
+    }
+
+    for (key, _) in &config.props {
+        if !used.contains(key) {
+            diagnostics.push(
+                Diagnostic::warning(
+                    "unused_prop",
+                    format!("`{key}` is not used by any rule, hook, formatter or prop"),
+                    None::<String>,
+                )
+                .at(Some(ConfigLocation::prop(key))),
+            );
+        }
+    }
+    diagnostics
+}
+
+fn lint_rules(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut warn = |index: usize, rule: &Rule, code: &str, message: String, path: &str| {
+        diagnostics.push(
+            Diagnostic::warning(code, message, Some(path)).at(Some(rule_location(index, rule))),
+        );
+    };
+
+    let mut writes: BTreeMap<String, usize> = BTreeMap::new();
+    for (index, rule) in config.rules.iter().enumerate() {
+        if let Rule::Write { path, .. } = rule {
+            writes.entry(normalize(path)).or_insert(index);
+        }
+    }
+
+    let mut written = BTreeSet::new();
+    let mut deleted: BTreeMap<String, usize> = BTreeMap::new();
+    for (index, rule) in config.rules.iter().enumerate() {
+        match rule {
+            Rule::Write { path, .. } => {
+                let target = normalize(path);
+                if !written.insert(target.clone()) {
+                    warn(
+                        index,
+                        rule,
+                        "duplicate_write",
+                        format!("path is already written by rules[{}]", writes[&target]),
+                        path,
+                    );
+                }
+                deleted.remove(&target);
+            }
+            Rule::Delete { path } => {
+                deleted.insert(normalize(path), index);
+            }
+            Rule::Mkdir { path } => {
+                deleted.remove(&normalize(path));
+            }
+            Rule::Rename { from, to } | Rule::Move { from, to } | Rule::Copy { from, to } => {
+                let source = normalize(from);
+                if let Some(delete) = deleted.get(&source) {
+                    warn(
+                        index,
+                        rule,
+                        "target_deleted",
+                        format!("source was deleted by rules[{delete}]"),
+                        from,
+                    );
+                }
+                if !matches!(rule, Rule::Copy { .. })
+                    && let Some(write) = writes.get(&source)
+                {
+                    warn(
+                        index,
+                        rule,
+                        "move_source_written",
+                        format!("source is also written by rules[{write}]"),
+                        from,
+                    );
+                }
+                deleted.remove(&normalize(to));
+            }
+            Rule::Chmod { path, .. }
+            | Rule::Append { path, .. }
+            | Rule::AppendOnce { path, .. }
+            | Rule::Prepend { path, .. }
+            | Rule::InsertBefore { path, .. }
+            | Rule::InsertAfter { path, .. }
+            | Rule::Replace { path, .. }
+            | Rule::ReplaceOrAppend { path, .. }
+            | Rule::ManagedBlock { path, .. } => {
+                if let Some(delete) = deleted.get(&normalize(path)) {
+                    warn(
+                        index,
+                        rule,
+                        "target_deleted",
+                        format!("path was deleted by rules[{delete}]"),
+                        path,
+                    );
+                }
+            }
+        }
+
+        match rule {
+            Rule::Replace { path, replace, .. } | Rule::ReplaceOrAppend { path, replace, .. }
+                if replace.is_match("") =>
+            {
+                warn(
+                    index,
+                    rule,
+                    "empty_match_regex",
+                    format!("regex `{replace}` can match the empty string"),
+                    path,
+                );
+            }
+            Rule::ManagedBlock {
+                path,
+                start_marker,
+                end_marker,
+                content,
+            } => {
+                for (name, marker) in [("start", start_marker), ("end", end_marker)] {
+                    if !marker.is_empty() && content.contains(marker.as_str()) {
+                        warn(
+                            index,
+                            rule,
+                            "marker_in_content",
+                            format!(
+                                "content contains the {name} marker, so the block cannot be found again"
+                            ),
+                            path,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    diagnostics
+}
+
+fn rule_location(index: usize, rule: &Rule) -> ConfigLocation {
+    ConfigLocation::rule(index, Some(rule.type_name()))
+}
+
+/// The rules, hooks and formatters of a config, each with its fields that
+/// are rendered as templates.
+fn templated(config: &Config) -> Vec<(ConfigLocation, Vec<&str>)> {
+    let rules = config
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(index, rule)| (rule_location(index, rule), templates(rule)));
+    let hooks = config
+        .hooks
+        .iter()
+        .enumerate()
+        .map(|(index, hook)| (ConfigLocation::hook(index), hook_templates(hook)));
+    let formatters = config
+        .formatters
+        .iter()
+        .enumerate()
+        .map(|(index, formatter)| {
+            (
+                ConfigLocation::formatter(index),
+                formatter_templates(formatter),
+            )
+        });
+    rules.chain(hooks).chain(formatters).collect()
+}
+
+/// Every field of a hook that is rendered as a template.
+fn hook_templates(hook: &Hook) -> Vec<&str> {
+    hook.command
+        .iter()
+        .chain(&hook.cwd)
+        .chain(hook.env.values())
+        .map(String::as_str)
+        .collect()
+}
+
+/// Every field of a formatter that is rendered as a template.
+fn formatter_templates(formatter: &Formatter) -> Vec<&str> {
+    let mut templates = vec![formatter.glob.as_str()];
+    if let FormatWith::Command(command) = &formatter.format_with {
+        templates.extend(command.iter().map(String::as_str));
+    }
+    templates
+}
+
+/// Every field of a rule that is rendered as a template.
+fn templates(rule: &Rule) -> Vec<&str> {
+    match rule {
+        Rule::Write { path, content, .. }
+        | Rule::Append { path, content }
+        | Rule::AppendOnce { path, content }
+        | Rule::Prepend { path, content }
+        | Rule::Replace { path, content, .. }
+        | Rule::ReplaceOrAppend { path, content, .. } => vec![path, content],
+        Rule::Delete { path } | Rule::Mkdir { path } | Rule::Chmod { path, .. } => vec![path],
+        Rule::Rename { from, to } | Rule::Move { from, to } | Rule::Copy { from, to } => {
+            vec![from, to]
+        }
+        Rule::InsertBefore {
+            path,
+            marker,
+            content,
+        }
+        | Rule::InsertAfter {
+            path,
+            marker,
+            content,
+        } => vec![path, marker, content],
+        Rule::ManagedBlock {
+            path,
+            start_marker,
+            end_marker,
+            content,
+        } => vec![path, start_marker, end_marker, content],
+    }
+}
+
+/// Compares `./a//b` and `a/b` as the same path.
+fn normalize(path: &str) -> String {
+    Path::new(path)
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect::<std::path::PathBuf>()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_codes(config: serde_json::Value) -> Vec<(String, String)> {
+        let config: Config = serde_json::from_value(config).expect("config should parse");
+        lint(&config, None)
+            .into_iter()
+            .map(|diagnostic| {
+                let location = diagnostic.location.expect("lints point into the config");
+                (diagnostic.code, location.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_conflicting_and_dead_rules() {
+        let codes = lint_codes(serde_json::json!({
+            "rules": [
+                {"type": "write", "path": "a.txt", "content": "a", "if_exists": "skip"},
+                {"type": "write", "path": "./a.txt", "content": "b", "if_exists": "overwrite"},
+                {"type": "delete", "path": "old.txt"},
+                {"type": "append", "path": "old.txt", "content": "x"},
+                {"type": "move", "from": "a.txt", "to": "b.txt"},
+                {"type": "managed_block", "path": "c.txt", "start_marker": "# start", "end_marker": "# end", "content": "# start\nx"},
+                {"type": "replace", "path": "c.txt", "replace": "x*", "content": "y", "replace_all": true}
+            ]
+        }));
+
+        assert_eq!(
+            codes,
+            vec![
+                (
+                    "duplicate_write".to_string(),
+                    "rules[1], type=write".to_string()
+                ),
+                (
+                    "target_deleted".to_string(),
+                    "rules[3], type=append".to_string()
+                ),
+                (
+                    "move_source_written".to_string(),
+                    "rules[4], type=move".to_string()
+                ),
+                (
+                    "marker_in_content".to_string(),
+                    "rules[5], type=managed_block".to_string()
+                ),
+                (
+                    "empty_match_regex".to_string(),
+                    "rules[6], type=replace".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_unused_and_undefined_props() {
+        let codes = lint_codes(serde_json::json!({
+            "props": {"name": "demo", "image": "{{ registry }}/{{ name }}", "unused": 1},
+            "rules": [
+                {"type": "write", "path": "{{ dir }}/Dockerfile", "content": "FROM {{ image }}\n{% for tag in tags %}{{ tag }}{% endfor %}", "if_exists": "skip"}
+            ]
+        }));
+
+        assert_eq!(
+            codes,
+            vec![
+                ("undefined_prop".to_string(), "props.image".to_string()),
+                (
+                    "undefined_prop".to_string(),
+                    "rules[0], type=write".to_string()
+                ),
+                (
+                    "undefined_prop".to_string(),
+                    "rules[0], type=write".to_string()
+                ),
+                ("unused_prop".to_string(), "props.unused".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_props_used_by_hooks_and_formatters() {
+        let config = serde_json::json!({
+            "props": {"pkg": "left-pad", "style": "deno"},
+            "hooks": [
+                {"when": "after", "command": ["npm", "install", "{{ pkg }}", "{{ other }}"], "cwd": "{{ dir }}"}
+            ],
+            "formatters": [
+                {"glob": "**/*.{{ ext }}", "format_with": ["{{ style }}", "fmt", "-"]}
+            ]
+        });
+
+        assert_eq!(
+            lint_codes(config.clone()),
+            vec![
+                ("undefined_prop".to_string(), "hooks[0]".to_string()),
+                ("undefined_prop".to_string(), "hooks[0]".to_string()),
+                ("undefined_prop".to_string(), "formatters[0]".to_string()),
+            ]
+        );
+        let config: Config = serde_json::from_value(config).expect("config should parse");
+        assert_eq!(required_props(&config), vec!["dir", "ext", "other"]);
+    }
+}
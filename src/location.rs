@@ -0,0 +1,486 @@
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+/// Where a diagnostic or error points in a config: the rule or prop it comes
+/// from and, once resolved against the config text with a [`SourceMap`], the
+/// file, line and column.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// 1-based line in `file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// 1-based column in `file`, counted in characters.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// Index into `rules`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prop: Option<String>,
+    /// Index into `hooks`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hook: Option<usize>,
+    /// Index into `formatters`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatter: Option<usize>,
+}
+
+impl ConfigLocation {
+    pub fn rule(index: usize, rule_type: Option<&str>) -> Self {
+        Self {
+            rule: Some(index),
+            rule_type: rule_type.map(str::to_string),
+            ..Self::default()
+        }
+    }
+
+    pub fn prop(name: impl Into<String>) -> Self {
+        Self {
+            prop: Some(name.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn hook(index: usize) -> Self {
+        Self {
+            hook: Some(index),
+            ..Self::default()
+        }
+    }
+
+    pub fn formatter(index: usize) -> Self {
+        Self {
+            formatter: Some(index),
+            ..Self::default()
+        }
+    }
+
+    /// `rules[3], type=replace`, `props.name`, `hooks[0]` or `formatters[0]`,
+    /// without the file position.
+    pub fn context(&self) -> Option<String> {
+        if let Some(index) = self.rule {
+            return Some(match &self.rule_type {
+                Some(rule_type) => format!("rules[{index}], type={rule_type}"),
+                None => format!("rules[{index}]"),
+            });
+        }
+        if let Some(index) = self.hook {
+            return Some(format!("hooks[{index}]"));
+        }
+        if let Some(index) = self.formatter {
+            return Some(format!("formatters[{index}]"));
+        }
+        self.prop.as_ref().map(|prop| format!("props.{prop}"))
+    }
+
+    /// `config.toml:42:5`, as far as the file position is known.
+    pub fn position(&self) -> Option<String> {
+        let mut position = self.file.clone()?;
+        if let Some(line) = self.line {
+            position.push_str(&format!(":{line}"));
+            if let Some(column) = self.column {
+                position.push_str(&format!(":{column}"));
+            }
+        }
+        Some(position)
+    }
+}
+
+impl fmt::Display for ConfigLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.position(), self.context()) {
+            (Some(position), Some(context)) => write!(f, "{position} ({context})"),
+            (Some(position), None) => f.write_str(&position),
+            (None, Some(context)) => f.write_str(&context),
+            (None, None) => f.write_str("config"),
+        }
+    }
+}
+
+/// Byte offsets of the rules and props in a config's source text, used to
+/// turn a [`ConfigLocation`] into a line and column.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    file: String,
+    text: String,
+    /// Start of each rule and its `type`, when the format keeps it.
+    rules: Vec<(usize, Option<String>)>,
+    props: BTreeMap<String, usize>,
+    hooks: Vec<usize>,
+    formatters: Vec<usize>,
+}
+
+impl SourceMap {
+    /// Maps a TOML config. Rules point at their `[[rules]]` header (or inline
+    /// table), props at their value. Invalid TOML maps nothing.
+    pub fn toml(file: impl Into<String>, text: impl Into<String>) -> Self {
+        #[derive(Deserialize)]
+        struct Spans {
+            #[serde(default)]
+            props: BTreeMap<String, toml::Spanned<toml::Value>>,
+            #[serde(default)]
+            rules: Vec<toml::Spanned<toml::Value>>,
+            #[serde(default)]
+            hooks: Vec<toml::Spanned<toml::Value>>,
+            #[serde(default)]
+            formatters: Vec<toml::Spanned<toml::Value>>,
+        }
+
+        let text = text.into();
+        let spans = toml::from_str::<Spans>(&text).ok();
+        Self {
+            file: file.into(),
+            rules: spans
+                .as_ref()
+                .map(|spans| {
+                    spans
+                        .rules
+                        .iter()
+                        .map(|rule| {
+                            let rule_type =
+                                rule.get_ref().get("type").and_then(toml::Value::as_str);
+                            (rule.span().start, rule_type.map(str::to_string))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            hooks: spans
+                .as_ref()
+                .map(|spans| spans.hooks.iter().map(|hook| hook.span().start).collect())
+                .unwrap_or_default(),
+            formatters: spans
+                .as_ref()
+                .map(|spans| {
+                    spans
+                        .formatters
+                        .iter()
+                        .map(|formatter| formatter.span().start)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            props: spans
+                .map(|spans| {
+                    spans
+                        .props
+                        .into_iter()
+                        .map(|(key, value)| (key, value.span().start))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            text,
+        }
+    }
+
+    /// Maps a JSON config. Rules point at their opening brace, props at their
+    /// key. Invalid JSON maps nothing.
+    pub fn json(file: impl Into<String>, text: impl Into<String>) -> Self {
+        let text = text.into();
+        let mut map = Self {
+            file: file.into(),
+            text: String::new(),
+            rules: Vec::new(),
+            props: BTreeMap::new(),
+            hooks: Vec::new(),
+            formatters: Vec::new(),
+        };
+        let _ = JsonScanner::new(&text).scan_config(&mut map);
+        map.text = text;
+        map
+    }
+
+    /// Maps only the file name, for formats without span information.
+    pub fn unmapped(file: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            file: file.into(),
+            text: text.into(),
+            rules: Vec::new(),
+            props: BTreeMap::new(),
+            hooks: Vec::new(),
+            formatters: Vec::new(),
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Fills in the file, line and column of `location`'s rule or prop.
+    pub fn resolve(&self, location: &ConfigLocation) -> ConfigLocation {
+        let mut resolved = location.clone();
+        resolved.file.get_or_insert_with(|| self.file.clone());
+        let offset = if let Some(index) = location.rule {
+            self.rules.get(index).map(|(start, _)| *start)
+        } else if let Some(index) = location.hook {
+            self.hooks.get(index).copied()
+        } else if let Some(index) = location.formatter {
+            self.formatters.get(index).copied()
+        } else {
+            location
+                .prop
+                .as_ref()
+                .and_then(|prop| self.props.get(prop).copied())
+        };
+        if let Some(offset) = offset
+            && location.line.is_none()
+        {
+            let (line, column) = self.line_column(offset);
+            resolved.line = Some(line);
+            resolved.column = Some(column);
+        }
+        resolved
+    }
+
+    /// A location at a byte range of the text, e.g. from a parse error. A
+    /// span starting at a rule also names the rule.
+    pub fn locate_span(&self, span: Range<usize>) -> ConfigLocation {
+        let (line, column) = self.line_column(span.start);
+        let rule = self
+            .rules
+            .iter()
+            .position(|(start, _)| *start == span.start);
+        ConfigLocation {
+            file: Some(self.file.clone()),
+            line: Some(line),
+            column: Some(column),
+            rule,
+            rule_type: rule.and_then(|index| self.rules[index].1.clone()),
+            ..ConfigLocation::default()
+        }
+    }
+
+    /// The text of a 1-based line, without its line break.
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        self.text
+            .split('\n')
+            .nth(line.checked_sub(1)?)
+            .map(|text| text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+/// Just enough of a JSON reader to find where the top-level `rules`, `hooks`
+/// and `formatters` entries and `props` keys start.
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn scan_config(&mut self, map: &mut SourceMap) -> Option<()> {
+        self.expect(b'{')?;
+        self.each_entry(|scanner, key, _| {
+            match key.as_str() {
+                "rules" if scanner.peek() == Some(b'[') => {
+                    scanner.each_element(|scanner, start| {
+                        map.rules.push((start, None));
+                        scanner.skip_value()
+                    })?;
+                }
+                "hooks" if scanner.peek() == Some(b'[') => {
+                    scanner.each_element(|scanner, start| {
+                        map.hooks.push(start);
+                        scanner.skip_value()
+                    })?;
+                }
+                "formatters" if scanner.peek() == Some(b'[') => {
+                    scanner.each_element(|scanner, start| {
+                        map.formatters.push(start);
+                        scanner.skip_value()
+                    })?;
+                }
+                "props" if scanner.peek() == Some(b'{') => {
+                    scanner.expect(b'{')?;
+                    scanner.each_entry(|scanner, key, start| {
+                        map.props.insert(key, start);
+                        scanner.skip_value()
+                    })?;
+                }
+                _ => scanner.skip_value()?,
+            }
+            Some(())
+        })
+    }
+
+    /// Calls `visit` with each key and the offset of its opening quote, with
+    /// the scanner at the value. Expects the `{` to be consumed.
+    fn each_entry(
+        &mut self,
+        mut visit: impl FnMut(&mut Self, String, usize) -> Option<()>,
+    ) -> Option<()> {
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Some(());
+        }
+        loop {
+            self.skip_whitespace();
+            let start = self.position;
+            let key = self.string()?;
+            self.expect(b':')?;
+            self.skip_whitespace();
+            visit(self, key, start)?;
+            match self.next_token()? {
+                b',' => continue,
+                b'}' => return Some(()),
+                _ => return None,
+            }
+        }
+    }
+
+    /// Calls `visit` with the offset of each array element, with the scanner
+    /// at the element.
+    fn each_element(
+        &mut self,
+        mut visit: impl FnMut(&mut Self, usize) -> Option<()>,
+    ) -> Option<()> {
+        self.expect(b'[')?;
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Some(());
+        }
+        loop {
+            self.skip_whitespace();
+            visit(self, self.position)?;
+            match self.next_token()? {
+                b',' => continue,
+                b']' => return Some(()),
+                _ => return None,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'{' => {
+                self.expect(b'{')?;
+                self.each_entry(|scanner, _, _| scanner.skip_value())
+            }
+            b'[' => self.each_element(|scanner, _| scanner.skip_value()),
+            b'"' => self.string().map(drop),
+            _ => {
+                while self.bytes.get(self.position).is_some_and(|byte| {
+                    !matches!(byte, b',' | b'}' | b']') && !byte.is_ascii_whitespace()
+                }) {
+                    self.position += 1;
+                }
+                Some(())
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let start = self.position;
+        self.expect(b'"')?;
+        loop {
+            match *self.bytes.get(self.position)? {
+                b'\\' => self.position += 2,
+                b'"' => break,
+                _ => self.position += 1,
+            }
+        }
+        self.position += 1;
+        serde_json::from_slice(&self.bytes[start..self.position]).ok()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.next_token()? == byte).then_some(())
+    }
+
+    fn next_token(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.position)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.position += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_rules_and_props_in_toml() {
+        let map = SourceMap::toml(
+            "config.toml",
+            "[props]\nname = \"demo\"\n\n[[rules]]\ntype = \"write\"\n\n  [[rules]]\n  type = \"replace\"\n",
+        );
+
+        assert_eq!(
+            map.resolve(&ConfigLocation::rule(1, Some("replace")))
+                .to_string(),
+            "config.toml:7:3 (rules[1], type=replace)"
+        );
+        assert_eq!(
+            map.resolve(&ConfigLocation::prop("name")).to_string(),
+            "config.toml:2:8 (props.name)"
+        );
+        assert_eq!(map.line_text(7), Some("  [[rules]]"));
+        assert_eq!(
+            map.resolve(&ConfigLocation::rule(5, None)).to_string(),
+            "config.toml (rules[5])"
+        );
+    }
+
+    #[test]
+    fn resolves_rules_and_props_in_json() {
+        let map = SourceMap::json(
+            "config.json",
+            r#"{
+  "props": {"na\"me": [1, {"x": "}"}], "other": 2},
+  "rules": [
+    {"type": "delete", "path": "a"},
+    {"type": "mkdir", "path": "b"}
+  ]
+}"#,
+        );
+
+        assert_eq!(
+            map.resolve(&ConfigLocation::rule(1, Some("mkdir")))
+                .to_string(),
+            "config.json:5:5 (rules[1], type=mkdir)"
+        );
+        assert_eq!(
+            map.resolve(&ConfigLocation::prop("other")).to_string(),
+            "config.json:2:40 (props.other)"
+        );
+        assert_eq!(map.resolve(&ConfigLocation::prop("na\"me")).line, Some(2));
+    }
+}
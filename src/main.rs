@@ -2,14 +2,37 @@ use std::{
     fs,
     io::{self, Write},
     path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
 };
 
 use clap::{Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
-use genify::generate_files;
-use reqwest::blocking::Client;
+use genify::{
+    generation::{
+        ApplyOutput, ApplyRequest, GenerationCore, GenerationRequest, HookReport, PropSource,
+        RootApplyStatus, TemplateInfo, TemplateKind, ValidateConfigRequest,
+    },
+    location::SourceMap,
+    manifest::{ManifestSource, content_hash},
+};
 use serde_json::Value as JsonValue;
 use url::Url;
 
+use crate::cli::{
+    cache,
+    convert::ConfigFormat,
+    diff_view::{self, ColorChoice, DiffViewOptions},
+    fetch::{self, FetchArgs},
+    fixtures::{self, CaseOutcome},
+    git_source::GitSource,
+    props::{PRECEDENCE, PropOverrides, PropsArgs},
+    report,
+    trust::{SIGNATURE_SUFFIX, Signer, TrustStore},
+    watch::{self, Rendered},
+};
+
+mod cli;
+
 static BIN_NAME: &str = env!("CARGO_PKG_NAME");
 static BIN_DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 
@@ -18,20 +41,87 @@ static BIN_DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
-    /// Path to a config file or http(s) URL.
+    /// Path to a config file, http(s) URL or git+ URL.
     path: Option<ConfigPath>,
+    #[command(flatten)]
+    generate: GenerateArgs,
+    #[command(flatten)]
+    fetch: FetchArgs,
+}
+
+/// How configs are fetched this run, set once from the global flags.
+static FETCH: OnceLock<FetchArgs> = OnceLock::new();
+
+/// How to generate a config into the current directory.
+#[derive(Args)]
+struct GenerateArgs {
     /// Do not ask any interactive question.
     #[arg(short, long)]
     no_interaction: bool,
-    /// Override props using a JSON object (Array/Map supported).
-    #[arg(short = 'p', long = "props-json", value_name = "JSON")]
-    props_json: Option<String>,
+    #[command(flatten)]
+    props: PropsArgs,
+    /// Show the changes as a diff without writing files.
+    #[arg(long)]
+    dry_run: bool,
+    /// Do not record the run in .genify/manifest.toml.
+    #[arg(long)]
+    no_manifest: bool,
+    /// Run the config's hooks and formatters; without this they are skipped with a warning.
+    #[arg(long)]
+    allow_hooks: bool,
+    #[command(flatten)]
+    diff_view: DiffViewArgs,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Start genify as an MCP server over STDIO.
     Mcp(McpArgs),
+    /// List the configs of a catalog.
+    List(ListArgs),
+    /// Generate a config picked by its catalog name into the current directory.
+    New(NewArgs),
+    /// Re-render the config recorded in .genify/manifest.toml, keeping edited files.
+    Update(UpdateArgs),
+    /// Apply a config to one or more project roots without prompting.
+    Apply(ApplyArgs),
+    /// Show the operations a config would perform and where each prop comes from.
+    Plan(PlanArgs),
+    /// Check a config for conflicting rules, dead rules and unused or undefined props.
+    Lint(LintArgs),
+    /// Run a config against fixture cases and compare the result with their expected files.
+    Test(TestArgs),
+    /// Re-render a config whenever it or its props change, showing what changed.
+    Watch(WatchArgs),
+    /// Write a config that recreates an existing project, with the given values as props.
+    Capture(CaptureArgs),
+    /// Convert a config between TOML, JSON and YAML.
+    Convert(ConvertArgs),
+    /// Print the JSON Schema of the config format.
+    Schema(SchemaArgs),
+}
+
+#[derive(Args)]
+struct DiffViewArgs {
+    /// When to color the dry-run diff (auto honors NO_COLOR and TTY detection).
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, value_name = "WHEN")]
+    color: ColorChoice,
+    /// Show the dry-run diff in two columns.
+    #[arg(long)]
+    side_by_side: bool,
+    /// Do not page the dry-run diff.
+    #[arg(long)]
+    no_pager: bool,
+}
+
+impl DiffViewArgs {
+    fn options(&self) -> DiffViewOptions {
+        DiffViewOptions {
+            color: self.color,
+            side_by_side: self.side_by_side,
+            pager: !self.no_pager,
+        }
+    }
 }
 
 #[derive(Args)]
@@ -42,16 +132,181 @@ struct McpArgs {
     /// Disable tools that write to disk.
     #[arg(long)]
     read_only: bool,
+    /// Let genify_apply run the hooks, and every tool the formatters, of the configs it is given.
+    #[arg(long, conflicts_with = "read_only")]
+    allow_hooks: bool,
+    /// Refuse genify_apply writes to paths ignored by .gitignore or .genifyignore.
+    #[arg(long)]
+    protect_ignored: bool,
+    /// Refuse genify_apply writes to paths matching GLOB, relative to the root.
+    #[arg(long, value_name = "GLOB")]
+    protect: Vec<String>,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Catalog directory, with an optional genify-catalog.toml index.
+    #[arg(long, env = "GENIFY_CATALOG", default_value = ".", value_name = "DIR")]
+    catalog: PathBuf,
+}
+
+#[derive(Args)]
+struct NewArgs {
+    /// Catalog name of the config, as shown by `genify list`.
+    name: String,
+    /// Catalog directory, with an optional genify-catalog.toml index.
+    #[arg(long, env = "GENIFY_CATALOG", default_value = ".", value_name = "DIR")]
+    catalog: PathBuf,
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+#[derive(Args)]
+struct UpdateArgs {
+    /// Path to a config file, http(s) URL or git+ URL. Defaults to the source recorded in the manifest.
+    path: Option<ConfigPath>,
+    /// Generation root containing .genify/manifest.toml.
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+    #[command(flatten)]
+    props: PropsArgs,
+    /// Show the changes as a diff without writing files.
+    #[arg(long)]
+    dry_run: bool,
+    /// Run the config's hooks and formatters; without this they are skipped with a warning.
+    #[arg(long)]
+    allow_hooks: bool,
+    #[command(flatten)]
+    diff_view: DiffViewArgs,
+}
+
+#[derive(Args)]
+struct ApplyArgs {
+    /// Path to a config file, http(s) URL or git+ URL.
+    path: ConfigPath,
+    /// Project root to apply the config to (repeatable).
+    #[arg(long = "root", value_name = "DIR")]
+    roots: Vec<PathBuf>,
+    /// Read project roots from a file, one per line; blank lines and # comments are ignored.
+    #[arg(long, value_name = "FILE")]
+    roots_from: Option<PathBuf>,
+    #[command(flatten)]
+    props: PropsArgs,
+    /// Do not record the run in each root's .genify/manifest.toml.
+    #[arg(long)]
+    no_manifest: bool,
+    /// Stop at the first root that fails instead of continuing with the others.
+    #[arg(long)]
+    fail_fast: bool,
+    /// Apply on this branch of each root's git worktree, creating it if needed, and stage the changes.
+    #[arg(long, value_name = "NAME")]
+    git_branch: Option<String>,
+    /// Commit the changed files of each root with MESSAGE, leaving other changes out of the commit.
+    #[arg(long, value_name = "MESSAGE")]
+    git_commit: Option<String>,
+    /// Run the config's hooks and formatters; without this they are skipped with a warning.
+    #[arg(long)]
+    allow_hooks: bool,
+}
+
+#[derive(Args)]
+struct PlanArgs {
+    /// Path to a config file, http(s) URL or git+ URL.
+    path: ConfigPath,
+    /// Generation root.
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+    #[command(flatten)]
+    props: PropsArgs,
+}
+
+#[derive(Args)]
+struct LintArgs {
+    /// Path to a config file, http(s) URL or git+ URL.
+    path: ConfigPath,
+    /// Generation root that rule paths must stay inside.
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+}
+
+#[derive(Args)]
+struct TestArgs {
+    /// Path to a config file, http(s) URL or git+ URL.
+    path: ConfigPath,
+    /// Directory with one subdirectory per case [default: tests/ next to the config].
+    #[arg(long, value_name = "DIR")]
+    fixtures: Option<PathBuf>,
+    /// Overwrite each case's expected/ with the generated files.
+    #[arg(long)]
+    bless: bool,
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    /// Path to the config file.
+    path: ConfigPath,
+    /// Generation root to show the dry-run diff against.
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+    /// Apply into this directory instead, emptying it before every render.
+    #[arg(long, value_name = "DIR", conflicts_with = "root")]
+    scratch: Option<PathBuf>,
+    #[command(flatten)]
+    props: PropsArgs,
+    /// Wait until files have been quiet this long before re-rendering.
+    #[arg(long, value_name = "MS", default_value_t = 200)]
+    debounce: u64,
+    /// When to color the first dry-run diff (auto honors NO_COLOR and TTY detection).
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, value_name = "WHEN")]
+    color: ColorChoice,
+}
+
+#[derive(Args)]
+struct CaptureArgs {
+    /// Project directory to capture.
+    dir: PathBuf,
+    /// Replace VALUE and its case variants with a prop (repeatable), e.g. project_name=acme.
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+    /// Write the config to FILE instead of stdout, in the format its extension names.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// Path to a config file, http(s) URL or git+ URL.
+    path: ConfigPath,
+    /// Format of the input [default: from the extension, else TOML].
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    from: Option<ConfigFormat>,
+    /// Format to write [default: from the --output extension, else TOML].
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    to: Option<ConfigFormat>,
+    /// Write the converted config to FILE instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SchemaArgs {
+    /// Write the schema to FILE instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum ConfigPath {
     File(String),
     Http(Url),
+    Git(GitSource),
 }
 
 impl From<&str> for ConfigPath {
     fn from(value: &str) -> Self {
+        if let Some(source) = GitSource::parse(value) {
+            return Self::Git(source);
+        }
         if let Ok(url) = Url::parse(value)
             && (url.scheme() == "http" || url.scheme() == "https")
         {
@@ -61,24 +316,119 @@ impl From<&str> for ConfigPath {
     }
 }
 
+impl ConfigPath {
+    /// Location recorded in the manifest; files are made absolute so the
+    /// manifest stays valid when genify runs from another directory.
+    fn location(&self) -> String {
+        match self {
+            Self::File(p) => fs::canonicalize(p)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| p.clone()),
+            Self::Http(url) => url.to_string(),
+            Self::Git(source) => source.to_string(),
+        }
+    }
+
+    /// The path or URL as given, used to point diagnostics into the config.
+    fn label(&self) -> String {
+        match self {
+            Self::File(p) => p.clone(),
+            Self::Http(url) => url.to_string(),
+            Self::Git(source) => source.to_string(),
+        }
+    }
+
+    fn format(&self) -> Option<ConfigFormat> {
+        match self {
+            Self::File(p) => ConfigFormat::detect(Path::new(p)),
+            Self::Http(url) => ConfigFormat::detect(Path::new(url.path())),
+            Self::Git(source) => ConfigFormat::detect(Path::new(&source.path)),
+        }
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
     let cmd = Cli::command();
     let Cli {
         command,
         path,
-        no_interaction,
-        props_json,
+        generate,
+        fetch,
     } = cli;
+    FETCH.get_or_init(|| fetch);
 
     if let Some(command) = command {
         match command {
             Commands::Mcp(args) => {
-                if let Err(error) = genify::mcp::serve_stdio(&args.root, args.read_only) {
+                let core =
+                    GenerationCore::with_read_only(&args.root, args.read_only).and_then(|core| {
+                        core.allow_hooks(args.allow_hooks)
+                            .protect_paths(args.protect_ignored, &args.protect)
+                    });
+                if let Err(error) = core
+                    .map_err(Into::into)
+                    .and_then(genify::mcp::serve_core_stdio)
+                {
                     eprintln!("Failed to run MCP server: {error}");
                     std::process::exit(1);
                 }
             }
+            Commands::List(args) => {
+                if let Err(err) = run_list(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::New(args) => {
+                if let Err(err) = run_new(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Update(args) => {
+                if let Err(err) = run_update(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Apply(args) => {
+                if let Err(err) = run_apply(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Plan(args) => {
+                if let Err(err) = run_plan(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Lint(args) => {
+                if let Err(err) = run_lint(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Test(args) => {
+                if let Err(err) = run_test(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Watch(args) => {
+                if let Err(err) = run_watch(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Capture(args) => {
+                if let Err(err) = run_capture(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Convert(args) => {
+                if let Err(err) = run_convert(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
+            Commands::Schema(args) => {
+                if let Err(err) = run_schema(args) {
+                    err.with_cmd(&cmd).exit();
+                }
+            }
         }
         return;
     }
@@ -91,91 +441,832 @@ fn main() {
         .with_cmd(&cmd)
         .exit();
     };
+    if let Err(err) = run_generate(&path, generate) {
+        err.with_cmd(&cmd).exit();
+    }
+}
 
-    let mut config = parse_file(&path).unwrap_or_else(|err| err.with_cmd(&cmd).exit());
+/// Generates the config at `path` into the current directory, asking for
+/// each prop unless `--no-interaction` is given.
+fn run_generate(path: &ConfigPath, args: GenerateArgs) -> Result<(), clap::Error> {
+    let GenerateArgs {
+        no_interaction,
+        props,
+        dry_run,
+        no_manifest,
+        allow_hooks,
+        diff_view,
+    } = args;
+    let (config, source) = load_config(path)?;
+    let core = open_core(Path::new("."))?.allow_hooks(allow_hooks);
 
-    if let Some(raw_props) = &props_json {
-        let overrides = parse_props_json(raw_props).unwrap_or_else(|err| err.with_cmd(&cmd).exit());
-        config.props.extend(overrides);
-    }
+    // Project props go under the CLI overrides so prompts start from the values
+    // the project pinned.
+    let mut prompted = config.clone();
+    let project_props = core
+        .project_props(None)
+        .map_err(|err| report::core_error(err, None))?;
+    merge_props(&mut prompted.props, project_props.unwrap_or_default());
+    let overrides = props.overrides(&prompted.props)?;
+    merge_props(&mut prompted.props, overrides.props.clone());
 
-    if let Err(error) = genify::render_config_props_with_func(config, |k, v| {
+    let mut answers = genify::Map::new();
+    let rendered = genify::render_config_props_with_func(prompted, |k, v| {
         if no_interaction {
             return;
         }
-        let prompt = |default: &str| -> Option<String> {
-            print!("{} ({}): ", k, default);
-            if io::stdout().flush().is_err() {
-                return None;
-            }
-            let mut input = String::new();
-            if io::stdin().read_line(&mut input).is_ok() {
-                let trimmed = input.trim();
-                if !trimmed.is_empty() {
-                    return Some(trimmed.to_string());
-                }
+        let default = v.clone();
+        prompt_prop(k, v);
+        if *v != default {
+            answers.push((k.clone(), v.clone()));
+        }
+    });
+    rendered.map_err(|error| report::render_error(error, &source))?;
+
+    // The core renders the config itself, so it gets the config as written and
+    // only the props the user gave; those also go above the project props file.
+    let mut request_props = overrides.props;
+    merge_props(&mut request_props, answers);
+    let props = (!request_props.is_empty()).then(|| props_to_json(&request_props));
+    let config = config_to_json(&config)?;
+    if dry_run {
+        show_dry_run(
+            &core,
+            GenerationRequest {
+                config: Some(config),
+                props,
+                ..GenerationRequest::default()
+            },
+            diff_view.options(),
+            &source,
+        )
+    } else {
+        let manifest = (!no_manifest).then(|| ManifestSource {
+            location: path.location(),
+            hash: Some(content_hash(source.text().as_bytes())),
+        });
+        apply(
+            &core,
+            ApplyRequest {
+                config: Some(config),
+                props,
+                manifest,
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            },
+            &source,
+        )
+    }
+}
+
+/// Asks for a prop on the terminal, showing its rendered default, and keeps
+/// the default when the answer is empty or does not parse.
+fn prompt_prop(k: &str, v: &mut genify::Value) {
+    let prompt = |default: &str| -> Option<String> {
+        print!("{} ({}): ", k, default);
+        if io::stdout().flush().is_err() {
+            return None;
+        }
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_ok() {
+            let trimmed = input.trim();
+            if !trimmed.is_empty() {
+                return Some(trimmed.to_string());
             }
-            None
-        };
+        }
+        None
+    };
 
-        match v {
-            genify::Value::String(s) => {
-                if let Some(new) = prompt(s) {
-                    *v = genify::Value::String(new);
-                }
+    match v {
+        genify::Value::Null => {
+            if let Some(new) = prompt("") {
+                *v = genify::Value::String(new);
             }
-            genify::Value::Integer(i) => {
-                if let Some(new) = prompt(&i.to_string())
-                    && let Ok(parsed) = new.parse::<i64>()
-                {
-                    *v = genify::Value::Integer(parsed);
-                }
+        }
+        genify::Value::String(s) => {
+            if let Some(new) = prompt(s) {
+                *v = genify::Value::String(new);
             }
-            genify::Value::Float(f) => {
-                if let Some(new) = prompt(&f.to_string())
-                    && let Ok(parsed) = new.parse::<f64>()
-                {
-                    *v = genify::Value::Float(parsed);
-                }
+        }
+        genify::Value::Integer(i) => {
+            if let Some(new) = prompt(&i.to_string())
+                && let Ok(parsed) = new.parse::<i64>()
+            {
+                *v = genify::Value::Integer(parsed);
             }
-            genify::Value::Boolean(b) => {
-                if let Some(new) = prompt(&b.to_string())
-                    && let Ok(parsed) = new.parse::<bool>()
-                {
-                    *v = genify::Value::Boolean(parsed);
+        }
+        genify::Value::Float(f) => {
+            if let Some(new) = prompt(&f.to_string())
+                && let Ok(parsed) = new.parse::<f64>()
+            {
+                *v = genify::Value::Float(parsed);
+            }
+        }
+        genify::Value::Boolean(b) => {
+            if let Some(new) = prompt(&b.to_string())
+                && let Ok(parsed) = new.parse::<bool>()
+            {
+                *v = genify::Value::Boolean(parsed);
+            }
+        }
+        genify::Value::Datetime(d) => {
+            if let Some(new) = prompt(&d.to_string()) {
+                match new.parse::<genify::Datetime>() {
+                    Ok(parsed) => *v = genify::Value::Datetime(parsed),
+                    Err(err) => eprintln!("Failed to parse \"{k}\": {err}"),
                 }
             }
-            genify::Value::Array(_) | genify::Value::Map(_) => {
-                let Some(default) = value_to_json_string(v) else {
-                    return;
-                };
-                if let Some(new) = prompt(&default) {
-                    match parse_json_value(&new) {
-                        Ok(genify::Value::Array(parsed)) => *v = genify::Value::Array(parsed),
-                        Ok(genify::Value::Map(parsed)) => *v = genify::Value::Map(parsed),
-                        Ok(_) => eprintln!(
-                            "Value for \"{k}\" must be a JSON array or object; keeping default."
-                        ),
-                        Err(err) => eprintln!("Failed to parse \"{k}\": {err}"),
-                    }
+        }
+        genify::Value::Array(_) | genify::Value::Map(_) => {
+            let Some(default) = value_to_json_string(v) else {
+                return;
+            };
+            if let Some(new) = prompt(&default) {
+                match parse_json_value(&new) {
+                    Ok(genify::Value::Array(parsed)) => *v = genify::Value::Array(parsed),
+                    Ok(genify::Value::Map(parsed)) => *v = genify::Value::Map(parsed),
+                    Ok(_) => eprintln!(
+                        "Value for \"{k}\" must be a JSON array or object; keeping default."
+                    ),
+                    Err(err) => eprintln!("Failed to parse \"{k}\": {err}"),
                 }
             }
         }
+    }
+}
+
+fn run_list(args: ListArgs) -> Result<(), clap::Error> {
+    let configs = catalog_configs(&open_core(&args.catalog)?)?;
+    if configs.is_empty() {
+        println!("No configs found in {}", args.catalog.display());
+        return Ok(());
+    }
+    let width = configs
+        .iter()
+        .map(|item| item.name.len())
+        .max()
+        .unwrap_or(0);
+    for item in &configs {
+        let mut line = format!("{:<width$}", item.name);
+        if let Some(version) = &item.version {
+            line.push_str(&format!("  {version}"));
+        }
+        if let Some(about) = item.title.as_ref().or(item.description.as_ref()) {
+            line.push_str(&format!("  {about}"));
+        }
+        if !item.tags.is_empty() {
+            line.push_str(&format!("  [{}]", item.tags.join(", ")));
+        }
+        println!("{}", line.trim_end());
+        if !item.required_props.is_empty() {
+            println!(
+                "{:width$}  requires: {}",
+                "",
+                item.required_props.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_new(args: NewArgs) -> Result<(), clap::Error> {
+    let core = open_core(&args.catalog)?;
+    let configs = catalog_configs(&core)?;
+    let Some(item) = configs.iter().find(|item| {
+        item.name == args.name || item.name.strip_suffix(".toml") == Some(args.name.as_str())
+    }) else {
+        let names = configs
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>();
+        return Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            format!(
+                "No config named `{}` in {}; available: {}",
+                args.name,
+                args.catalog.display(),
+                names.join(", ")
+            ),
+        ));
+    };
+    let path = match ConfigPath::from(item.path.as_str()) {
+        ConfigPath::File(path) => {
+            ConfigPath::File(core.root().join(path).to_string_lossy().into_owned())
+        }
+        url => url,
+    };
+    run_generate(&path, args.generate)
+}
+
+/// The configs of the catalog at the core's root, named and described by its
+/// index when it has one.
+fn catalog_configs(core: &GenerationCore) -> Result<Vec<TemplateInfo>, clap::Error> {
+    let templates = core
+        .list_templates()
+        .map_err(|err| report::core_error(err, None))?;
+    for diagnostic in &templates.warnings {
+        eprintln!("{}", report::diagnostic(diagnostic, None));
+    }
+    Ok(templates
+        .items
+        .into_iter()
+        .filter(|item| matches!(item.kind, TemplateKind::Config))
+        .collect())
+}
+
+fn run_update(args: UpdateArgs) -> Result<(), clap::Error> {
+    let core = open_core(&args.root)?.allow_hooks(args.allow_hooks);
+    let manifest = core
+        .manifest(None)
+        .map_err(|err| report::core_error(err, None))?;
+    let path = args
+        .path
+        .unwrap_or_else(|| ConfigPath::from(manifest.source.location.as_str()));
+    let (config, source) = load_config(&path)?;
+    let mut base = config.props.clone();
+    merge_props(&mut base, manifest.props());
+    merge_props(
+        &mut base,
+        core.project_props(None)
+            .map_err(|err| report::core_error(err, None))?
+            .unwrap_or_default(),
+    );
+    let props = overrides_to_json(args.props.overrides(&base)?);
+    let config = config_to_json(&config)?;
+
+    if args.dry_run {
+        return show_dry_run(
+            &core,
+            GenerationRequest {
+                config: Some(config),
+                props,
+                update: true,
+                ..GenerationRequest::default()
+            },
+            args.diff_view.options(),
+            &source,
+        );
+    }
+    apply(
+        &core,
+        ApplyRequest {
+            config: Some(config),
+            props,
+            update: true,
+            manifest: Some(ManifestSource {
+                location: path.location(),
+                hash: Some(content_hash(source.text().as_bytes())),
+            }),
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        },
+        &source,
+    )
+}
+
+fn run_apply(args: ApplyArgs) -> Result<(), clap::Error> {
+    let mut roots = args.roots;
+    if let Some(path) = &args.roots_from {
+        roots.extend(read_roots(path)?);
+    }
+    if roots.is_empty() {
+        roots.push(PathBuf::from("."));
+    }
+
+    let (config, source) = load_config(&args.path)?;
+    let props = overrides_to_json(args.props.overrides(&config.props)?);
+    let config = config_to_json(&config)?;
+    let manifest = (!args.no_manifest).then(|| ManifestSource {
+        location: args.path.location(),
+        hash: Some(content_hash(source.text().as_bytes())),
+    });
+
+    let output = GenerationCore::apply_to_roots(
+        ApplyRequest {
+            config: Some(config),
+            props,
+            manifest,
+            roots: roots
+                .iter()
+                .map(|root| root.to_string_lossy().to_string())
+                .collect(),
+            fail_fast: args.fail_fast,
+            git_branch: args.git_branch,
+            git_commit: args.git_commit,
+            explicit_approval: true,
+            ..ApplyRequest::default()
+        },
+        args.allow_hooks,
+    )
+    .map_err(|err| report::core_error(err, Some(&source)))?;
+
+    for report in &output.roots {
+        let status = match report.status {
+            RootApplyStatus::Changed => "changed",
+            RootApplyStatus::Unchanged => "unchanged",
+            RootApplyStatus::Failed => "failed",
+            RootApplyStatus::Skipped => "skipped",
+        };
+        println!("{}: {status} - {}", report.root, report.summary);
+        for file in &report.changed_files {
+            println!("  {file}");
+        }
+        if let Some(git) = &report.git {
+            if let Some(branch) = &git.branch {
+                let created = if git.created_branch { " (created)" } else { "" };
+                println!("  on branch {branch}{created}");
+            }
+            if let Some(commit) = &git.commit {
+                println!("  committed {}", &commit[..commit.len().min(12)]);
+            } else if !git.staged.is_empty() {
+                println!("  staged {} file(s)", git.staged.len());
+            }
+        }
+        print_hooks(&report.hooks, "  ");
+        for diagnostic in report.warnings.iter().chain(&report.errors) {
+            let diagnostic = report::diagnostic(diagnostic, Some(&source));
+            eprintln!("  {}", diagnostic.replace('\n', "\n  "));
+        }
+    }
+    println!("{}", output.summary);
+
+    if output.has_failures() {
+        return Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            "Some roots failed to apply",
+        ));
+    }
+    Ok(())
+}
+
+fn run_plan(args: PlanArgs) -> Result<(), clap::Error> {
+    let core = open_core(&args.root)?;
+    let (config, source, signer) = load_signed_config(&args.path)?;
+    let mut base = config.props.clone();
+    merge_props(
+        &mut base,
+        core.project_props(None)
+            .map_err(|err| report::core_error(err, None))?
+            .unwrap_or_default(),
+    );
+    let overrides = args.props.overrides(&base)?;
+    let sources = overrides.sources.clone();
+    let plan = core
+        .plan(GenerationRequest {
+            config: Some(config_to_json(&config)?),
+            props: overrides_to_json(overrides),
+            ..GenerationRequest::default()
+        })
+        .map_err(|err| report::core_error(err, Some(&source)))?;
+
+    println!("Signed by: {signer}");
+    println!();
+    println!("Operations:");
+    for operation in &plan.operations {
+        let kind = serde_json::to_value(operation.operation).map_err(serialization_error)?;
+        let effect = if operation.will_delete {
+            "delete"
+        } else if operation.will_create {
+            "create"
+        } else if operation.will_modify {
+            "modify"
+        } else {
+            "keep"
+        };
+        let path = match (&operation.source_path, &operation.target_path) {
+            (Some(source), Some(target)) => format!("{source} -> {target}"),
+            _ => operation.path.clone(),
+        };
+        println!(
+            "  {:<18} {path} ({effect})",
+            kind.as_str().unwrap_or_default()
+        );
+    }
+    if !plan.hooks.is_empty() {
+        println!();
+        println!("Hooks (run only with --allow-hooks):");
+        for hook in &plan.hooks {
+            let env = hook
+                .env
+                .iter()
+                .map(|(name, value)| format!("{name}={value} "))
+                .collect::<String>();
+            println!(
+                "  {:<18} {env}{} (in {})",
+                hook.when.name(),
+                hook.command.join(" "),
+                hook.cwd
+            );
+        }
+    }
+    println!();
+    println!("Props (later layers win: {}):", PRECEDENCE.join(" < "));
+    for prop in &plan.props {
+        let source = match prop.source {
+            PropSource::Config => "config",
+            PropSource::Manifest => "manifest",
+            PropSource::Project => "project",
+            PropSource::Request => sources
+                .get(&prop.name)
+                .map_or("request", |layer| layer.label()),
+        };
+        let value = serde_json::to_string(&prop.value).map_err(serialization_error)?;
+        println!("  {} = {value} [{source}]", prop.name);
+    }
+
+    for diagnostic in plan.warnings.iter().chain(&plan.errors) {
+        eprintln!("{}", report::diagnostic(diagnostic, Some(&source)));
+    }
+    if !plan.errors.is_empty() {
+        return Err(clap::Error::raw(ErrorKind::InvalidValue, "Plan has errors"));
+    }
+    Ok(())
+}
+
+fn run_lint(args: LintArgs) -> Result<(), clap::Error> {
+    let core = open_core(&args.root)?;
+    let (config, source) = load_config(&args.path)?;
+    let output = core
+        .validate_config(ValidateConfigRequest {
+            config: Some(config_to_json(&config)?),
+        })
+        .map_err(|err| report::core_error(err, Some(&source)))?;
+
+    for diagnostic in &output.diagnostics {
+        eprintln!("{}", report::diagnostic(diagnostic, Some(&source)));
+    }
+    match output.diagnostics.len() {
+        0 => {
+            println!("{}: no problems found", source.file());
+            Ok(())
+        }
+        count => Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            format!("{}: {count} problem(s) found", source.file()),
+        )),
+    }
+}
+
+fn run_test(args: TestArgs) -> Result<(), clap::Error> {
+    let (config, source) = load_config(&args.path)?;
+    let config = config_to_json(&config)?;
+    let dir = args.fixtures.unwrap_or_else(|| match &args.path {
+        ConfigPath::File(p) => Path::new(p).with_file_name("tests"),
+        ConfigPath::Http(_) => PathBuf::from("tests"),
+        ConfigPath::Git(source) => git_checkout(source)
+            .and_then(|checkout| source.file(&checkout).map_err(git_error))
+            .map(|file| file.with_file_name("tests"))
+            .unwrap_or_else(|_| PathBuf::from("tests")),
+    });
+    let cases = fixtures::discover(&dir).map_err(|err| {
+        clap::Error::raw(
+            ErrorKind::ValueValidation,
+            format!("Failed to read fixtures in {}: {err}", dir.display()),
+        )
+    })?;
+
+    println!("running {} case(s) from {}", cases.len(), dir.display());
+    let mut failures = Vec::new();
+    for case in &cases {
+        let outcome = case
+            .run(&config, args.bless)
+            .map_err(|err| clap::Error::raw(ErrorKind::Io, format!("{}: {err}", case.name)))?;
+        let status = match &outcome {
+            CaseOutcome::Passed => "ok",
+            CaseOutcome::Blessed => "blessed",
+            CaseOutcome::Mismatched(_) | CaseOutcome::Failed(_) => "FAILED",
+        };
+        println!("case {} ... {status}", case.name);
+        match outcome {
+            CaseOutcome::Mismatched(mismatches) => failures.push((case, mismatches)),
+            CaseOutcome::Failed(errors) => failures.push((
+                case,
+                errors
+                    .iter()
+                    .map(|error| (String::new(), report::diagnostic(error, Some(&source))))
+                    .collect(),
+            )),
+            CaseOutcome::Passed | CaseOutcome::Blessed => {}
+        }
+    }
+
+    for (case, mismatches) in &failures {
+        eprintln!("\n---- {} ----", case.name);
+        for (path, explanation) in mismatches {
+            let explanation = explanation.trim_end();
+            if path.is_empty() {
+                eprintln!("{explanation}");
+            } else if explanation.contains('\n') {
+                eprintln!("{path}:\n{explanation}");
+            } else {
+                eprintln!("{path}: {explanation}");
+            }
+        }
+    }
+    if !failures.is_empty() {
+        return Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            format!(
+                "{} of {} case(s) failed; rerun with --bless to accept the generated files",
+                failures.len(),
+                cases.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn run_watch(args: WatchArgs) -> Result<(), clap::Error> {
+    let ConfigPath::File(config) = &args.path else {
+        return Err(clap::Error::raw(
+            ErrorKind::ValueValidation,
+            "Only config files can be watched",
+        ));
+    };
+    let root = args.scratch.as_deref().unwrap_or(&args.root);
+    let mut files = vec![PathBuf::from(config), root.join(".genify/props.toml")];
+    files.extend(args.props.props_files.iter().cloned());
+
+    // A failing first render is reported like later ones, so fixing the
+    // config while it is watched picks the fix up.
+    let mut previous = render_watched(&args, None)
+        .map_err(|err| eprintln!("{}", err.to_string().trim_end()))
+        .ok();
+    println!("Watching {} for changes...", args.path.label());
+    watch::watch(&files, Duration::from_millis(args.debounce), |changed| {
+        let changed = changed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>();
+        println!("\n[{}] changed, re-rendering", changed.join(", "));
+        match render_watched(&args, previous.as_ref()) {
+            Ok(rendered) => previous = Some(rendered),
+            Err(err) => eprintln!("{}", err.to_string().trim_end()),
+        }
     })
-    .and_then(genify::render_config_rules)
-    .and_then(|c| genify::extend_paths(c, Path::new(".")))
-    .and_then(generate_files)
-    {
+    .map_err(|err| clap::Error::raw(ErrorKind::Io, format!("Failed to watch files: {err}")))
+}
+
+/// Renders the watched config once, in memory against `--root` or applied into
+/// `--scratch`. The first render shows the whole diff, later ones only what
+/// changed since `previous`.
+fn render_watched(args: &WatchArgs, previous: Option<&Rendered>) -> Result<Rendered, clap::Error> {
+    if let Some(scratch) = &args.scratch {
+        watch::reset_scratch(scratch).map_err(|err| {
+            clap::Error::raw(
+                ErrorKind::Io,
+                format!("Failed to reset {}: {err}", scratch.display()),
+            )
+        })?;
+    }
+    let core = open_core(args.scratch.as_deref().unwrap_or(&args.root))?;
+    let (config, source) = load_config(&args.path)?;
+    let mut base = config.props.clone();
+    merge_props(
+        &mut base,
+        core.project_props(None)
+            .map_err(|err| report::core_error(err, None))?
+            .unwrap_or_default(),
+    );
+    let request = GenerationRequest {
+        config: Some(config_to_json(&config)?),
+        props: overrides_to_json(args.props.overrides(&base)?),
+        ..GenerationRequest::default()
+    };
+
+    let preview = core
+        .preview(request.clone())
+        .map_err(|err| report::core_error(err, Some(&source)))?;
+    for diagnostic in preview.warnings.iter().chain(&preview.errors) {
+        eprintln!("{}", report::diagnostic(diagnostic, Some(&source)));
+    }
+    if !preview.errors.is_empty() {
+        return Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            "Render failed because generation has errors",
+        ));
+    }
+    let rendered = Rendered::from(&preview);
+
+    if args.scratch.is_some() {
+        let output = core
+            .apply(ApplyRequest {
+                config: request.config.clone(),
+                props: request.props.clone(),
+                explicit_approval: true,
+                ..ApplyRequest::default()
+            })
+            .map_err(|err| report::core_error(err, Some(&source)))?;
+        println!("{}", output.summary);
+    }
+    match previous {
+        Some(previous) => match rendered.changes_since(previous) {
+            changes if changes.is_empty() => println!("No changes since the last render."),
+            changes => print!("{changes}"),
+        },
+        None if args.scratch.is_some() => {}
+        None => {
+            let output = core
+                .diff(request)
+                .map_err(|err| report::core_error(err, Some(&source)))?;
+            let options = DiffViewOptions {
+                color: args.color,
+                side_by_side: false,
+                pager: false,
+            };
+            diff_view::show(&output, options).map_err(|err| {
+                clap::Error::raw(ErrorKind::Io, format!("Failed to write diff: {err}"))
+            })?;
+        }
+    }
+    Ok(rendered)
+}
+
+fn run_capture(args: CaptureArgs) -> Result<(), clap::Error> {
+    let output = genify::capture::capture(&args.dir, &args.vars).map_err(|err| {
         clap::Error::raw(
             ErrorKind::InvalidValue,
-            format!("Failed to process config: {error:?}"),
+            format!("Failed to capture {}: {err}", args.dir.display()),
         )
-        .with_cmd(&cmd)
-        .exit();
-    };
+    })?;
+    for (path, reason) in &output.skipped {
+        eprintln!("skipped {path}: {reason}");
+    }
+    let format = args
+        .output
+        .as_deref()
+        .and_then(ConfigFormat::detect)
+        .unwrap_or(ConfigFormat::Toml);
+    write_output(args.output.as_deref(), &format.render(&output.config)?)
+}
+
+fn parse_var(raw: &str) -> Result<(String, String), String> {
+    raw.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{raw}`"))
+}
+
+fn run_convert(args: ConvertArgs) -> Result<(), clap::Error> {
+    let from = args
+        .from
+        .or_else(|| args.path.format())
+        .unwrap_or(ConfigFormat::Toml);
+    let to = args
+        .to
+        .or_else(|| args.output.as_deref().and_then(ConfigFormat::detect))
+        .unwrap_or(ConfigFormat::Toml);
+    let config = from.parse(&from.source_map(&args.path.label(), &read_file(&args.path)?))?;
+    write_output(args.output.as_deref(), &to.render(&config)?)
+}
+
+fn run_schema(args: SchemaArgs) -> Result<(), clap::Error> {
+    let schema =
+        serde_json::to_string_pretty(&genify::config_json_schema()).map_err(serialization_error)?;
+    write_output(args.output.as_deref(), &(schema + "\n"))
+}
+
+/// Writes `content` to `output`, or to stdout when no file is given.
+fn write_output(output: Option<&Path>, content: &str) -> Result<(), clap::Error> {
+    match output {
+        Some(output) => fs::write(output, content).map_err(|err| {
+            clap::Error::raw(
+                ErrorKind::Io,
+                format!("Failed to write {}: {err}", output.display()),
+            )
+        }),
+        None => {
+            print!("{content}");
+            Ok(())
+        }
+    }
+}
+
+fn read_roots(path: &Path) -> Result<Vec<PathBuf>, clap::Error> {
+    let raw = fs::read_to_string(path).map_err(|err| {
+        clap::Error::raw(
+            ErrorKind::ValueValidation,
+            format!("Failed to read {}: {err}", path.display()),
+        )
+    })?;
+    Ok(raw
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn open_core(root: &Path) -> Result<GenerationCore, clap::Error> {
+    GenerationCore::new(root).map_err(|err| report::core_error(err, None))
+}
+
+fn apply(
+    core: &GenerationCore,
+    request: ApplyRequest,
+    source: &SourceMap,
+) -> Result<(), clap::Error> {
+    let ApplyOutput {
+        summary,
+        hooks,
+        warnings,
+        errors,
+        ..
+    } = core
+        .apply(request)
+        .map_err(|err| report::core_error(err, Some(source)))?;
+
+    print_hooks(&hooks, "");
+    for diagnostic in warnings.iter().chain(&errors) {
+        eprintln!("{}", report::diagnostic(diagnostic, Some(source)));
+    }
+    if !errors.is_empty() {
+        return Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            summary.trim_end_matches('.'),
+        ));
+    }
+    println!("{summary}");
+    Ok(())
+}
+
+/// Prints each hook that ran with its exit status, followed by its output.
+fn print_hooks(hooks: &[HookReport], indent: &str) {
+    for hook in hooks {
+        let status = hook.status.map_or_else(
+            || "did not finish".to_string(),
+            |code| format!("exit {code}"),
+        );
+        println!(
+            "{indent}{} hook: {} ({status})",
+            hook.when.name(),
+            hook.command.join(" ")
+        );
+        for line in hook.stdout.lines() {
+            println!("{indent}  {line}");
+        }
+        for line in hook.stderr.lines() {
+            eprintln!("{indent}  {line}");
+        }
+    }
+}
+
+fn show_dry_run(
+    core: &GenerationCore,
+    request: GenerationRequest,
+    options: DiffViewOptions,
+    source: &SourceMap,
+) -> Result<(), clap::Error> {
+    let output = core
+        .diff(request)
+        .map_err(|err| report::core_error(err, Some(source)))?;
+
+    for diagnostic in output.warnings.iter().chain(&output.errors) {
+        eprintln!("{}", report::diagnostic(diagnostic, Some(source)));
+    }
+    if !output.errors.is_empty() {
+        return Err(clap::Error::raw(
+            ErrorKind::InvalidValue,
+            "Dry run failed because generation has errors",
+        ));
+    }
+
+    diff_view::show(&output, options)
+        .map_err(|err| clap::Error::raw(ErrorKind::Io, format!("Failed to write diff: {err}")))
 }
 
-fn parse_file(path: &ConfigPath) -> Result<genify::Config, clap::Error> {
+fn config_to_json(config: &genify::Config) -> Result<JsonValue, clap::Error> {
+    serde_json::to_value(config).map_err(serialization_error)
+}
+
+fn overrides_to_json(overrides: PropOverrides) -> Option<JsonValue> {
+    (!overrides.props.is_empty()).then(|| props_to_json(&overrides.props))
+}
+
+fn merge_props(props: &mut genify::Map, overrides: genify::Map) {
+    for (key, value) in overrides {
+        match props.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => *existing = value,
+            None => props.push((key, value)),
+        }
+    }
+}
+
+/// The props as the core reads them, with datetimes tagged so they do not
+/// reach it as strings.
+fn props_to_json(props: &genify::Map) -> JsonValue {
+    JsonValue::from(&genify::Value::Map(props.clone()))
+}
+
+fn serialization_error(err: serde_json::Error) -> clap::Error {
+    clap::Error::raw(
+        ErrorKind::InvalidValue,
+        format!("Failed to serialize config: {err}"),
+    )
+}
+
+fn read_file(path: &ConfigPath) -> Result<String, clap::Error> {
+    let fetch = FETCH.get_or_init(FetchArgs::default);
+    let invalid = |message: String| clap::Error::raw(ErrorKind::ValueValidation, message + "\n");
     let raw = match path {
         ConfigPath::File(p) => {
             let path = Path::new(p);
@@ -189,53 +1280,100 @@ fn parse_file(path: &ConfigPath) -> Result<genify::Config, clap::Error> {
                 .map_err(|_| clap::Error::raw(ErrorKind::ValueValidation, "Failed to read file"))?
         }
         ConfigPath::Http(url) => {
-            let client = Client::new();
-            client
-                .get(url.clone())
-                .send()
-                .and_then(|r| r.error_for_status()) // handle HTTP errors
-                .and_then(|r| r.text())
-                .map_err(|e| {
-                    clap::Error::raw(
-                        ErrorKind::ValueValidation,
-                        format!("Failed to fetch URL: {e}"),
-                    )
-                })?
+            return fetch::fetch_url(
+                url,
+                &cache::cache_dir(),
+                fetch.offline,
+                fetch.sha256.as_deref(),
+            )
+            .map_err(invalid);
+        }
+        ConfigPath::Git(source) => {
+            let file = source.file(&git_checkout(source)?).map_err(git_error)?;
+            fs::read_to_string(&file).map_err(|err| {
+                clap::Error::raw(
+                    ErrorKind::ValueValidation,
+                    format!("Failed to read {} from {}: {err}", source.path, source.repo),
+                )
+            })?
         }
     };
+    if let Some(expected) = &fetch.sha256 {
+        fetch::verify_sha256(&raw, expected).map_err(invalid)?;
+    }
+    Ok(raw)
+}
 
-    let config = genify::parse_toml(&raw).map_err(|err| {
-        clap::Error::raw(
-            ErrorKind::ValueValidation,
-            format!("Failed to parse TOML: {err:?}"),
-        )
-    })?;
-    Ok(config)
+/// The cached checkout of the commit `source` points at, fetched if needed.
+fn git_checkout(source: &GitSource) -> Result<PathBuf, clap::Error> {
+    let offline = FETCH.get().is_some_and(|fetch| fetch.offline);
+    source
+        .checkout(&cache::cache_dir(), offline)
+        .map_err(git_error)
+}
+
+fn git_error(message: String) -> clap::Error {
+    clap::Error::raw(
+        ErrorKind::ValueValidation,
+        format!("Failed to fetch git source: {message}\n"),
+    )
+}
+
+/// Reads and parses a config in the format its extension names (TOML by
+/// default), keeping its text to point diagnostics into.
+fn load_config(path: &ConfigPath) -> Result<(genify::Config, SourceMap), clap::Error> {
+    load_signed_config(path).map(|(config, source, _)| (config, source))
 }
 
-fn parse_props_json(raw: &str) -> Result<genify::Map, clap::Error> {
-    let parsed: JsonValue = serde_json::from_str(raw).map_err(|err| {
+/// [`load_config`], also checking the config's detached signature against the
+/// trust store and returning who signed it. Remote configs no trusted key
+/// signed are subject to the trust policy.
+fn load_signed_config(
+    path: &ConfigPath,
+) -> Result<(genify::Config, SourceMap, Signer), clap::Error> {
+    let invalid = |message: String| {
         clap::Error::raw(
-            ErrorKind::InvalidValue,
-            format!("Failed to parse props JSON: {err}"),
+            ErrorKind::ValueValidation,
+            format!("{}: {message}\n", path.label()),
         )
-    })?;
-    let object = parsed.as_object().ok_or_else(|| {
-        clap::Error::raw(ErrorKind::InvalidValue, "Props JSON must be a JSON object")
-    })?;
-
-    let mut props = Vec::with_capacity(object.len());
-    for (key, value) in object {
-        let converted = genify::Value::try_from(value.clone()).map_err(|err| {
-            clap::Error::raw(
-                ErrorKind::InvalidValue,
-                format!("Invalid value for \"{key}\": {err}"),
-            )
-        })?;
-        props.push((key.clone(), converted));
+    };
+    let format = path.format().unwrap_or(ConfigFormat::Toml);
+    let raw = read_file(path)?;
+    let store = TrustStore::load().map_err(invalid)?;
+    let signer = store
+        .signer(raw.as_bytes(), read_signature(path).as_deref())
+        .map_err(invalid)?;
+    if !matches!(path, ConfigPath::File(_)) {
+        store
+            .admit(&path.label(), &signer)
+            .map_err(|message| clap::Error::raw(ErrorKind::ValueValidation, message + "\n"))?;
     }
+    let source = format.source_map(&path.label(), &raw);
+    let config = format.parse(&source)?;
+    config
+        .check_genify_version()
+        .map_err(|err| invalid(err.to_string()))?;
+    Ok((config, source, signer))
+}
 
-    Ok(props)
+/// The detached signature published next to the config, if there is one.
+fn read_signature(path: &ConfigPath) -> Option<String> {
+    match path {
+        ConfigPath::File(p) => fs::read_to_string(format!("{p}{SIGNATURE_SUFFIX}")).ok(),
+        ConfigPath::Http(url) => {
+            let mut url = url.clone();
+            url.set_fragment(None);
+            url.set_path(&format!("{}{SIGNATURE_SUFFIX}", url.path()));
+            let offline = FETCH.get().is_some_and(|fetch| fetch.offline);
+            fetch::fetch_url(&url, &cache::cache_dir(), offline, None).ok()
+        }
+        ConfigPath::Git(source) => {
+            let file = source.file(&git_checkout(source).ok()?).ok()?;
+            let mut signature = file.into_os_string();
+            signature.push(SIGNATURE_SUFFIX);
+            fs::read_to_string(signature).ok()
+        }
+    }
 }
 
 fn parse_json_value(raw: &str) -> Result<genify::Value, String> {
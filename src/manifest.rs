@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Map;
+
+/// Location of the manifest relative to the generation root.
+pub const MANIFEST_PATH: &str = ".genify/manifest.toml";
+
+/// Directory holding a copy of the content genify generated for each tracked
+/// file, used as the base of three-way merges.
+pub const BASELINE_DIR: &str = ".genify/baseline";
+
+/// Directory holding the content each file genify changed, but did not
+/// create, had before genify first touched it. Updates apply the rules to
+/// that content again instead of to the already patched file.
+pub const ORIGINAL_DIR: &str = ".genify/original";
+
+/// Record of a generation run: where the config came from, the props it was
+/// rendered with and the content hash of every file genify produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub genify_version: String,
+    pub source: ManifestSource,
+    #[serde(default)]
+    pub props: toml::Table,
+    #[serde(default)]
+    pub files: BTreeMap<String, ManifestFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestSource {
+    pub location: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub hash: String,
+    /// The file did not exist before genify first wrote it, so genify owns its
+    /// whole content.
+    #[serde(default)]
+    pub created: bool,
+}
+
+impl Manifest {
+    pub fn new(source: ManifestSource) -> Self {
+        Self {
+            genify_version: env!("CARGO_PKG_VERSION").to_string(),
+            source,
+            props: toml::Table::new(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    pub fn parse(raw: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(raw)
+    }
+
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string_pretty(self)
+    }
+
+    pub fn props(&self) -> Map {
+        self.props
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone().into()))
+            .collect()
+    }
+
+    pub fn set_props(&mut self, props: &Map) {
+        self.props = props
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), crate::toml::to_toml_value(value)?)))
+            .collect();
+    }
+}
+
+/// Hashes content as `sha256:<hex>`.
+pub fn content_hash(content: &[u8]) -> String {
+    let digest = Sha256::digest(content);
+    let mut hash = String::with_capacity(7 + digest.len() * 2);
+    hash.push_str("sha256:");
+    for byte in digest {
+        hash.push_str(&format!("{byte:02x}"));
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut manifest = Manifest::new(ManifestSource {
+            location: "templates/service.toml".to_string(),
+            hash: Some(content_hash(b"config")),
+        });
+        manifest.set_props(&vec![
+            ("name".to_string(), Value::String("demo".to_string())),
+            (
+                "ports".to_string(),
+                Value::Array(vec![Value::Integer(80), Value::Integer(443)]),
+            ),
+        ]);
+        manifest.files.insert(
+            "src/main.rs".to_string(),
+            ManifestFile {
+                hash: content_hash(b"fn main() {}\n"),
+                created: true,
+            },
+        );
+
+        let raw = manifest.to_toml().expect("manifest should serialize");
+        let parsed = Manifest::parse(&raw).expect("manifest should parse");
+
+        assert_eq!(parsed, manifest);
+        assert_eq!(parsed.props()[0].1, Value::String("demo".to_string()));
+    }
+
+    #[test]
+    fn hashes_content_as_sha256() {
+        assert_eq!(
+            content_hash(b""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
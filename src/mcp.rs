@@ -30,8 +30,13 @@ pub enum ServerError {
     Runtime(#[from] std::io::Error),
 }
 
+/// Serves the tools over STDIO for `root`. Config hooks never run; use
+/// [`serve_core_stdio`] with a core that allows them to opt in.
 pub fn serve_stdio(root: impl AsRef<Path>, read_only: bool) -> Result<(), ServerError> {
-    let core = GenerationCore::with_read_only(root, read_only)?;
+    serve_core_stdio(GenerationCore::with_read_only(root, read_only)?)
+}
+
+pub fn serve_core_stdio(core: GenerationCore) -> Result<(), ServerError> {
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_io()
         .build()?;
@@ -77,6 +82,12 @@ impl GenifyMcpServer {
             })),
             "genify_apply" => Ok(self.tool_call(arguments, false, |core, args| {
                 let input: ApplyRequest = parse_arguments(args)?;
+                if !input.roots.is_empty() {
+                    let output = core.apply_roots(input).map_err(core_error_payload)?;
+                    let is_error = output.has_failures();
+                    let value = serde_json::to_value(output).map_err(serialization_error)?;
+                    return Ok((value, is_error));
+                }
                 let output = core.apply(input).map_err(core_error_payload)?;
                 let is_error = !output.errors.is_empty();
                 let value = serde_json::to_value(output).map_err(serialization_error)?;
@@ -206,12 +217,14 @@ fn serialization_error(err: serde_json::Error) -> JsonValue {
 }
 
 fn core_error_payload(err: CoreError) -> JsonValue {
-    json!({
-        "error": {
-            "kind": core_error_kind(&err),
-            "message": err.to_string()
-        }
-    })
+    let mut error = json!({
+        "kind": core_error_kind(&err),
+        "message": err.to_string()
+    });
+    if let Some(location) = err.location() {
+        error["location"] = json!(location);
+    }
+    json!({ "error": error })
 }
 
 fn core_error_kind(err: &CoreError) -> &'static str {
@@ -228,8 +241,15 @@ fn core_error_kind(err: &CoreError) -> &'static str {
         CoreError::CreateDirectory { .. } => "create_directory",
         CoreError::ParseToml { .. } => "parse_toml",
         CoreError::Render(_) => "render",
+        CoreError::MissingManifest { .. } => "missing_manifest",
+        CoreError::ParseManifest { .. } => "parse_manifest",
+        CoreError::SerializeManifest(_) => "serialize_manifest",
         CoreError::ApprovalRequired => "approval_required",
         CoreError::ReadOnly => "read_only",
+        CoreError::RootWithRoots => "root_with_roots",
+        CoreError::Git { .. } => "git",
+        CoreError::InvalidGlob { .. } => "invalid_glob",
+        CoreError::Located { source, .. } => core_error_kind(source),
     }
 }
 
@@ -262,7 +282,7 @@ fn tools() -> Vec<Tool> {
             "genify_diff",
             "Diff genify changes",
             tool_description(
-                "Render the JSON genify config in dry-run mode and return a unified diff.",
+                "Render the JSON genify config in dry-run mode and return a unified diff plus structured per-file hunks.",
             ),
             generation_input_schema(),
             Some(diff_output_schema()),
@@ -272,7 +292,7 @@ fn tools() -> Vec<Tool> {
             "genify_apply",
             "Apply genify changes",
             tool_description(
-                "Apply generated changes to disk. Requires explicit_approval=true or confirm_token=\"apply\".",
+                "Apply generated changes to disk. Requires explicit_approval=true or confirm_token=\"apply\". Pass roots to apply the same config to several directories and get a per-root report. Writes to paths the server protects are refused with ignored_path or protected_path errors.",
             ),
             apply_input_schema(),
             Some(apply_output_schema()),
@@ -291,7 +311,7 @@ fn tools() -> Vec<Tool> {
         tool(
             "genify_list_templates",
             "List genify templates",
-            "List genify config/template files discovered under the MCP root.",
+            "List genify config/template files discovered under the MCP root, skipping paths ignored by .gitignore or .genifyignore, with the names, descriptions and tags of genify-catalog.toml and the props each config requires.",
             no_input_schema(),
             Some(list_templates_output_schema()),
             ToolAnnotations::new().read_only(true).destructive(false),
@@ -334,6 +354,14 @@ fn generation_input_schema() -> JsonValue {
             "root": {
                 "type": "string",
                 "description": "Optional generation root, constrained to the MCP server --root."
+            },
+            "props": {
+                "type": "object",
+                "description": "Optional prop overrides applied over config.props and the root's .genify/props.toml."
+            },
+            "update": {
+                "type": "boolean",
+                "description": "Regenerate against the root's .genify/manifest.toml: files genify created and nobody edited are regenerated, edited files are kept."
             }
         },
         "required": ["config"]
@@ -341,101 +369,16 @@ fn generation_input_schema() -> JsonValue {
 }
 
 fn config_schema() -> JsonValue {
-    json!({
-        "type": "object",
-        "additionalProperties": false,
-                "description": "Inline genify config as JSON. Minimum: {\"rules\":[{\"type\":\"replace\",\"path\":\"src/application.rs\",\"replace\":\"old text\",\"content\":\"new text\"}]}",
-        "properties": {
-            "props": {
-                "type": "object",
-                "description": "Optional template props used by Tera expressions in paths and content."
-            },
-            "rules": {
-                "type": "array",
-                "description": "Generation rules. Supported types: write, delete, rename, move, copy, mkdir, chmod, append, append_once, prepend, insert_before, insert_after, replace, replace_or_append, managed_block.",
-                "items": {
-                    "type": "object",
-                    "additionalProperties": false,
-                    "properties": {
-                        "type": {
-                            "type": "string",
-                            "enum": [
-                                "write",
-                                "delete",
-                                "rename",
-                                "move",
-                                "copy",
-                                "mkdir",
-                                "chmod",
-                                "append",
-                                "append_once",
-                                "prepend",
-                                "insert_before",
-                                "insert_after",
-                                "replace",
-                                "replace_or_append",
-                                "managed_block"
-                            ]
-                        },
-                        "path": {
-                            "type": "string"
-                        },
-                        "from": {
-                            "type": "string",
-                            "description": "Source path for rename, move, and copy."
-                        },
-                        "to": {
-                            "type": "string",
-                            "description": "Target path for rename, move, and copy."
-                        },
-                        "mode": {
-                            "type": "string",
-                            "description": "Unix chmod mode, for example \"755\" or \"644\"."
-                        },
-                        "if_exists": {
-                            "type": "string",
-                            "enum": [
-                                "overwrite",
-                                "error",
-                                "skip"
-                            ],
-                            "description": "Required for write. overwrite replaces an existing file, error fails when the target exists, skip leaves an existing file unchanged."
-                        },
-                        "marker": {
-                            "type": "string",
-                            "description": "Marker text for insert_before and insert_after."
-                        },
-                        "start_marker": {
-                            "type": "string",
-                            "description": "Start marker for managed_block."
-                        },
-                        "end_marker": {
-                            "type": "string",
-                            "description": "End marker for managed_block."
-                        },
-                        "replace": {
-                            "type": "string",
-                            "description": "Regex used for replace and replace_or_append rules."
-                        },
-                        "replace_all": {
-                            "type": "boolean",
-                            "description": "When true, replace every regex match. Defaults to false. When false, replace fails unless the match count equals expected_matches, default 1."
-                        },
-                        "expected_matches": {
-                            "type": "integer",
-                            "minimum": 0,
-                            "description": "Expected regex match count for replace and replace_or_append. Defaults to 1 when replace_all is false."
-                        },
-                        "content": {
-                            "type": "string"
-                        }
-                    },
-                    "required": ["type"]
-                }
-            }
-        },
-        "required": ["rules"]
-    })
+    let mut schema = crate::config_json_schema();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("$schema");
+        object.remove("title");
+        object.insert(
+            "description".to_string(),
+            json!("Inline genify config as JSON. Minimum: {\"rules\":[{\"type\":\"replace\",\"path\":\"src/application.rs\",\"replace\":\"old text\",\"content\":\"new text\"}]}"),
+        );
+    }
+    schema
 }
 
 fn apply_input_schema() -> JsonValue {
@@ -448,6 +391,32 @@ fn apply_input_schema() -> JsonValue {
         "type": "boolean",
         "description": "Set true to approve disk changes."
     });
+    schema["properties"]["manifest"] = json!({
+        "type": "object",
+        "description": "Record the run in the root's .genify/manifest.toml under this config source.",
+        "properties": {
+            "location": { "type": "string" },
+            "hash": { "type": "string" }
+        },
+        "required": ["location"]
+    });
+    schema["properties"]["roots"] = json!({
+        "type": "array",
+        "items": { "type": "string" },
+        "description": "Apply to each of these roots, constrained to the MCP server --root, instead of root, which must then be left out. Each root is sandboxed on its own."
+    });
+    schema["properties"]["fail_fast"] = json!({
+        "type": "boolean",
+        "description": "With roots, stop at the first failing root and report the rest as skipped."
+    });
+    schema["properties"]["git_branch"] = json!({
+        "type": "string",
+        "description": "Switch the root's git worktree to this branch first, creating it when missing, and stage the changed files."
+    });
+    schema["properties"]["git_commit"] = json!({
+        "type": "string",
+        "description": "Stage the changed files and commit only them with this message. Refused when those files have uncommitted changes."
+    });
     schema
 }
 
@@ -475,10 +444,40 @@ fn plan_output_schema() -> JsonValue {
         "properties": {
             "operations": { "type": "array" },
             "affected_paths": { "type": "array", "items": { "type": "string" } },
+            "hooks": {
+                "type": "array",
+                "description": "Commands the config runs before or after generation, only when the server allows hooks.",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "when": { "type": "string", "enum": ["before", "after"] },
+                        "command": { "type": "array", "items": { "type": "string" } },
+                        "cwd": { "type": "string" },
+                        "env": { "type": "object", "additionalProperties": { "type": "string" } }
+                    },
+                    "required": ["when", "command", "cwd"]
+                }
+            },
+            "props": {
+                "type": "array",
+                "description": "Resolved props with the layer that set each one: config, manifest, project (.genify/props.toml), or request.",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "value": {},
+                        "source": {
+                            "type": "string",
+                            "enum": ["config", "manifest", "project", "request"]
+                        }
+                    },
+                    "required": ["name", "value", "source"]
+                }
+            },
             "warnings": { "type": "array" },
             "errors": { "type": "array" }
         },
-        "required": ["operations", "affected_paths", "warnings", "errors"]
+        "required": ["operations", "affected_paths", "props", "warnings", "errors"]
     })
 }
 
@@ -487,24 +486,92 @@ fn diff_output_schema() -> JsonValue {
         "type": "object",
         "properties": {
             "diff": { "type": "string" },
+            "files": { "type": "array" },
             "summary": { "type": "object" },
             "warnings": { "type": "array" },
             "errors": { "type": "array" }
         },
-        "required": ["diff", "summary", "warnings", "errors"]
+        "required": ["diff", "files", "summary", "warnings", "errors"]
     })
 }
 
 fn apply_output_schema() -> JsonValue {
     json!({
         "type": "object",
+        "anyOf": [
+            {
+                "properties": {
+                    "changed_files": { "type": "array", "items": { "type": "string" } },
+                    "summary": { "type": "string" },
+                    "manifest": { "type": "string" },
+                    "hooks": hook_reports_schema(),
+                    "git": git_output_schema(),
+                    "warnings": { "type": "array" },
+                    "errors": { "type": "array" }
+                },
+                "required": ["changed_files", "summary", "warnings", "errors"]
+            },
+            {
+                "properties": {
+                    "roots": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "root": { "type": "string" },
+                                "status": {
+                                    "type": "string",
+                                    "enum": ["changed", "unchanged", "failed", "skipped"]
+                                },
+                                "changed_files": { "type": "array", "items": { "type": "string" } },
+                                "summary": { "type": "string" },
+                                "hooks": hook_reports_schema(),
+                                "git": git_output_schema(),
+                                "warnings": { "type": "array" },
+                                "errors": { "type": "array" }
+                            },
+                            "required": ["root", "status", "changed_files", "summary", "warnings", "errors"]
+                        }
+                    },
+                    "summary": { "type": "string" }
+                },
+                "required": ["roots", "summary"]
+            }
+        ]
+    })
+}
+
+fn hook_reports_schema() -> JsonValue {
+    json!({
+        "type": "array",
+        "description": "Hooks that ran, in order, with their exit status and captured output.",
+        "items": {
+            "type": "object",
+            "properties": {
+                "when": { "type": "string", "enum": ["before", "after"] },
+                "command": { "type": "array", "items": { "type": "string" } },
+                "cwd": { "type": "string" },
+                "status": { "type": ["integer", "null"] },
+                "success": { "type": "boolean" },
+                "stdout": { "type": "string" },
+                "stderr": { "type": "string" }
+            },
+            "required": ["when", "command", "cwd", "status", "success", "stdout", "stderr"]
+        }
+    })
+}
+
+fn git_output_schema() -> JsonValue {
+    json!({
+        "type": "object",
+        "description": "Branch, staged files and commit, when git_branch or git_commit was given.",
         "properties": {
-            "changed_files": { "type": "array", "items": { "type": "string" } },
-            "summary": { "type": "string" },
-            "warnings": { "type": "array" },
-            "errors": { "type": "array" }
+            "branch": { "type": "string" },
+            "created_branch": { "type": "boolean" },
+            "staged": { "type": "array", "items": { "type": "string" } },
+            "commit": { "type": "string" }
         },
-        "required": ["changed_files", "summary", "warnings", "errors"]
+        "required": ["created_branch", "staged"]
     })
 }
 
@@ -524,9 +591,10 @@ fn list_templates_output_schema() -> JsonValue {
     json!({
         "type": "object",
         "properties": {
-            "items": { "type": "array" }
+            "items": { "type": "array" },
+            "warnings": { "type": "array" }
         },
-        "required": ["items"]
+        "required": ["items", "warnings"]
     })
 }
 
@@ -0,0 +1,203 @@
+use std::ops::Range;
+
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+
+pub(crate) const CURRENT_LABEL: &str = "current";
+pub(crate) const GENERATED_LABEL: &str = "generated";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MergeResult {
+    pub content: String,
+    /// 1-based line ranges of the conflict blocks in `content`, markers included.
+    pub conflicts: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Current,
+    Generated,
+}
+
+#[derive(Debug, Clone)]
+struct Change {
+    side: Side,
+    base: Range<usize>,
+    lines: Range<usize>,
+}
+
+/// Line-based three-way merge of `current` and `generated`, both derived from
+/// `base`. Changes that overlap or touch are written as git-style conflict
+/// blocks with `current` first.
+pub(crate) fn merge3(base: &str, current: &str, generated: &str) -> MergeResult {
+    let base_lines = lines(base);
+    let current_lines = lines(current);
+    let generated_lines = lines(generated);
+
+    let mut changes = changes_for(Side::Current, &base_lines, &current_lines);
+    changes.extend(changes_for(Side::Generated, &base_lines, &generated_lines));
+    changes.sort_by_key(|change| (change.base.start, change.base.end));
+
+    let mut content = String::with_capacity(current.len().max(generated.len()));
+    let mut line_count = 0;
+    let mut conflicts = Vec::new();
+    let mut cursor = 0;
+    let mut index = 0;
+    while index < changes.len() {
+        let start = changes[index].base.start;
+        let mut end = changes[index].base.end;
+        let mut chunk_end = index + 1;
+        while chunk_end < changes.len() && changes[chunk_end].base.start <= end {
+            end = end.max(changes[chunk_end].base.end);
+            chunk_end += 1;
+        }
+        let chunk = &changes[index..chunk_end];
+        index = chunk_end;
+
+        push_lines(&mut content, &mut line_count, &base_lines[cursor..start]);
+        cursor = end;
+
+        let ours = resolve(
+            chunk,
+            Side::Current,
+            start..end,
+            &base_lines,
+            &current_lines,
+        );
+        let theirs = resolve(
+            chunk,
+            Side::Generated,
+            start..end,
+            &base_lines,
+            &generated_lines,
+        );
+        let touched_current = chunk.iter().any(|change| change.side == Side::Current);
+        let touched_generated = chunk.iter().any(|change| change.side == Side::Generated);
+        if !touched_generated || ours == theirs {
+            push_lines(&mut content, &mut line_count, &ours);
+        } else if !touched_current {
+            push_lines(&mut content, &mut line_count, &theirs);
+        } else {
+            let first = line_count + 1;
+            push_marker(
+                &mut content,
+                &mut line_count,
+                &format!("<<<<<<< {CURRENT_LABEL}"),
+            );
+            push_lines(&mut content, &mut line_count, &ours);
+            push_marker(&mut content, &mut line_count, "=======");
+            push_lines(&mut content, &mut line_count, &theirs);
+            push_marker(
+                &mut content,
+                &mut line_count,
+                &format!(">>>>>>> {GENERATED_LABEL}"),
+            );
+            conflicts.push(first..line_count + 1);
+        }
+    }
+    push_lines(&mut content, &mut line_count, &base_lines[cursor..]);
+
+    MergeResult { content, conflicts }
+}
+
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn changes_for(side: Side, base: &[&str], other: &[&str]) -> Vec<Change> {
+    capture_diff_slices(Algorithm::Myers, base, other)
+        .into_iter()
+        .filter(|op| !matches!(op, DiffOp::Equal { .. }))
+        .map(|op| Change {
+            side,
+            base: op.old_range(),
+            lines: op.new_range(),
+        })
+        .collect()
+}
+
+/// Returns how `side` rewrote `range` of the base.
+fn resolve<'a>(
+    chunk: &[Change],
+    side: Side,
+    range: Range<usize>,
+    base: &[&'a str],
+    side_lines: &[&'a str],
+) -> Vec<&'a str> {
+    let mut resolved = Vec::new();
+    let mut position = range.start;
+    for change in chunk.iter().filter(|change| change.side == side) {
+        resolved.extend_from_slice(&base[position..change.base.start]);
+        resolved.extend_from_slice(&side_lines[change.lines.clone()]);
+        position = change.base.end;
+    }
+    resolved.extend_from_slice(&base[position..range.end]);
+    resolved
+}
+
+fn push_lines(content: &mut String, line_count: &mut usize, lines: &[&str]) {
+    for line in lines {
+        ensure_newline(content);
+        content.push_str(line);
+        *line_count += 1;
+    }
+}
+
+fn push_marker(content: &mut String, line_count: &mut usize, marker: &str) {
+    ensure_newline(content);
+    content.push_str(marker);
+    content.push('\n');
+    *line_count += 1;
+}
+
+/// A side may end without a trailing newline; keep markers on their own line.
+fn ensure_newline(content: &mut String) {
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_non_overlapping_changes_from_both_sides() {
+        let base = "a\nb\nc\nd\ne\n";
+        let current = "a\nB\nc\nd\ne\n";
+        let generated = "a\nb\nc\nd\nE\n";
+
+        let merged = merge3(base, current, generated);
+
+        assert_eq!(merged.content, "a\nB\nc\nd\nE\n");
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn takes_identical_changes_once() {
+        let merged = merge3("a\nb\n", "a\nx\n", "a\nx\n");
+
+        assert_eq!(merged.content, "a\nx\n");
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn marks_overlapping_changes_as_conflicts() {
+        let base = "a\nb\nc\n";
+        let current = "a\nmine\nc\n";
+        let generated = "a\ntheirs\nc\n";
+
+        let merged = merge3(base, current, generated);
+
+        assert_eq!(
+            merged.content,
+            "a\n<<<<<<< current\nmine\n=======\ntheirs\n>>>>>>> generated\nc\n"
+        );
+        assert_eq!(merged.conflicts, vec![2..7]);
+    }
+
+    #[test]
+    fn empty_base_conflicts_unless_sides_match() {
+        assert_eq!(merge3("", "same\n", "same\n").content, "same\n");
+        assert_eq!(merge3("", "one\n", "two\n").conflicts.len(), 1);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{Error, Map, Value};
+
+/// Tera keywords and built-in names that can never refer to a prop.
+const KEYWORDS: &[&str] = &[
+    "and",
+    "as",
+    "block",
+    "break",
+    "continue",
+    "elif",
+    "else",
+    "endblock",
+    "endfilter",
+    "endfor",
+    "endif",
+    "endmacro",
+    "endraw",
+    "extends",
+    "false",
+    "False",
+    "filter",
+    "for",
+    "if",
+    "import",
+    "in",
+    "include",
+    "is",
+    "loop",
+    "macro",
+    "not",
+    "or",
+    "raw",
+    "self",
+    "set",
+    "set_global",
+    "super",
+    "true",
+    "True",
+];
+
+/// Returns prop indices in an order where every prop comes after the props
+/// its templates reference. Independent props keep their declaration order.
+pub(crate) fn render_order(props: &Map) -> Result<Vec<usize>, Error> {
+    let index: HashMap<&str, usize> = props
+        .iter()
+        .enumerate()
+        .map(|(position, (key, _))| (key.as_str(), position))
+        .collect();
+    let dependencies: Vec<BTreeSet<usize>> = props
+        .iter()
+        .map(|(_, value)| {
+            let mut names = BTreeSet::new();
+            collect_references(value, &mut names);
+            names
+                .iter()
+                .filter_map(|name| index.get(name.as_str()).copied())
+                .collect()
+        })
+        .collect();
+
+    let mut order = Vec::with_capacity(props.len());
+    let mut done = vec![false; props.len()];
+    while order.len() < props.len() {
+        let ready = (0..props.len()).find(|&position| {
+            !done[position]
+                && dependencies[position]
+                    .iter()
+                    .all(|&dependency| done[dependency])
+        });
+        match ready {
+            Some(position) => {
+                done[position] = true;
+                order.push(position);
+            }
+            None => {
+                let cycle = find_cycle(&dependencies, &done)
+                    .into_iter()
+                    .map(|position| props[position].0.clone())
+                    .collect();
+                return Err(Error::PropsCycle(cycle));
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// Follows unresolved dependencies from the first pending prop until one
+/// repeats; the path from its first visit is the cycle, closed with itself.
+fn find_cycle(dependencies: &[BTreeSet<usize>], done: &[bool]) -> Vec<usize> {
+    let Some(mut current) = (0..done.len()).find(|&position| !done[position]) else {
+        return Vec::new();
+    };
+    let mut path = Vec::new();
+    loop {
+        if let Some(start) = path.iter().position(|&visited| visited == current) {
+            let mut cycle = path.split_off(start);
+            cycle.push(current);
+            return cycle;
+        }
+        path.push(current);
+        match dependencies[current]
+            .iter()
+            .find(|&&dependency| !done[dependency])
+        {
+            Some(&next) => current = next,
+            None => return path,
+        }
+    }
+}
+
+pub(crate) fn collect_references(value: &Value, names: &mut BTreeSet<String>) {
+    match value {
+        Value::String(template) => references(template, names),
+        Value::Array(values) => {
+            for value in values {
+                collect_references(value, names);
+            }
+        }
+        Value::Map(map) => {
+            for (_, value) in map {
+                collect_references(value, names);
+            }
+        }
+        Value::Null
+        | Value::Integer(_)
+        | Value::Float(_)
+        | Value::Boolean(_)
+        | Value::Datetime(_) => {}
+    }
+}
+
+/// Collects the variable names a Tera template may read. Attribute names,
+/// filters, tests, function names, keyword arguments and names the template
+/// declares itself (`for` and `set` targets) are skipped; anything else that
+/// looks like an identifier counts, which may over-approximate but never
+/// misses a prop.
+pub(crate) fn references(template: &str, names: &mut BTreeSet<String>) {
+    let mut used = BTreeSet::new();
+    let mut declared = BTreeSet::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let close = match after.chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            _ => {
+                rest = after;
+                continue;
+            }
+        };
+        let body = &after[1..];
+        let end = body.find(close).unwrap_or(body.len());
+        rest = &body[(end + close.len()).min(body.len())..];
+        if close == "%}" && body[..end].trim_matches(['-', ' ', '\t', '\n']) == "raw" {
+            // Nothing up to `endraw` is template syntax.
+            rest = rest
+                .find("endraw")
+                .and_then(|raw_end| rest[raw_end..].find("%}").map(|tag| raw_end + tag + 2))
+                .map_or("", |raw_end| &rest[raw_end..]);
+            continue;
+        }
+        tag_references(&body[..end], &mut used, &mut declared);
+    }
+    names.extend(used.difference(&declared).cloned());
+}
+
+fn tag_references(tag: &str, names: &mut BTreeSet<String>, declared: &mut BTreeSet<String>) {
+    let chars: Vec<char> = tag.chars().collect();
+    let mut previous_token: Option<String> = None;
+    // Set after `is` (and a following `not`) so the next identifier is read
+    // as a test name.
+    let mut expects_test = false;
+    // Set after `for` until `in`, and after `set` for one identifier, so
+    // the identifiers in between are read as declarations.
+    let mut declaring = false;
+    let mut position = 0;
+    while position < chars.len() {
+        let char = chars[position];
+        if matches!(char, '"' | '\'' | '`') {
+            position += 1;
+            while position < chars.len() && chars[position] != char {
+                position += 1;
+            }
+            position += 1;
+            previous_token = Some(char.to_string());
+            continue;
+        }
+        if char.is_whitespace() {
+            position += 1;
+            continue;
+        }
+        if !(char.is_ascii_alphabetic() || char == '_') {
+            previous_token = Some(char.to_string());
+            position += 1;
+            continue;
+        }
+
+        let start = position;
+        while position < chars.len()
+            && (chars[position].is_ascii_alphanumeric() || chars[position] == '_')
+        {
+            position += 1;
+        }
+        let ident: String = chars[start..position].iter().collect();
+        let next = chars[position..]
+            .iter()
+            .position(|char| !char.is_whitespace())
+            .map(|offset| position + offset);
+        let is_call = next.is_some_and(|next| chars[next] == '(');
+        let is_keyword_argument = next.is_some_and(|next| {
+            chars[next] == '=' && chars.get(next + 1).is_none_or(|&char| char != '=')
+        });
+        let is_member = matches!(previous_token.as_deref(), Some("." | "|"));
+        let is_keyword = KEYWORDS.contains(&ident.as_str());
+        if declaring && !is_keyword {
+            declared.insert(ident.clone());
+        } else if !is_call && !is_keyword_argument && !is_member && !is_keyword && !expects_test {
+            names.insert(ident.clone());
+        }
+        declaring = match ident.as_str() {
+            "for" => true,
+            "set" | "set_global" => matches!(previous_token.as_deref(), None | Some("-")),
+            "in" => false,
+            _ => declaring && previous_token.as_deref() == Some("for"),
+        };
+        expects_test = match ident.as_str() {
+            "is" => true,
+            "not" => expects_test,
+            _ => false,
+        };
+        previous_token = Some(ident);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    #[test]
+    fn finds_variables_but_not_filters_attributes_or_strings() {
+        let mut names = BTreeSet::new();
+        references(
+            r#"{{ name | pascal_case }} {{ db.port }} {% if owner is not defined and enabled %}{{ "team" ~ suffix }}{% endif %} {{ range(end=count) }} {% for key, item in items %}{{ key }}{% endfor %} {%- set total = count %}{{ total }}"#,
+            &mut names,
+        );
+
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            vec!["count", "db", "enabled", "items", "name", "owner", "suffix"]
+        );
+    }
+
+    #[test]
+    fn skips_raw_blocks() {
+        let mut names = BTreeSet::new();
+        references(
+            "{% raw %}{{ literal }}{% if x %}{% endraw %}{{ after }} {%- raw -%}{{ unclosed }}",
+            &mut names,
+        );
+
+        assert_eq!(names.into_iter().collect::<Vec<_>>(), vec!["after"]);
+    }
+
+    #[test]
+    fn orders_props_after_their_references() {
+        let props = vec![
+            ("image".to_string(), string("{{ registry }}/{{ name }}")),
+            ("name".to_string(), string("demo")),
+            (
+                "registry".to_string(),
+                Value::Map(vec![("host".to_string(), string("{{ name }}.io"))]),
+            ),
+        ];
+
+        assert_eq!(
+            render_order(&props).expect("props are acyclic"),
+            vec![1, 2, 0]
+        );
+    }
+
+    #[test]
+    fn reports_the_props_in_a_cycle() {
+        let props = vec![
+            ("standalone".to_string(), string("ok")),
+            ("a".to_string(), string("{{ b }}")),
+            ("b".to_string(), string("{{ c }}")),
+            ("c".to_string(), string("{{ a }}")),
+        ];
+
+        let err = render_order(&props).expect_err("props are cyclic");
+
+        assert_eq!(
+            err.to_string(),
+            "props form a dependency cycle: a -> b -> c -> a"
+        );
+    }
+}
@@ -1,25 +1,178 @@
+use std::{collections::BTreeMap, fmt};
+
 use regex::Regex;
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings, json_schema};
 use serde::{
-    Deserialize, Serialize, Serializer,
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor},
     ser::{SerializeMap, SerializeSeq},
 };
 use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
 
-#[derive(Clone, Serialize, Debug)]
+/// A genify config. It serializes with props as a table in declaration order,
+/// so it round-trips through TOML, JSON and YAML alike. Datetimes are written
+/// as strings by serde; [`Config::to_toml`] keeps them as TOML datetimes.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, Default)]
+#[schemars(
+    title = "genify config",
+    description = "Props and the rules genify renders and applies to a project.",
+    deny_unknown_fields
+)]
 pub struct Config {
-    #[serde(default)]
+    /// What the config is for and who maintains it. Not used when generating.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    /// Template props used by Tera expressions in paths and content.
+    #[serde(
+        default,
+        serialize_with = "serialize_props",
+        deserialize_with = "deserialize_map"
+    )]
+    #[schemars(with = "std::collections::BTreeMap<String, Value>")]
     pub props: Map,
+    /// Generation rules, applied in order.
     #[serde(default)]
     pub rules: Vec<Rule>,
+    /// Commands run before the rules are applied or after the files are
+    /// written, in order. They only run when the caller allows hooks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<Hook>,
+    /// Formatters the content of generated files is piped through before it
+    /// is diffed or written. A file uses the first formatter whose glob
+    /// matches it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formatters: Vec<Formatter>,
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug)]
+impl Config {
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        crate::toml::to_toml_string(self)
+    }
+
+    /// Fails when `meta.min_genify_version` is newer than this genify.
+    pub fn check_genify_version(&self) -> Result<(), crate::Error> {
+        let Some(required) = self
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.min_genify_version.as_deref())
+        else {
+            return Ok(());
+        };
+        let current = env!("CARGO_PKG_VERSION");
+        let requirement = semver::VersionReq::parse(&format!(">={required}")).map_err(|err| {
+            crate::Error::Operation(format!(
+                "meta.min_genify_version `{required}` is not a version: {err}"
+            ))
+        })?;
+        let version = semver::Version::parse(current).expect("package version is semver");
+        if requirement.matches(&version) {
+            return Ok(());
+        }
+        Err(crate::Error::Operation(format!(
+            "config requires genify {required} or newer, but this is genify {current}; upgrade genify to use it"
+        )))
+    }
+}
+
+/// Describes a config for catalogs and template listings.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Version of the config itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Oldest genify release that can use the config, for example "0.0.4".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_genify_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// A command a config runs around generation, such as `cargo fmt` or
+/// `git init`. Its command, working directory and environment values are
+/// rendered with the props like rule paths are.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub when: HookWhen,
+    /// Program and arguments, run directly rather than through a shell.
+    pub command: Vec<String>,
+    /// Working directory relative to the root. Defaults to the root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Variables added to the environment genify runs with.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, JsonSchema, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookWhen {
+    /// Before any rule is applied.
+    Before,
+    /// After the files are written and the manifest is recorded.
+    After,
+}
+
+impl HookWhen {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Before => "before",
+            Self::After => "after",
+        }
+    }
+}
+
+/// Formats the generated files matching `glob`, such as `**/*.rs`.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Formatter {
+    /// Glob matched against paths relative to the root.
+    pub glob: String,
+    pub format_with: FormatWith,
+}
+
+/// A formatter that reads the content on stdin and writes the formatted
+/// content to stdout.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum FormatWith {
+    /// A built-in formatter: `rustfmt`, `gofmt`, `prettier`, `black`,
+    /// `shfmt` or `terraform`, or `auto` to pick one by file extension.
+    Builtin(String),
+    /// Program and arguments, run directly rather than through a shell. The
+    /// file's path relative to the root is in `$GENIFY_FORMAT_PATH`.
+    Command(Vec<String>),
+}
+
+/// JSON Schema of the config format, generated from [`Config`]. Subschemas
+/// are inlined so the schema can be embedded in other schemas as is.
+pub fn config_json_schema() -> JsonValue {
+    let generator = SchemaSettings::draft2020_12()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    generator.into_root_schema_for::<Config>().to_value()
+}
+
+#[derive(Clone, Deserialize, Serialize, JsonSchema, Debug)]
 #[serde(tag = "type", rename_all = "snake_case")]
+#[schemars(deny_unknown_fields)]
 pub enum Rule {
     Write {
         path: String,
         content: String,
+        /// What to do when the file already exists.
         if_exists: IfExists,
+        /// Write the content byte for byte instead of ending it with a single
+        /// newline.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        exact: bool,
     },
     Delete {
         path: String,
@@ -41,6 +194,7 @@ pub enum Rule {
     },
     Chmod {
         path: String,
+        /// Unix mode in octal, for example "755".
         mode: String,
     },
     Append {
@@ -67,21 +221,29 @@ pub enum Rule {
     },
     Replace {
         path: String,
+        /// Regex matched against the file content.
         #[serde(with = "serde_regex")]
+        #[schemars(with = "String")]
         replace: Regex,
         content: String,
+        /// Replace every match instead of exactly `expected_matches`.
         #[serde(default)]
         replace_all: bool,
+        /// Number of matches required when `replace_all` is false (default 1).
         #[serde(default)]
         expected_matches: Option<usize>,
     },
     ReplaceOrAppend {
         path: String,
+        /// Regex matched against the file content.
         #[serde(with = "serde_regex")]
+        #[schemars(with = "String")]
         replace: Regex,
         content: String,
+        /// Replace every match instead of exactly `expected_matches`.
         #[serde(default)]
         replace_all: bool,
+        /// Number of matches required when `replace_all` is false (default 1).
         #[serde(default)]
         expected_matches: Option<usize>,
     },
@@ -93,23 +255,59 @@ pub enum Rule {
     },
 }
 
-#[derive(Clone, Copy, Deserialize, Serialize, Debug, PartialEq, Eq)]
+impl Rule {
+    /// The rule's `type` tag.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Write { .. } => "write",
+            Self::Delete { .. } => "delete",
+            Self::Rename { .. } => "rename",
+            Self::Move { .. } => "move",
+            Self::Copy { .. } => "copy",
+            Self::Mkdir { .. } => "mkdir",
+            Self::Chmod { .. } => "chmod",
+            Self::Append { .. } => "append",
+            Self::AppendOnce { .. } => "append_once",
+            Self::Prepend { .. } => "prepend",
+            Self::InsertBefore { .. } => "insert_before",
+            Self::InsertAfter { .. } => "insert_after",
+            Self::Replace { .. } => "replace",
+            Self::ReplaceOrAppend { .. } => "replace_or_append",
+            Self::ManagedBlock { .. } => "managed_block",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, JsonSchema, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum IfExists {
+    /// Replace the existing file.
     Overwrite,
+    /// Fail when the file exists.
     Error,
+    /// Leave the existing file unchanged.
     Skip,
+    /// Three-way merge with the content genify generated last time.
+    Merge,
 }
 
 pub type Array = Vec<Value>;
 pub type Map = Vec<(String, Value)>;
 
+pub use toml::value::Datetime;
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum Value {
+    /// An unset prop. Templates see it as undefined, so `is defined` checks
+    /// and the `default` filter apply.
+    Null,
     String(String),
     Integer(i64),
     Float(f64),
     Boolean(bool),
+    /// A TOML date, time or offset datetime. JSON and templates see it as an
+    /// RFC 3339 string.
+    Datetime(Datetime),
     Array(Array),
     Map(Map),
 }
@@ -120,10 +318,12 @@ impl Serialize for Value {
         S: Serializer,
     {
         match self {
+            Value::Null => serializer.serialize_unit(),
             Value::String(v) => serializer.serialize_str(v),
             Value::Integer(v) => serializer.serialize_i64(*v),
             Value::Float(v) => serializer.serialize_f64(*v),
             Value::Boolean(v) => serializer.serialize_bool(*v),
+            Value::Datetime(v) => serializer.collect_str(v),
             Value::Array(vec) => {
                 let mut seq = serializer.serialize_seq(Some(vec.len()))?;
                 for e in vec {
@@ -131,17 +331,199 @@ impl Serialize for Value {
                 }
                 seq.end()
             }
-            Value::Map(vec) => {
-                let mut map = serializer.serialize_map(Some(vec.len()))?;
-                for (k, v) in vec {
-                    map.serialize_entry(k, v)?;
+            Value::Map(map) => serialize_map(map, serializer),
+        }
+    }
+}
+
+fn serialize_map<S>(map: &Map, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut state = serializer.serialize_map(Some(map.len()))?;
+    for (key, value) in map {
+        state.serialize_entry(key, value)?;
+    }
+    state.end()
+}
+
+/// Serializes config props with their datetimes tagged, so formats without
+/// a datetime type read them back as datetimes rather than strings.
+fn serialize_props<S>(map: &Map, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut state = serializer.serialize_map(Some(map.len()))?;
+    for (key, value) in map {
+        state.serialize_entry(key, &Tagged(value))?;
+    }
+    state.end()
+}
+
+/// A value serialized with [`DATETIME_TAG`] objects for its datetimes.
+struct Tagged<'a>(&'a Value);
+
+impl Serialize for Tagged<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Value::Datetime(v) => {
+                let mut state = serializer.serialize_map(Some(1))?;
+                state.serialize_entry(DATETIME_TAG, &v.to_string())?;
+                state.end()
+            }
+            Value::Array(vec) => {
+                let mut seq = serializer.serialize_seq(Some(vec.len()))?;
+                for e in vec {
+                    seq.serialize_element(&Tagged(e))?;
                 }
-                map.end()
+                seq.end()
             }
+            Value::Map(map) => serialize_props(map, serializer),
+            value => value.serialize(serializer),
         }
     }
 }
 
+fn deserialize_map<'de, D>(deserializer: D) -> Result<Map, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Map(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => Err(de::Error::custom("props must be a table")),
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+impl JsonSchema for Value {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Value".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "A prop value: string, number, boolean, array, table or null (unset). Strings always stay strings; a datetime is written as {\"$datetime\": \"<RFC 3339>\"}."
+        })
+    }
+}
+
+/// Key the `toml` crate uses to pass datetimes through serde.
+const TOML_DATETIME_FIELD: &str = "$__toml_private_datetime";
+
+/// Key of the single-entry JSON object that carries a datetime, since JSON
+/// has no datetime type and strings are never read as one.
+pub const DATETIME_TAG: &str = "$datetime";
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a prop value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Integer(value))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E>
+    where
+        E: de::Error,
+    {
+        i64::try_from(value)
+            .map(Value::Integer)
+            .map_err(|_| E::custom("number is out of range"))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Float(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_string()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A>(self, mut access: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut map = Map::with_capacity(access.size_hint().unwrap_or_default());
+        while let Some(key) = access.next_key::<String>()? {
+            if map.is_empty() && key == TOML_DATETIME_FIELD {
+                let raw: String = access.next_value()?;
+                return raw.parse().map(Value::Datetime).map_err(de::Error::custom);
+            }
+            let value = access.next_value()?;
+            // A tag alone in its object is a datetime; with other keys it is
+            // just a key.
+            if map.is_empty() && key == DATETIME_TAG {
+                let next = access.next_key::<String>()?;
+                if let (Value::String(raw), None) = (&value, &next) {
+                    return raw.parse().map(Value::Datetime).map_err(de::Error::custom);
+                }
+                map.push((key, value));
+                if let Some(key) = next {
+                    map.push((key, access.next_value()?));
+                }
+                continue;
+            }
+            map.push((key, value));
+        }
+        Ok(Value::Map(map))
+    }
+}
+
 impl TryFrom<JsonValue> for Value {
     type Error = String;
 
@@ -157,8 +539,42 @@ impl TryFrom<JsonValue> for Value {
                 }
                 Ok(Value::Array(result))
             }
-            JsonValue::Object(map) => map_from_json(map).map(Value::Map),
-            JsonValue::Null => Err("null is not supported".to_string()),
+            JsonValue::Object(map) => {
+                if map.len() == 1
+                    && let Some(JsonValue::String(raw)) = map.get(DATETIME_TAG)
+                {
+                    return raw
+                        .parse()
+                        .map(Value::Datetime)
+                        .map_err(|err| format!("invalid datetime `{raw}`: {err}"));
+                }
+                map_from_json(map).map(Value::Map)
+            }
+            JsonValue::Null => Ok(Value::Null),
+        }
+    }
+}
+
+/// The JSON [`Value::try_from`] reads back the same value from: datetimes
+/// are tagged with [`DATETIME_TAG`] instead of becoming strings.
+impl From<&Value> for JsonValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => JsonValue::Null,
+            Value::String(v) => JsonValue::String(v.clone()),
+            Value::Integer(v) => JsonValue::from(*v),
+            Value::Float(v) => JsonNumber::from_f64(*v).map_or(JsonValue::Null, JsonValue::Number),
+            Value::Boolean(v) => JsonValue::Bool(*v),
+            Value::Datetime(v) => JsonValue::Object(JsonMap::from_iter([(
+                DATETIME_TAG.to_string(),
+                v.to_string().into(),
+            )])),
+            Value::Array(vec) => JsonValue::Array(vec.iter().map(JsonValue::from).collect()),
+            Value::Map(map) => JsonValue::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), JsonValue::from(value)))
+                    .collect(),
+            ),
         }
     }
 }
@@ -220,8 +636,150 @@ mod tests {
     }
 
     #[test]
-    fn rejects_null_json_value() {
-        let error = Value::try_from(JsonValue::Null).expect_err("null is unsupported");
-        assert!(error.contains("null"));
+    fn keeps_datetime_like_json_strings_as_strings() {
+        let json = json!({
+            "optional": null,
+            "generated_on": "2024-05-01T10:30:00Z",
+            "day": "2024-05-01"
+        });
+
+        let value = Value::try_from(json.clone()).expect("map should be converted");
+
+        let Value::Map(props) = &value else {
+            panic!("expected a map");
+        };
+        // serde_json sorts object keys.
+        assert_eq!(props[0].1, Value::String("2024-05-01".to_string()));
+        assert_eq!(
+            props[1].1,
+            Value::String("2024-05-01T10:30:00Z".to_string())
+        );
+        assert_eq!(props[2].1, Value::Null);
+        assert_eq!(
+            serde_json::to_value(&value).expect("value should serialize"),
+            json
+        );
+    }
+
+    #[test]
+    fn round_trips_datetimes_through_tagged_json() {
+        let value = Value::Map(vec![
+            ("name".to_string(), Value::String("demo".to_string())),
+            (
+                "released".to_string(),
+                Value::Datetime(
+                    "2024-05-01T10:00:00Z"
+                        .parse()
+                        .expect("datetime should parse"),
+                ),
+            ),
+        ]);
+
+        let json = JsonValue::from(&value);
+
+        assert_eq!(
+            json["released"],
+            json!({ "$datetime": "2024-05-01T10:00:00Z" })
+        );
+        assert_eq!(
+            Value::try_from(json).expect("json should be converted"),
+            value
+        );
+        assert!(Value::try_from(json!({ "$datetime": "soon" })).is_err());
+    }
+
+    #[test]
+    fn reads_toml_datetimes_as_datetimes() {
+        let config: Config = toml::from_str(
+            "[props]\ngenerated_on = 2024-05-01T10:30:00Z\nlabel = \"2024-05-01\"\n",
+        )
+        .expect("config should deserialize");
+
+        assert!(matches!(config.props[0].1, Value::Datetime(_)));
+        assert_eq!(config.props[1].1, Value::String("2024-05-01".to_string()));
+        assert_eq!(
+            serde_json::to_value(&config.props[0].1).expect("value should serialize"),
+            json!("2024-05-01T10:30:00Z")
+        );
+    }
+
+    #[test]
+    fn deserializes_config_keeping_prop_order() {
+        let config: Config = serde_json::from_str(
+            r#"{"props": {"zone": "eu", "app": {"port": 8080}, "unset": null},
+                "rules": [{"type": "delete", "path": "old.txt"}]}"#,
+        )
+        .expect("config should deserialize");
+
+        assert_eq!(
+            config.props,
+            vec![
+                ("zone".to_string(), Value::String("eu".to_string())),
+                (
+                    "app".to_string(),
+                    Value::Map(vec![("port".to_string(), Value::Integer(8080))])
+                ),
+                ("unset".to_string(), Value::Null),
+            ]
+        );
+        assert_eq!(config.rules.len(), 1);
+        assert_eq!(
+            serde_json::to_string(&config).expect("config should serialize"),
+            r#"{"props":{"zone":"eu","app":{"port":8080},"unset":null},"rules":[{"type":"delete","path":"old.txt"}]}"#
+        );
+    }
+
+    #[test]
+    fn refuses_configs_that_need_a_newer_genify() {
+        let config = |min: &str| Config {
+            meta: Some(Meta {
+                min_genify_version: Some(min.to_string()),
+                ..Meta::default()
+            }),
+            ..Config::default()
+        };
+
+        assert!(Config::default().check_genify_version().is_ok());
+        assert!(config("0.0.1").check_genify_version().is_ok());
+        assert!(
+            config(env!("CARGO_PKG_VERSION"))
+                .check_genify_version()
+                .is_ok()
+        );
+        let err = config("999.0").check_genify_version().unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("config requires genify 999.0 or newer"),
+            "{err}"
+        );
+        assert!(config("soon").check_genify_version().is_err());
+    }
+
+    #[test]
+    fn json_schema_requires_fields_per_rule_type() {
+        let schema = config_json_schema();
+        let variants = schema["properties"]["rules"]["items"]["oneOf"]
+            .as_array()
+            .expect("rules should be a oneOf of rule types");
+        let required = |rule_type: &str| {
+            variants
+                .iter()
+                .find(|variant| variant["properties"]["type"]["const"] == rule_type)
+                .map(|variant| variant["required"].clone())
+                .expect("every rule type should have a schema")
+        };
+
+        assert_eq!(variants.len(), 15);
+        assert_eq!(
+            required("write"),
+            json!(["type", "path", "content", "if_exists"])
+        );
+        assert_eq!(required("append"), json!(["type", "path", "content"]));
+        assert_eq!(
+            required("replace"),
+            json!(["type", "path", "replace", "content"])
+        );
+        assert_eq!(schema["additionalProperties"], json!(false));
+        assert_eq!(schema["properties"]["props"]["type"], "object");
     }
 }
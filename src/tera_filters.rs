@@ -2,6 +2,18 @@ use convert_case::{Case, Casing};
 use std::{collections::HashMap, hash::BuildHasher};
 use tera::{Result, Tera, Value, to_value, try_get_value};
 
+/// The case filters and the case each converts to, in registration order.
+pub(crate) const CASE_FILTERS: [(&str, Case<'static>); 8] = [
+    ("pascal_case", Case::Pascal),
+    ("camel_case", Case::Camel),
+    ("kebab_case", Case::Kebab),
+    ("snake_case", Case::Snake),
+    ("title_case", Case::Title),
+    ("flat_case", Case::Flat),
+    ("cobol_case", Case::Cobol),
+    ("train_case", Case::Train),
+];
+
 pub fn register_all(tera: &mut Tera) {
     tera.register_filter("pascal_case", pascal_case);
     tera.register_filter("camel_case", camel_case);
@@ -1,34 +1,35 @@
-use serde::{Deserialize, Serialize};
-
 pub fn parse_toml(raw: &str) -> Result<crate::Config, toml::de::Error> {
-    Ok(Config::parse(raw)?.into())
-}
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub(crate) struct Config {
-    #[serde(default)]
-    pub props: toml::map::Map<String, toml::Value>,
-    #[serde(default)]
-    pub rules: Vec<crate::schema::Rule>,
+    toml::from_str(raw)
 }
 
-impl Config {
-    pub(crate) fn parse(raw: &str) -> Result<Self, toml::de::Error> {
-        toml::from_str(raw)
+/// Writes a config as TOML, keeping datetimes as TOML datetimes rather than
+/// the strings serde produces for them.
+pub(crate) fn to_toml_string(config: &crate::Config) -> Result<String, toml::ser::Error> {
+    let mut document = toml::Table::new();
+    if let Some(meta) = &config.meta {
+        document.insert("meta".to_string(), toml::Value::try_from(meta)?);
     }
-}
-
-impl From<Config> for crate::Config {
-    fn from(value: Config) -> Self {
-        Self {
-            props: value
-                .props
-                .into_iter()
-                .map(|(k, v)| (k, v.into()))
-                .collect(),
-            rules: value.rules,
-        }
+    let props: toml::Table = config
+        .props
+        .iter()
+        .filter_map(|(key, value)| Some((key.clone(), to_toml_value(value)?)))
+        .collect();
+    if !props.is_empty() {
+        document.insert("props".to_string(), toml::Value::Table(props));
     }
+    if !config.rules.is_empty() {
+        document.insert("rules".to_string(), toml::Value::try_from(&config.rules)?);
+    }
+    if !config.hooks.is_empty() {
+        document.insert("hooks".to_string(), toml::Value::try_from(&config.hooks)?);
+    }
+    if !config.formatters.is_empty() {
+        document.insert(
+            "formatters".to_string(),
+            toml::Value::try_from(&config.formatters)?,
+        );
+    }
+    toml::to_string_pretty(&document)
 }
 
 impl From<toml::Value> for crate::Value {
@@ -38,7 +39,7 @@ impl From<toml::Value> for crate::Value {
             toml::Value::Integer(v) => crate::Value::Integer(v),
             toml::Value::Float(v) => crate::Value::Float(v),
             toml::Value::Boolean(v) => crate::Value::Boolean(v),
-            toml::Value::Datetime(datetime) => crate::Value::String(datetime.to_string()),
+            toml::Value::Datetime(datetime) => crate::Value::Datetime(datetime),
             toml::Value::Array(vec) => {
                 crate::Value::Array(vec.into_iter().map(|v| v.into()).collect())
             }
@@ -48,3 +49,24 @@ impl From<toml::Value> for crate::Value {
         }
     }
 }
+
+/// TOML has no null, so `Value::Null` has no TOML value and is left out of
+/// arrays and tables; reading it back yields an absent, undefined prop.
+pub(crate) fn to_toml_value(value: &crate::Value) -> Option<toml::Value> {
+    Some(match value {
+        crate::Value::Null => return None,
+        crate::Value::String(v) => toml::Value::String(v.clone()),
+        crate::Value::Integer(v) => toml::Value::Integer(*v),
+        crate::Value::Float(v) => toml::Value::Float(*v),
+        crate::Value::Boolean(v) => toml::Value::Boolean(*v),
+        crate::Value::Datetime(v) => toml::Value::Datetime(*v),
+        crate::Value::Array(vec) => {
+            toml::Value::Array(vec.iter().filter_map(to_toml_value).collect())
+        }
+        crate::Value::Map(map) => toml::Value::Table(
+            map.iter()
+                .filter_map(|(k, v)| Some((k.clone(), to_toml_value(v)?)))
+                .collect(),
+        ),
+    })
+}
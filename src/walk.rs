@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+
+use ignore::{Match, WalkBuilder, gitignore::Gitignore, gitignore::GitignoreBuilder};
+
+/// Lists paths genify leaves alone, in `.gitignore` syntax. Unlike
+/// `.gitignore` files, it counts outside git repositories too.
+pub const IGNORE_FILE: &str = ".genifyignore";
+
+/// Walks `root` in file name order, hidden files included, leaving out the
+/// directories named in `skip_dirs` and whatever `.gitignore` files, git's
+/// excludes or an [`IGNORE_FILE`] ignore.
+pub(crate) fn walker(root: &Path, skip_dirs: &'static [&'static str]) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            let skipped = entry.depth() > 0
+                && entry.file_type().is_some_and(|kind| kind.is_dir())
+                && skip_dirs.iter().any(|dir| entry.file_name() == *dir);
+            !skipped
+        });
+    builder
+}
+
+/// Tells whether a path under `root` is ignored the way [`walker`] would
+/// leave it out, without walking to it, so the path need not exist.
+#[derive(Debug, Clone)]
+pub(crate) struct IgnoredPaths {
+    root: PathBuf,
+    /// The git worktree around the root; `.gitignore` files only count
+    /// inside one.
+    worktree: Option<PathBuf>,
+}
+
+impl IgnoredPaths {
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            worktree: root
+                .ancestors()
+                .find(|dir| dir.join(".git").exists())
+                .map(Path::to_path_buf),
+        }
+    }
+
+    pub(crate) fn is_ignored(&self, path: &Path) -> bool {
+        let top = self.worktree.as_deref().unwrap_or(&self.root);
+        let Ok(relative) = path.strip_prefix(top) else {
+            return false;
+        };
+
+        // From the lowest precedence to the highest: the last match wins.
+        let mut matchers = Vec::new();
+        if self.worktree.is_some() {
+            matchers.push((Gitignore::global().0, top.to_path_buf()));
+            let mut exclude = GitignoreBuilder::new(top);
+            exclude.add(top.join(".git/info/exclude"));
+            matchers.push((
+                exclude.build().unwrap_or_else(|_| Gitignore::empty()),
+                top.to_path_buf(),
+            ));
+        }
+        let mut dir = top.to_path_buf();
+        let mut components = relative.parent().into_iter().flat_map(Path::components);
+        loop {
+            if self.worktree.is_some() {
+                matchers.push((Gitignore::new(dir.join(".gitignore")).0, dir.clone()));
+            }
+            matchers.push((Gitignore::new(dir.join(IGNORE_FILE)).0, dir.clone()));
+            let Some(component) = components.next() else {
+                break;
+            };
+            dir.push(component);
+        }
+
+        let mut ignored = false;
+        for (matcher, dir) in &matchers {
+            let Ok(relative) = path.strip_prefix(dir) else {
+                continue;
+            };
+            match matcher.matched_path_or_any_parents(relative, false) {
+                Match::Ignore(_) => ignored = true,
+                Match::Whitelist(_) => ignored = false,
+                Match::None => {}
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn ignores_paths_like_the_walker() {
+        let root = std::env::temp_dir().join(format!("genify-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join(".git")).expect("test dir should be created");
+        fs::create_dir_all(root.join("web/node_modules/pkg")).expect("test dir should be created");
+        fs::create_dir_all(root.join("vendor")).expect("test dir should be created");
+        fs::write(root.join(".gitignore"), "node_modules/\n*.log\n")
+            .expect("test file should be written");
+        fs::write(root.join("web/.gitignore"), "!keep.log\n").expect("test file should be written");
+        fs::write(root.join(IGNORE_FILE), "vendor/\n").expect("test file should be written");
+        fs::write(root.join("web/node_modules/pkg/index.js"), "")
+            .expect("test file should be written");
+        fs::write(root.join("web/app.js"), "").expect("test file should be written");
+        fs::write(root.join("vendor/lib.rs"), "").expect("test file should be written");
+
+        let walked: Vec<_> = walker(&root, &[".git"])
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+            .map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(&root)
+                    .expect("entry should be under the root")
+                    .to_path_buf()
+            })
+            .collect();
+        assert_eq!(
+            walked,
+            vec![
+                PathBuf::from(".genifyignore"),
+                PathBuf::from(".gitignore"),
+                PathBuf::from("web/.gitignore"),
+                PathBuf::from("web/app.js"),
+            ]
+        );
+
+        let ignored = IgnoredPaths::new(&root);
+        assert!(ignored.is_ignored(&root.join("web/node_modules/new/file.js")));
+        assert!(ignored.is_ignored(&root.join("vendor/new.rs")));
+        assert!(ignored.is_ignored(&root.join("debug.log")));
+        assert!(!ignored.is_ignored(&root.join("web/keep.log")));
+        assert!(!ignored.is_ignored(&root.join("web/app.js")));
+        let _ = fs::remove_dir_all(root);
+    }
+}